@@ -0,0 +1,89 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// Share of rounds that come with an event
+const EVENT_CHANCE: f64 = 0.5;
+pub const GOLD_RUSH_YIELD: f32 = 2.0; // Multiplies every swing
+pub const CAVE_IN_STUN: Duration = Duration::from_secs(10);
+pub const TAX_SHARE: f32 = 0.1; // Of everyone's unspent gold
+pub const CHARITY_DRIVE_CREDIT: f32 = 1.5; // Multiplies every donation
+
+// Modifier that changes the rules for one round
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundEvent {
+    GoldRush,
+    CaveIn, // One miner can't mine for the first seconds of the round
+    TaxCollector,
+    CharityDrive,
+}
+
+impl RoundEvent {
+    pub const ALL: [RoundEvent; 4] = [
+        RoundEvent::GoldRush,
+        RoundEvent::CaveIn,
+        RoundEvent::TaxCollector,
+        RoundEvent::CharityDrive,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RoundEvent::GoldRush => "Gold Rush",
+            RoundEvent::CaveIn => "Cave-In",
+            RoundEvent::TaxCollector => "Tax Collector",
+            RoundEvent::CharityDrive => "Charity Drive",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            RoundEvent::GoldRush => "Every swing mines double this round",
+            RoundEvent::CaveIn => "A cave-in stops one miner for 10 seconds",
+            RoundEvent::TaxCollector => "Everyone pays 10% of their gold",
+            RoundEvent::CharityDrive => "Donations count 1.5x this round",
+        }
+    }
+
+    // Picks the event, if any, for a round. The same seed always gives the same
+    // events in the same rounds, so a match can be replayed.
+    pub fn roll(rng: &mut StdRng) -> Option<RoundEvent> {
+        if rng.gen_bool(EVENT_CHANCE) {
+            Some(RoundEvent::ALL[rng.gen_range(0..RoundEvent::ALL.len())])
+        } else {
+            None
+        }
+    }
+}
+
+// Random numbers for everything an event decides in one round of a seeded match
+pub fn round_rng(seed: u64, round: usize) -> StdRng {
+    StdRng::seed_from_u64(seed ^ (round as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(seed: u64, rounds: usize) -> Vec<Option<RoundEvent>> {
+        (1..=rounds).map(|round| RoundEvent::roll(&mut round_rng(seed, round))).collect()
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_events_in_the_same_rounds() {
+        assert_eq!(events(1234, 30), events(1234, 30));
+        assert_ne!(events(1234, 30), events(1235, 30));
+    }
+
+    #[test]
+    fn every_event_comes_up_and_so_do_quiet_rounds() {
+        let events = events(99, 200);
+        assert!(events.contains(&None));
+        for event in RoundEvent::ALL {
+            assert!(events.contains(&Some(event)), "no {} in 200 rounds", event.name());
+        }
+        let share = events.iter().filter(|event| event.is_some()).count() as f64 / events.len() as f64;
+        assert!((share - EVENT_CHANCE).abs() < 0.15);
+    }
+}
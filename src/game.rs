@@ -0,0 +1,1471 @@
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::events::{self, RoundEvent, CAVE_IN_STUN, CHARITY_DRIVE_CREDIT, GOLD_RUSH_YIELD, TAX_SHARE};
+use crate::items::{Item, CLAIM_JUMP_SHARE, DYNAMITE_STUN, INFORMANT_REVEAL, SPYGLASS_REVEAL};
+use crate::market::{Market, Ore};
+use crate::miner::{Miner, MinerType, COMBO_WINDOW, LOAN_DEFAULT_DAMAGE, LOAN_INTEREST, LOAN_ROUNDS, MAX_DAMAGE_REDUCTION, MAX_HEALTH_UPGRADES};
+use crate::perks;
+use crate::reserve::Reserve;
+use crate::rules::{AuctionFormat, BotDifficulty, MatchRules, ReserveMode, LAST_STANDING_MAX_ROUNDS, SUDDEN_DEATH_ROUND};
+use crate::tech::{self, Stats};
+use crate::yields;
+
+// Once every human is out the rest of the match plays this much faster
+pub const ELIMINATED_SPEEDUP: u32 = 4;
+const FAST_FORWARD_STEP: Duration = Duration::from_millis(25);
+const FAST_FORWARD_ROUNDS: usize = 100; // Most rounds `fast_forward` plays out before calling the match
+const ACTIVITY_LOG_LIMIT: usize = 30; // Older entries are dropped
+const BOT_SAVING_CUTOFF: f32 = 10.0; // Seconds left in a round when bots stop saving for items
+const BOT_DEPOSIT_CUTOFF: f32 = 2.0; // Seconds left in a round when bots bank their spare gold
+const BOT_WITHDRAW_WINDOW: f32 = 3.0; // Seconds into a round in which bots take their deposits out
+pub const DRAFT_DURATION: Duration = Duration::from_secs(20); // Then unpicked cards are picked automatically
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameState {
+    Draft, // Picking perk cards before the round starts
+    Playing,
+    RoundEnd,
+    GameOver,
+}
+
+// Something a human asks their miner to do. The same actions come from the
+// local UI and, over the network, from remote players.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    Research { node: usize }, // Index into the tech tree
+    BuyHeal,
+    BuyShield,
+    UpgradeMaxHealth,
+    Donate { amount: f32 },
+    DonateAll,
+    UseItem { item: Item, target: Option<usize> }, // Target is a miner index
+    Sell { ore: Ore }, // Every whole unit of one ore
+    SellAll,
+    PickPerk { card: usize }, // Position in the miner's perk offer
+    EndTurn, // Commits this turn's orders in a turn-based match
+    Deposit { amount: f32 },
+    Withdraw { amount: f32 },
+    Borrow { amount: f32 },
+    Repay { amount: f32 }, // Capped at what is owed
+    Strike, // Strikes the rock by hand, in real time only
+    Continue, // Ready for the next round
+    Restart, // Ready for a new game
+}
+
+// The rules-level state of a match, independent of any window or connection.
+// The local UI and the dedicated server both drive it through `tick` and `apply`,
+// and it is what gets sent to clients as a snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Game {
+    pub rules: MatchRules,
+    pub miners: Vec<Miner>, // Human seats first, in seat order, then bots
+    pub current_round: usize,
+    pub round_elapsed: Duration, // Also times the draft
+    pub game_state: GameState,
+    pub round_results: Option<Vec<(usize, f32)>>, // (miner_index, donated_gold)
+    pub round_winners: Vec<usize>, // Miner index ranked #1 in each finished round
+    pub activity: Vec<LogEntry>, // Oldest first
+    pub market: Market,
+    pub seed: u64, // Decides the round events
+    pub event: Option<RoundEvent>, // Modifier for the current round
+    pub pending_items: Vec<(usize, Item, usize)>, // (user, item, target) held back until a turn-based turn resolves
+    pub common_deposit: Option<Reserve>, // What everyone mines from when the reserves are shared
+}
+
+// What one miner can make out about another. With fog of war on, only health and a
+// rough tier show unless the rival has been scouted. The UI and the bot logic both
+// read rivals through this, so bots know no more than a player would.
+#[derive(Debug, Clone, Copy)]
+pub struct RivalView {
+    pub health: i32,
+    pub max_health: i32,
+    pub shielded: bool,
+    pub tier: usize, // See `Miner::tier`
+    pub scouted: bool, // Revealed by a scouting item
+    pub gold: Option<f32>,
+    pub ore: Option<[f32; 4]>, // Whole units
+    pub donation: Option<f32>, // This round
+    pub stats: Option<Stats>,
+}
+
+// One line of the activity log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub miner: Option<usize>, // Who acted, if it wasn't the game itself
+    pub target: Option<usize>, // Who it was done to
+    pub message: String,
+    pub fogged: Option<String>, // The message without what the fog of war hides, for everyone else
+}
+
+impl LogEntry {
+    // The line as it reads to the given seats: hidden details only show to those involved
+    pub fn text_for(&self, seats: &[usize]) -> &str {
+        let involved = seats.iter().any(|seat| self.miner == Some(*seat) || self.target == Some(*seat));
+        match &self.fogged {
+            Some(fogged) if !involved => fogged,
+            _ => &self.message,
+        }
+    }
+}
+
+// Humans first so a seat number is also that human's miner index
+fn spawn_miners(rules: &MatchRules) -> Vec<Miner> {
+    let mut miners = Vec::new();
+
+    for seat in 0..rules.human_count {
+        let name = if rules.is_hot_seat() {
+            format!("Player {}", seat + 1)
+        } else {
+            "You (Player)".to_string()
+        };
+        miners.push(Miner::new(MinerType::Human(seat), name));
+    }
+
+    for i in 0..rules.bot_count {
+        miners.push(Miner::new(MinerType::Bot, format!("Bot #{}", i + 1)));
+    }
+
+    miners
+}
+
+impl Game {
+    pub fn new(rules: MatchRules) -> Self {
+        let miners = spawn_miners(&rules);
+        Game::with_miners(rules, miners)
+    }
+
+    // A match whose human seats go by the given names, in seat order
+    pub fn named(rules: MatchRules, names: &[String]) -> Self {
+        let mut miners = spawn_miners(&rules);
+        for (miner, name) in miners.iter_mut().filter(|miner| !miner.is_bot()).zip(names) {
+            miner.name = name.clone();
+        }
+        Game::with_miners(rules, miners)
+    }
+
+    fn with_miners(rules: MatchRules, mut miners: Vec<Miner>) -> Self {
+        let reserve = Reserve::for_rules(&rules);
+        let shared = rules.reserves == ReserveMode::Shared;
+        for miner in &mut miners {
+            miner.reserve = if shared { None } else { reserve };
+        }
+
+        let mut game = Game {
+            miners,
+            seed: rules.seed.unwrap_or_else(rand::random),
+            rules,
+            current_round: 1,
+            round_elapsed: Duration::ZERO,
+            game_state: GameState::Playing,
+            round_results: None,
+            round_winners: Vec::new(),
+            activity: Vec::new(),
+            market: Market::default(),
+            event: None,
+            pending_items: Vec::new(),
+            common_deposit: if shared { reserve } else { None },
+        };
+        game.begin_round();
+        game.open_round();
+        game
+    }
+
+    // The whole round is still to come while the draft is on
+    pub fn time_left(&self) -> Duration {
+        if self.game_state == GameState::Draft {
+            return self.rules.round_duration();
+        }
+        self.rules.round_duration().saturating_sub(self.round_elapsed)
+    }
+
+    // Every human has been eliminated but the bots are still competing
+    pub fn all_humans_out(&self) -> bool {
+        self.rules.human_count > 0 && !self.miners.iter().any(|miner| !miner.is_bot() && miner.alive)
+    }
+
+    // How much faster than real time the round clock runs
+    pub fn time_scale(&self) -> u32 {
+        if self.all_humans_out() {
+            ELIMINATED_SPEEDUP
+        } else {
+            1
+        }
+    }
+
+    // Miner indices from first to last place: survivors before the eliminated, who
+    // rank by how long they lasted. Ties go to more health, then more gold donated.
+    pub fn standings(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.miners.len()).collect();
+        order.sort_by(|a, b| {
+            let (a, b) = (&self.miners[*a], &self.miners[*b]);
+            let lasted = |miner: &Miner| miner.eliminated_in.unwrap_or(usize::MAX);
+            lasted(b)
+                .cmp(&lasted(a))
+                .then(b.health.cmp(&a.health))
+                .then(b.total_donated.partial_cmp(&a.total_donated).unwrap())
+        });
+        order
+    }
+
+    // Miners still in the match
+    pub fn alive_count(&self) -> usize {
+        self.miners.iter().filter(|miner| miner.alive).count()
+    }
+
+    // 1-based place of a miner in the standings
+    pub fn placement(&self, miner_index: usize) -> usize {
+        self.standings().iter().position(|i| *i == miner_index).map_or(0, |place| place + 1)
+    }
+
+    // "Round 3/15", or just "Round 3" when the match has no round limit
+    pub fn round_label(&self) -> String {
+        if self.rules.last_standing {
+            format!("Round {}", self.current_round)
+        } else {
+            format!("Round {}/{}", self.current_round, self.rules.max_rounds)
+        }
+    }
+
+    // Win (true) or loss per finished round, from one miner's point of view
+    pub fn past_results(&self, miner_index: usize) -> Vec<bool> {
+        self.round_winners.iter().map(|winner| *winner == miner_index).collect()
+    }
+
+    pub fn draft_time_left(&self) -> Duration {
+        DRAFT_DURATION.saturating_sub(self.round_elapsed)
+    }
+
+    // Advances the round clock by `dt`
+    pub fn tick(&mut self, dt: Duration) {
+        if self.game_state == GameState::Draft {
+            self.tick_draft(dt * self.time_scale());
+            return;
+        }
+
+        // Only update miners while playing
+        // This fixes issue with gold accumulating during round end screen
+        if self.game_state != GameState::Playing {
+            return;
+        }
+
+        if self.rules.turn_based {
+            self.tick_turn();
+            return;
+        }
+
+        let dt = dt * self.time_scale();
+
+        // Update all miners
+        let yield_factor = self.yield_factor();
+        for (i, miner) in self.miners.iter_mut().enumerate() {
+            miner.update(dt, yield_factor, yields::miner_seed(self.seed, i), self.common_deposit.as_mut());
+        }
+        self.market.update(dt, &mut rand::thread_rng());
+
+        // Make random decisions for bots
+        for i in 0..self.miners.len() {
+            self.bot_make_decision(i);
+        }
+
+        // Check if round is over
+        self.round_elapsed += dt;
+        if self.round_elapsed >= self.rules.round_duration() {
+            self.end_round();
+        }
+    }
+
+    // Applies a gameplay action for the human in `seat`; returns whether it did anything.
+    // Continue and Restart are decided by whoever runs the match, not by the game.
+    pub fn apply(&mut self, seat: usize, action: Action) -> bool {
+        let allowed_in = match action {
+            Action::PickPerk { .. } => GameState::Draft,
+            Action::EndTurn if !self.rules.turn_based => return false,
+            // A turn-based round is mined before anyone gets to act
+            Action::Strike if self.rules.turn_based => return false,
+            _ => GameState::Playing,
+        };
+        if self.game_state != allowed_in {
+            return false;
+        }
+
+        match self.miners.get(seat) {
+            // Orders are final once the turn is committed
+            Some(miner) if miner.turn_done && allowed_in == GameState::Playing => return false,
+            Some(miner) if miner.alive && !miner.is_bot() => {},
+            _ => return false,
+        }
+
+        if !self.perform(seat, action) {
+            return false;
+        }
+        // A turn-based phase moves on as soon as the last human is done with it
+        if self.rules.turn_based {
+            match self.game_state {
+                GameState::Draft => self.tick_draft(Duration::ZERO),
+                GameState::Playing => self.tick_turn(),
+                GameState::RoundEnd | GameState::GameOver => {},
+            }
+        }
+        true
+    }
+
+    // Carries out an action for any miner, human or bot, and notes it in the activity log
+    fn perform(&mut self, miner_index: usize, action: Action) -> bool {
+        match action {
+            Action::UseItem { item, target } => return self.use_item(miner_index, item, target),
+            Action::Sell { ore } => return self.sell(miner_index, ore),
+            Action::SellAll => {
+                let mut sold = false;
+                for ore in Ore::ALL {
+                    sold |= self.sell(miner_index, ore);
+                }
+                return sold;
+            },
+            // Strikes stay out of the log; there are far too many of them
+            Action::Strike => {
+                let yield_factor = self.yield_factor();
+                let seed = yields::miner_seed(self.seed, miner_index);
+                return self.miners[miner_index].strike(yield_factor, seed, self.common_deposit.as_mut());
+            },
+            _ => {},
+        }
+
+        let credit_factor = if self.event == Some(RoundEvent::CharityDrive) { CHARITY_DRIVE_CREDIT } else { 1.0 };
+        let loan_due = self.loan_due_round();
+        let miner = &mut self.miners[miner_index];
+        let done = match action {
+            Action::Research { node } => miner.research(node),
+            Action::BuyHeal => miner.buy_heal(),
+            Action::BuyShield => miner.buy_shield(),
+            Action::UpgradeMaxHealth => miner.upgrade_max_health(),
+            Action::Donate { amount } => miner.contribute_gold(amount, credit_factor),
+            Action::DonateAll => miner.contribute_gold(miner.gold, credit_factor),
+            Action::PickPerk { card } => miner.pick_perk(card),
+            Action::EndTurn => !std::mem::replace(&mut miner.turn_done, true),
+            Action::Deposit { amount } => miner.deposit_gold(amount),
+            Action::Withdraw { amount } => miner.withdraw(amount),
+            Action::Borrow { amount } => miner.borrow(amount, loan_due),
+            Action::Repay { amount } => miner.repay(amount),
+            Action::UseItem { .. }
+            | Action::Sell { .. }
+            | Action::SellAll
+            | Action::Strike
+            | Action::Continue
+            | Action::Restart => false,
+        };
+        if !done {
+            return false;
+        }
+
+        // Donations and banking stay out of the log; rivals only learn about donations from the donation column
+        let message = match action {
+            Action::Research { node } => {
+                let message = format!("{} researched {}.", miner.name, tech::tree().nodes[node].name);
+                let fogged = format!("{} finished some research.", miner.name);
+                self.log_fogged(Some(miner_index), None, message, fogged);
+                return true;
+            },
+            Action::BuyHeal => format!("{} healed up to {} health.", miner.name, miner.health),
+            Action::BuyShield => format!("{} raised a shield.", miner.name),
+            Action::UpgradeMaxHealth => format!("{} raised their max health to {}.", miner.name, miner.max_health),
+            Action::PickPerk { .. } => {
+                let card = miner.perk.map(|card| perks::deck().cards[card].name.as_str()).unwrap_or_default();
+                format!("{} picked {}.", miner.name, card)
+            },
+            _ => return true,
+        };
+        self.log(Some(miner_index), None, message);
+        true
+    }
+
+    // Sells all whole units of an ore. Sales stay out of the log; the price drop gives them away.
+    fn sell(&mut self, miner_index: usize, ore: Ore) -> bool {
+        let miner = &mut self.miners[miner_index];
+        let units = miner.sellable(ore);
+        if units < 1.0 {
+            return false;
+        }
+
+        miner.ore[ore.index()] -= units;
+        miner.gold += self.market.sell(ore, units);
+        true
+    }
+
+    // Items on sale in this match; scouting is only any use in the fog
+    pub fn available_items(&self) -> Vec<Item> {
+        let mut items = Item::SABOTAGE.to_vec();
+        if self.rules.fog_of_war {
+            items.extend(Item::SCOUTING);
+        }
+        items
+    }
+
+    // Buys and uses an item on the spot
+    fn use_item(&mut self, user: usize, item: Item, target: Option<usize>) -> bool {
+        let miner = &self.miners[user];
+        if !self.available_items().contains(&item) || !miner.cooldown_left(item).is_zero() || miner.gold < item.cost() {
+            return false;
+        }
+        let target = match target {
+            Some(target) if item.needs_target() && self.rivals(user).contains(&target) => Some(target),
+            None if !item.needs_target() => None,
+            _ => return false,
+        };
+
+        let miner = &mut self.miners[user];
+        miner.gold -= item.cost();
+        miner.item_cooldowns[item.index()] = item.cooldown();
+
+        // In a turn-based match sabotage lands when the turn resolves, so nobody can react to it
+        match (item, target) {
+            (Item::Dynamite | Item::ClaimJump, Some(target)) if self.rules.turn_based => {
+                self.pending_items.push((user, item, target));
+            },
+            _ => self.item_effect(user, item, target),
+        }
+        true
+    }
+
+    // What an item does once it is paid for, noted in the activity log
+    fn item_effect(&mut self, user: usize, item: Item, target: Option<usize>) {
+        let name = self.miners[user].name.clone();
+        let message = match (item, target) {
+            (Item::Dynamite, Some(target)) => {
+                let rival = &mut self.miners[target];
+                rival.stunned_for = DYNAMITE_STUN;
+                format!("{} threw dynamite at {}!", name, rival.name)
+            },
+            (Item::ClaimJump, Some(target)) => {
+                let rival = &mut self.miners[target];
+                let stolen = rival.gold * CLAIM_JUMP_SHARE;
+                rival.gold -= stolen;
+                let rival_name = rival.name.clone();
+                self.miners[user].gold += stolen;
+                let message = format!("{} jumped {}'s claim ({:.0}g)!", name, rival_name, stolen);
+                let fogged = format!("{} jumped {}'s claim!", name, rival_name);
+                self.log_fogged(Some(user), Some(target), message, fogged);
+                return;
+            },
+            (Item::Spyglass, Some(target)) => {
+                self.miners[user].reveal(target, SPYGLASS_REVEAL);
+                format!("{} is watching {} through a spyglass.", name, self.miners[target].name)
+            },
+            (Item::Informant, _) => {
+                for rival in self.rivals(user) {
+                    self.miners[user].reveal(rival, INFORMANT_REVEAL);
+                }
+                format!("{} paid an informant.", name)
+            },
+            _ => {
+                // Whatever was on show so far stays on show
+                let miner = &mut self.miners[user];
+                miner.decoy = Some(miner.shown_donation());
+                format!("{} made a decoy donation.", name)
+            },
+        };
+        self.log(Some(user), target, message);
+    }
+
+    // A miner's donation this round as rivals see it, or None while it is hidden from them
+    pub fn visible_donation(&self, miner_index: usize) -> Option<f32> {
+        let miner = &self.miners[miner_index];
+        let sealed = self.rules.auction.is_sealed() || self.rules.turn_based;
+        if sealed || miner.donation_hidden() {
+            None
+        } else {
+            Some(miner.shown_donation())
+        }
+    }
+
+    // What `viewer` can tell about `target`; None is a spectator, who sees what any rival would
+    // without scouting. Everyone knows everything about themselves.
+    pub fn observe(&self, viewer: Option<usize>, target: usize) -> RivalView {
+        let miner = &self.miners[target];
+        let own = viewer == Some(target);
+        let scouted = viewer.is_some_and(|viewer| self.miners[viewer].has_revealed(target));
+        // The fog lifts for the final standings
+        let clear = own || scouted || !self.rules.fog_of_war || self.game_state == GameState::GameOver;
+
+        RivalView {
+            health: miner.health,
+            max_health: miner.max_health,
+            shielded: miner.shielded,
+            tier: miner.tier(),
+            scouted,
+            gold: if clear { Some(miner.gold) } else { None },
+            ore: if clear { Some(miner.ore.map(f32::floor)) } else { None },
+            donation: match (own, clear) {
+                (true, _) => Some(miner.donated_gold),
+                (false, true) => self.visible_donation(target),
+                (false, false) => None,
+            },
+            stats: if clear { Some(miner.stats()) } else { None },
+        }
+    }
+
+    // A copy of the match with everything `viewer` can't see scrubbed out, for sending to a
+    // network client; None is a spectator. Rivals' hidden numbers are zeroed, log lines lose what
+    // the fog hides, and sealed bids and held-back items are left out, and so is the seed, which
+    // would give away the events and rolls to come. Clients only draw the match, so none of it is missed.
+    pub fn redacted_for(&self, viewer: Option<usize>) -> Game {
+        let mut game = self.clone();
+        game.seed = 0;
+        game.rules.seed = None;
+        game.pending_items.retain(|(user, _, _)| Some(*user) == viewer);
+
+        for (i, miner) in game.miners.iter_mut().enumerate() {
+            if viewer == Some(i) {
+                continue;
+            }
+            let view = self.observe(viewer, i);
+            miner.donated_gold = view.donation.unwrap_or(0.0);
+            miner.donation_spent = 0.0;
+            miner.decoy = None;
+            miner.saving_for = None;
+            miner.researching = None;
+            if view.gold.is_none() {
+                miner.gold = 0.0;
+                miner.deposit = 0.0;
+                miner.loan = 0.0;
+                miner.loan_due = None;
+                miner.ore = [0.0; 4];
+            }
+            if view.stats.is_none() {
+                miner.redacted_techs = miner.techs.len();
+                miner.techs.clear();
+                miner.reserve = None;
+            }
+        }
+        for entry in &mut game.activity {
+            entry.message = entry.text_for(viewer.as_slice()).to_string();
+            entry.fogged = None;
+        }
+        game
+    }
+
+    // Other miners still in the match
+    pub fn rivals(&self, miner_index: usize) -> Vec<usize> {
+        (0..self.miners.len())
+            .filter(|i| *i != miner_index && self.miners[*i].alive)
+            .collect()
+    }
+
+    fn log(&mut self, miner: Option<usize>, target: Option<usize>, message: String) {
+        self.activity.push(LogEntry { miner, target, message, fogged: None });
+        if self.activity.len() > ACTIVITY_LOG_LIMIT {
+            self.activity.remove(0);
+        }
+    }
+
+    // Logs a line that gives away something the fog of war hides, with a wording
+    // for everyone but the miners involved
+    fn log_fogged(&mut self, miner: Option<usize>, target: Option<usize>, message: String, fogged: String) {
+        self.log(miner, target, message);
+        if self.rules.fog_of_war {
+            if let Some(entry) = self.activity.last_mut() {
+                entry.fogged = Some(fogged);
+            }
+        }
+    }
+
+    pub fn bot_make_decision(&mut self, bot_index: usize) {
+        let difficulty = self.rules.bot_difficulty;
+        let time_left = self.time_left().as_secs_f32();
+        if !self.miners[bot_index].alive || !self.miners[bot_index].is_automated() {
+            return;
+        }
+
+        self.bot_strike(bot_index);
+        self.bot_sell_ore(bot_index, time_left);
+        self.bot_bank(bot_index, time_left);
+        let bot = &self.miners[bot_index];
+
+        // A bot close to elimination protects itself first, borrowing for a shield if it has to
+        if bot.health <= self.bot_danger_health() {
+            if !bot.shielded {
+                self.bot_raise_gold(bot_index, self.miners[bot_index].shield_cost());
+            }
+            if self.perform(bot_index, Action::BuyShield) || self.perform(bot_index, Action::BuyHeal) {
+                return;
+            }
+            // Save up instead of donating when the rest of the round's mining pays for a shield
+            let bot = &self.miners[bot_index];
+            if !bot.shielded && bot.gold + bot.expected_income(time_left) >= bot.shield_cost() {
+                return;
+            }
+        }
+
+        let mut rng = rand::thread_rng();
+
+        // Now and then a bot decides to get in a rival's way, harder bots more often,
+        // and stops donating until it can afford the item
+        let item_chance = match difficulty {
+            BotDifficulty::Easy => 0.0003,
+            BotDifficulty::Normal => 0.001,
+            BotDifficulty::Hard => 0.002,
+        };
+        if self.miners[bot_index].saving_for.is_none() && rng.gen_bool(item_chance) {
+            let items = self.available_items();
+            let item = items[rng.gen_range(0..items.len())];
+            if self.miners[bot_index].cooldown_left(item).is_zero() {
+                self.miners[bot_index].saving_for = Some(item);
+            }
+        }
+        if let Some(item) = self.miners[bot_index].saving_for {
+            let action = Action::UseItem { item, target: self.bot_pick_target(bot_index, item) };
+            // Late in the round the gold is better spent on the donation
+            if self.perform(bot_index, action) || time_left < BOT_SAVING_CUTOFF {
+                self.miners[bot_index].saving_for = None;
+            } else {
+                return;
+            }
+        }
+
+        // Research is planned the same way when the rest of the round's mining pays for it,
+        // and saved for until it is bought or can't be afforded this round any more.
+        // Hard bots go for the cheapest node and plan far more often.
+        let research_chance = match difficulty {
+            BotDifficulty::Easy => 0.0005,
+            BotDifficulty::Normal => 0.001,
+            BotDifficulty::Hard => 0.004,
+        };
+        if self.miners[bot_index].researching.is_none() && rng.gen_bool(research_chance) {
+            let bot = &self.miners[bot_index];
+            let budget = bot.gold + self.market.value(&bot.ore) + bot.expected_income(time_left);
+            let options = self.bot_research_options(bot_index, budget);
+            self.miners[bot_index].researching = match difficulty {
+                BotDifficulty::Hard => options
+                    .into_iter()
+                    .min_by(|a, b| tech::tree().nodes[*a].cost.partial_cmp(&tech::tree().nodes[*b].cost).unwrap()),
+                _ if options.is_empty() => None,
+                _ => Some(options[rng.gen_range(0..options.len())]),
+            };
+        }
+        if let Some(node) = self.miners[bot_index].researching {
+            // Dynamite or a claim jump can put it out of reach before the round ends
+            let bot = &self.miners[bot_index];
+            let budget = bot.gold + self.market.value(&bot.ore) + bot.expected_income(time_left);
+            let out_of_reach = budget < tech::tree().nodes[node].cost;
+            if self.perform(bot_index, Action::Research { node }) || out_of_reach {
+                self.miners[bot_index].researching = None;
+            } else {
+                return;
+            }
+        }
+
+        let affordable = self.bot_research_options(bot_index, self.miners[bot_index].gold);
+
+        // 0, 1: Research something affordable, 2: Contribute gold, 3: Max health when affordable, else contribute
+        let bot = &self.miners[bot_index];
+        let action = match rng.gen_range(0..4) {
+            0 | 1 if !affordable.is_empty() => Action::Research { node: affordable[rng.gen_range(0..affordable.len())] },
+            0 | 1 => return,
+            3 if bot.max_health_level < MAX_HEALTH_UPGRADES && bot.gold >= bot.max_health_upgrade_cost() => {
+                Action::UpgradeMaxHealth
+            },
+            _ => match self.bot_bid(bot_index, time_left, &mut rng) {
+                Some(amount) => Action::Donate { amount },
+                None => return,
+            },
+        };
+        self.perform(bot_index, action);
+    }
+
+    // How much a bot donates now, if anything, given what the auction format rewards
+    fn bot_bid<R: Rng>(&self, bot_index: usize, time_left: f32, rng: &mut R) -> Option<f32> {
+        let difficulty = self.rules.bot_difficulty;
+        let bot = &self.miners[bot_index];
+
+        // Contribute a random portion of gold; harder bots give more at once
+        let contribution_percentage = match difficulty {
+            BotDifficulty::Easy => rng.gen_range(0.05..0.3),
+            BotDifficulty::Normal => rng.gen_range(0.1..0.6), // 10% to 60% of current gold
+            BotDifficulty::Hard => rng.gen_range(0.2..0.8),
+        };
+        let share = bot.gold * contribution_percentage;
+        let late = time_left < BOT_SAVING_CUTOFF;
+
+        match self.rules.auction {
+            AuctionFormat::AllPay => Some(share),
+            // Easy bots don't think it through and bid as usual
+            _ if difficulty == BotDifficulty::Easy => Some(share),
+            // Nobody learns anything from an early sealed bid, so the gold stays free for the shop until the end
+            AuctionFormat::Sealed if late => Some(share),
+            AuctionFormat::Sealed => None,
+            // Bidding high costs the winner no more than the runner-up's bid and losers nothing,
+            // so hard bots put in everything at the end and normal bots most of it
+            AuctionFormat::SecondPrice if late && difficulty == BotDifficulty::Hard => Some(bot.gold),
+            AuctionFormat::SecondPrice if late => Some(bot.gold * 0.75),
+            AuctionFormat::SecondPrice => None,
+            // Losing costs nothing, so everything goes in at the end. Until then bots only
+            // raise their donation far enough to lead the visible field.
+            AuctionFormat::RefundLosers if late => Some(bot.gold),
+            AuctionFormat::RefundLosers => {
+                let leading = self
+                    .rivals(bot_index)
+                    .into_iter()
+                    .filter_map(|rival| self.observe(Some(bot_index), rival).donation)
+                    .fold(0.0, f32::max);
+                let needed = leading - bot.donated_gold + 1.0;
+                if needed > 0.0 && needed <= bot.gold {
+                    Some(needed.max(share * 0.25))
+                } else {
+                    None
+                }
+            },
+        }
+    }
+
+    // Health at which a bot starts worrying about elimination; harder bots see it coming sooner
+    fn bot_danger_health(&self) -> i32 {
+        match self.rules.bot_difficulty {
+            BotDifficulty::Easy => 2,
+            BotDifficulty::Normal => 3,
+            BotDifficulty::Hard => 5,
+        }
+    }
+
+    // Easy bots draw a card at random. The others weigh each card in gold over the coming
+    // round, and value damage reduction far more once they are in danger.
+    fn bot_pick_perk<R: Rng>(&self, bot_index: usize, rng: &mut R) -> usize {
+        let bot = &self.miners[bot_index];
+        if self.rules.bot_difficulty == BotDifficulty::Easy {
+            return rng.gen_range(0..bot.perk_offer.len());
+        }
+
+        let income = bot.expected_income(self.rules.round_duration().as_secs_f32());
+        let protection = if bot.health <= self.bot_danger_health() { income } else { income * 0.1 };
+        // Reduction past the cap is worth nothing
+        let reduction = bot.stats().damage_reduction;
+        let worth = |card: &usize| {
+            let effects = perks::deck().cards[*card].effects;
+            let hidden = if effects.hidden_donation { income * 0.1 } else { 0.0 };
+            effects.gold
+                + income * (effects.speed_bonus + effects.yield_bonus + effects.donation_bonus)
+                + income * effects.crit_chance * (yields::table().crit_multiplier - 1.0)
+                + protection * effects.damage_reduction.min(MAX_DAMAGE_REDUCTION - reduction).max(0) as f32
+                + hidden
+        };
+
+        (0..bot.perk_offer.len())
+            .max_by(|a, b| worth(&bot.perk_offer[*a]).partial_cmp(&worth(&bot.perk_offer[*b])).unwrap())
+            .unwrap_or(0)
+    }
+
+    // Bots work the rock at a steady pace set by their difficulty: easy bots too slowly to
+    // build a combo, harder bots quickly enough to keep one going all round
+    fn bot_strike(&mut self, bot_index: usize) {
+        let interval = match self.rules.bot_difficulty {
+            BotDifficulty::Easy => 1.0,
+            BotDifficulty::Normal => 0.5,
+            BotDifficulty::Hard => 0.3,
+        };
+        let bot = &self.miners[bot_index];
+        let due = (self.round_elapsed.as_secs_f32() / interval) as usize;
+        let since_last = (COMBO_WINDOW - bot.combo_timer).as_secs_f32();
+        if bot.strikes < due && (!bot.combo_running() || since_last >= interval) {
+            self.perform(bot_index, Action::Strike);
+        }
+    }
+
+    // Bots keep spare gold in the bank between rounds, where it earns interest and claim jumps
+    // can't reach it, and take it out again as the next round starts. Easy bots don't bother,
+    // and normal bots only bank half. Loans are paid back once the bot can easily spare the gold,
+    // and ahead of any donation in the round they fall due.
+    fn bot_bank(&mut self, bot_index: usize, time_left: f32) {
+        let round_seconds = self.rules.round_duration().as_secs_f32();
+        let bot = &self.miners[bot_index];
+        if bot.deposit > 0.0 && time_left > round_seconds - BOT_WITHDRAW_WINDOW {
+            self.perform(bot_index, Action::Withdraw { amount: bot.deposit });
+        }
+
+        let bot = &self.miners[bot_index];
+        let due_now = bot.loan_due.is_some_and(|due| due <= self.current_round);
+        if bot.loan > 0.0 && bot.gold >= 1.0 && (due_now || bot.gold >= bot.loan * 2.0) {
+            self.perform(bot_index, Action::Repay { amount: bot.gold.min(bot.loan) });
+        }
+
+        let banked_share = match self.rules.bot_difficulty {
+            BotDifficulty::Easy => 0.0,
+            BotDifficulty::Normal => 0.5,
+            BotDifficulty::Hard => 0.9,
+        };
+        let bot = &self.miners[bot_index];
+        if time_left < BOT_DEPOSIT_CUTOFF && banked_share > 0.0 && bot.gold >= 1.0 {
+            self.perform(bot_index, Action::Deposit { amount: bot.gold * banked_share });
+        }
+    }
+
+    // Brings a bot's gold up to `needed` from its deposit and then, unless it is an easy bot,
+    // with a loan. Bots only take one loan at a time, with its full term to run, and only one
+    // a round's mining pays back. Returns whether it got there.
+    fn bot_raise_gold(&mut self, bot_index: usize, needed: f32) -> bool {
+        let bot = &self.miners[bot_index];
+        let short = needed - bot.gold;
+        if short <= 0.0 {
+            return true;
+        }
+        if bot.deposit > 0.0 {
+            self.perform(bot_index, Action::Withdraw { amount: bot.deposit.min(short) });
+        }
+
+        let bot = &self.miners[bot_index];
+        let short = needed - bot.gold;
+        let income = bot.expected_income(self.rules.round_duration().as_secs_f32());
+        let affordable = short * (1.0 + LOAN_INTEREST) <= income && short <= bot.credit_left();
+        let full_term = self.loan_due_round() == self.current_round + LOAN_ROUNDS - 1;
+        if short > 0.0 && self.rules.bot_difficulty != BotDifficulty::Easy && bot.loan == 0.0 && affordable && full_term {
+            self.perform(bot_index, Action::Borrow { amount: short.ceil().min(bot.credit_left()) });
+        }
+        self.miners[bot_index].gold >= needed
+    }
+
+    // Easy bots sell everything as soon as it is mined; the others wait for prices at or
+    // above the usual, hard bots for a premium. Late in the round everything goes.
+    fn bot_sell_ore(&mut self, bot_index: usize, time_left: f32) {
+        let premium = match self.rules.bot_difficulty {
+            BotDifficulty::Easy => 0.0,
+            BotDifficulty::Normal => 1.0,
+            BotDifficulty::Hard => 1.1,
+        };
+        for ore in Ore::ALL {
+            if time_left < BOT_SAVING_CUTOFF || self.market.price(ore) >= ore.base_price() * premium {
+                self.sell(bot_index, ore);
+            }
+        }
+    }
+
+    // Tech tree nodes open to the bot that cost no more than `budget`
+    fn bot_research_options(&self, bot_index: usize, budget: f32) -> Vec<usize> {
+        let bot = &self.miners[bot_index];
+        tech::tree()
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(index, node)| bot.can_research(*index) && budget >= node.cost)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    // Dynamite goes to whoever looks like they are winning the round, claim jumps to the richest
+    // rival, as far as the bot can tell; in the fog the highest tier stands in for both.
+    // The spyglass goes to the highest tier rival the bot can't see yet.
+    fn bot_pick_target(&self, bot_index: usize, item: Item) -> Option<usize> {
+        let rivals = self.rivals(bot_index).into_iter();
+        let views: Vec<RivalView> = (0..self.miners.len()).map(|i| self.observe(Some(bot_index), i)).collect();
+        let by = |key: fn(&RivalView) -> Option<f32>| {
+            let views = &views;
+            move |a: &usize, b: &usize| {
+                let (a, b) = (&views[*a], &views[*b]);
+                key(a).unwrap_or(0.0).partial_cmp(&key(b).unwrap_or(0.0)).unwrap().then(a.tier.cmp(&b.tier))
+            }
+        };
+        match item {
+            Item::Dynamite => rivals.max_by(by(|view| view.donation)),
+            Item::ClaimJump => rivals.max_by(by(|view| view.gold)),
+            Item::Spyglass => rivals
+                .filter(|rival| !self.miners[bot_index].has_revealed(*rival))
+                .max_by_key(|rival| views[*rival].tier),
+            Item::Decoy | Item::Informant => None,
+        }
+    }
+
+    pub fn end_round(&mut self) {
+        // Collect all living miners' donated gold amounts
+        let mut results: Vec<(usize, f32)> = self.miners
+            .iter()
+            .enumerate()
+            .filter(|(_, miner)| miner.alive)
+            .map(|(i, miner)| (i, miner.donated_gold))
+            .collect();
+
+        // Sort by donated gold (highest first)
+        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+        // Record who won this round (was ranked #1)
+        if let Some((winner, _)) = results.first() {
+            self.round_winners.push(*winner);
+            let message = format!("Round {} ended - {} ranked #1!", self.current_round, self.miners[*winner].name);
+            self.log(None, None, message);
+        }
+
+        self.settle_auction(&results);
+        // What is left of each donation after refunds now counts towards the match total
+        for miner in &mut self.miners {
+            miner.total_donated += miner.donated_gold;
+        }
+
+        // Assign damage based on position
+        let sudden_death = self.sudden_death_damage();
+        for (position, (miner_index, _)) in results.iter().enumerate() {
+            let miner = &mut self.miners[*miner_index];
+            let damage = if position > 0 { position as i32 + sudden_death } else { 0 };
+            miner.take_damage(damage);
+            if !miner.alive {
+                miner.eliminated_in = Some(self.current_round);
+                let message = format!("{} was eliminated.", miner.name);
+                self.log(Some(*miner_index), None, message);
+            }
+        }
+
+        // Survivors earn interest on what they kept back, and the bank collects loans that are due
+        for i in 0..self.miners.len() {
+            let miner = &mut self.miners[i];
+            if !miner.alive {
+                continue;
+            }
+            miner.pay_interest();
+            if let Some(unpaid) = miner.settle_bank(self.current_round) {
+                let mut message = format!("{} defaulted on {:.0}g and lost {} health.", miner.name, unpaid, LOAN_DEFAULT_DAMAGE);
+                if !miner.alive {
+                    miner.eliminated_in = Some(self.current_round);
+                    message = format!("{} defaulted on {:.0}g and was eliminated.", miner.name, unpaid);
+                }
+                self.log(Some(i), None, message);
+            }
+        }
+
+        // Reset donated gold; shields, decoys and perks only last the round they were bought in
+        for miner in &mut self.miners {
+            miner.donated_gold = 0.0;
+            miner.donation_spent = 0.0;
+            miner.shielded = false;
+            miner.decoy = None;
+            miner.perk = None;
+            miner.strikes = 0;
+            miner.combo = 0;
+            miner.combo_timer = Duration::ZERO;
+        }
+
+        // Miners knocked out in earlier rounds are listed below the ranking, most recent first
+        let ranked: Vec<usize> = results.iter().map(|(i, _)| *i).collect();
+        results.extend(
+            self.standings()
+                .into_iter()
+                .filter(|i| !ranked.contains(i))
+                .map(|i| (i, 0.0))
+        );
+
+        // Store results for display
+        self.round_results = Some(results);
+
+        // The game is over once a single miner (or nobody) is left, or after the last
+        // round. A match played to the last miner standing has sudden death to make sure
+        // it gets there, and a hard limit in case it still doesn't. Humans who are out
+        // can watch the bots finish the match (or skip ahead with `fast_forward`).
+        let decided = self.alive_count() <= 1;
+        let out_of_rounds = self.current_round >= self.round_limit();
+        if decided || out_of_rounds {
+            self.game_state = GameState::GameOver;
+        } else {
+            // Move to next round
+            self.game_state = GameState::RoundEnd;
+        }
+    }
+
+    // Refunds whatever the auction format doesn't charge. `results` is ranked, top donor first.
+    fn settle_auction(&mut self, results: &[(usize, f32)]) {
+        let format = self.rules.auction;
+        let runner_up = results.get(1).map_or(0.0, |(_, donated)| *donated);
+
+        for (position, (miner_index, donated)) in results.iter().enumerate() {
+            let refunded_share = match (format, position) {
+                (AuctionFormat::AllPay | AuctionFormat::Sealed, _) => 0.0,
+                (AuctionFormat::SecondPrice, 0) if *donated > 0.0 => 1.0 - runner_up / donated,
+                (AuctionFormat::RefundLosers, 0) | (AuctionFormat::SecondPrice, 0) => 0.0,
+                _ => 1.0,
+            };
+            self.miners[*miner_index].refund_donation(refunded_share);
+        }
+
+        let message = match (format, results.first()) {
+            (AuctionFormat::SecondPrice, Some((winner, _))) => {
+                format!("{} paid the runner-up's {:.0}g; every other donation was refunded.", self.miners[*winner].name, runner_up)
+            },
+            (AuctionFormat::RefundLosers, Some(_)) => "Every donation but the top one was refunded.".to_string(),
+            _ => return,
+        };
+        self.log(None, None, message);
+    }
+
+    // Plays out the rest of the match without waiting. A match that still hasn't finished after
+    // FAST_FORWARD_ROUNDS rounds' worth of ticks is called, standings as they are.
+    pub fn fast_forward(&mut self) {
+        let round_ticks = (self.rules.round_duration() + DRAFT_DURATION).as_millis() / FAST_FORWARD_STEP.as_millis() * 2;
+        let mut ticks_left = round_ticks as usize * FAST_FORWARD_ROUNDS;
+        while self.game_state != GameState::GameOver {
+            if ticks_left == 0 {
+                self.game_state = GameState::GameOver;
+                self.log(None, None, "The match was called before anyone won.".to_string());
+                return;
+            }
+            ticks_left -= 1;
+            match self.game_state {
+                GameState::Draft | GameState::Playing => self.tick(FAST_FORWARD_STEP),
+                GameState::RoundEnd => self.start_next_round(),
+                GameState::GameOver => {},
+            }
+        }
+    }
+
+    pub fn start_next_round(&mut self) {
+        if self.game_state != GameState::RoundEnd {
+            return;
+        }
+
+        self.current_round += 1;
+        self.round_results = None;
+        self.begin_round();
+        let sudden_death = self.sudden_death_damage();
+        if sudden_death > 0 {
+            let message = format!("Sudden death: every place below first takes {} extra damage this round.", sudden_death);
+            self.log(None, None, message);
+        }
+        self.open_round();
+    }
+
+    // Every round, the first included, starts with the perk draft when there are cards to deal
+    fn open_round(&mut self) {
+        if !self.deal_perks() {
+            self.start_playing();
+        } else if self.rules.turn_based {
+            // Bots pick their cards straight away rather than on the next tick
+            self.tick_draft(Duration::ZERO);
+        }
+    }
+
+    // Extra damage every place below first takes this round: none until SUDDEN_DEATH_ROUND
+    // of a last standing match, then 1 more each round
+    fn sudden_death_damage(&self) -> i32 {
+        if !self.rules.last_standing || self.current_round < SUDDEN_DEATH_ROUND {
+            return 0;
+        }
+        (self.current_round - SUDDEN_DEATH_ROUND + 1) as i32
+    }
+
+    // Every swing and strike this round is scaled by this, for round events
+    fn yield_factor(&self) -> f32 {
+        if self.event == Some(RoundEvent::GoldRush) { GOLD_RUSH_YIELD } else { 1.0 }
+    }
+
+    // Round at whose end a loan taken now falls due. Nothing can be collected after the last
+    // round, so loans taken near the end of a match fall due with it.
+    fn loan_due_round(&self) -> usize {
+        (self.current_round + LOAN_ROUNDS - 1).min(self.round_limit())
+    }
+
+    // Round after which the match ends whoever is still standing
+    fn round_limit(&self) -> usize {
+        if self.rules.last_standing { LAST_STANDING_MAX_ROUNDS } else { self.rules.max_rounds }
+    }
+
+    // Random numbers for a turn-based round, drawn from the match seed so a match played by file
+    // comes out the same on every machine. Each `stream` gets numbers of its own: 0 moves the
+    // market and the rest belong to one bot decision each.
+    fn turn_rng(&self, stream: usize) -> StdRng {
+        events::round_rng(self.seed ^ (stream as u64 + 1).wrapping_mul(0xD1B5_4A32_D192_ED03), self.current_round)
+    }
+
+    // Deals every survivor a hand of perk cards and holds the round until they have picked.
+    // The cards come from their own stream of the seed, so they don't change which events a seed gives.
+    // Returns whether there is a draft to hold.
+    fn deal_perks(&mut self) -> bool {
+        let deck = perks::deck();
+        if deck.cards.is_empty() {
+            return false;
+        }
+
+        let mut rng = events::round_rng(!self.seed, self.current_round);
+        for miner in self.miners.iter_mut().filter(|miner| miner.alive) {
+            miner.perk_offer = deck.deal(&mut rng);
+        }
+        self.round_elapsed = Duration::ZERO;
+        self.game_state = GameState::Draft;
+        true
+    }
+
+    // Bots pick straight away; humans have until the draft clock runs out,
+    // or as long as they like in a turn-based match
+    fn tick_draft(&mut self, dt: Duration) {
+        self.round_elapsed += dt;
+        let out_of_time = !self.rules.turn_based && self.round_elapsed >= DRAFT_DURATION;
+
+        for i in 0..self.miners.len() {
+            let miner = &self.miners[i];
+            if miner.is_drafting() && (miner.is_automated() || out_of_time) {
+                let card = self.bot_pick_perk(i, &mut self.turn_rng(1 + self.miners.len() + i));
+                self.perform(i, Action::PickPerk { card });
+            }
+        }
+
+        if !self.miners.iter().any(Miner::is_drafting) {
+            self.start_playing();
+        }
+    }
+
+    // Starts the round clock. A turn-based round is mined in one go here, so all that
+    // is left of it is the orders phase.
+    fn start_playing(&mut self) {
+        self.round_elapsed = Duration::ZERO;
+        self.game_state = GameState::Playing;
+        if !self.rules.turn_based {
+            return;
+        }
+
+        // Mined a second at a time, so everyone gets their share of a common deposit before it runs
+        // dry, and prices drift as much as they would have over the round without overshooting
+        let yield_factor = self.yield_factor();
+        let mut rng = self.turn_rng(0);
+        for _ in 0..self.rules.round_seconds {
+            for (i, miner) in self.miners.iter_mut().enumerate() {
+                miner.mine_for(1.0, yield_factor, yields::miner_seed(self.seed, i), self.common_deposit.as_mut());
+            }
+            self.market.update(Duration::from_secs(1), &mut rng);
+        }
+        for miner in &mut self.miners {
+            miner.turn_done = false;
+        }
+
+        // Bots put their orders in straight away
+        self.tick_turn();
+    }
+
+    // The orders phase of a turn-based round: bots commit straight away, and once every miner
+    // still in the match has committed, held-back items go off and the round is scored.
+    // Also runs straight after anything that could finish the phase rather than on the next
+    // tick, so a turn-based match plays out the same however long anyone takes.
+    fn tick_turn(&mut self) {
+        for i in 0..self.miners.len() {
+            let miner = &self.miners[i];
+            if miner.alive && miner.is_automated() && !miner.turn_done {
+                self.bot_take_turn(i);
+            }
+        }
+        if !self.miners.iter().all(|miner| !miner.alive || miner.turn_done) {
+            return;
+        }
+
+        for (user, item, target) in std::mem::take(&mut self.pending_items) {
+            if self.miners[target].alive {
+                self.item_effect(user, item, Some(target));
+            }
+        }
+        self.end_round();
+    }
+
+    // Everything a bot spreads over a real-time round, decided in one go, as if the round were
+    // about to end: sell up, stay alive, maybe use an item, research with part of the gold and bid
+    fn bot_take_turn(&mut self, bot_index: usize) {
+        let mut rng = self.turn_rng(1 + bot_index);
+        let round_seconds = self.rules.round_duration().as_secs_f32();
+        self.bot_bank(bot_index, round_seconds);
+        self.bot_sell_ore(bot_index, 0.0);
+
+        let bot = &self.miners[bot_index];
+        if bot.health <= self.bot_danger_health() {
+            if !bot.shielded {
+                self.bot_raise_gold(bot_index, self.miners[bot_index].shield_cost());
+            }
+            let _ = self.perform(bot_index, Action::BuyShield) || self.perform(bot_index, Action::BuyHeal);
+        }
+
+        let item_chance = match self.rules.bot_difficulty {
+            BotDifficulty::Easy => 0.1,
+            BotDifficulty::Normal => 0.25,
+            BotDifficulty::Hard => 0.4,
+        };
+        if rng.gen_bool(item_chance) {
+            let items = self.available_items();
+            let item = items[rng.gen_range(0..items.len())];
+            let action = Action::UseItem { item, target: self.bot_pick_target(bot_index, item) };
+            self.perform(bot_index, action);
+        }
+
+        // Research takes at most half the gold so there is some left to donate
+        let options = self.bot_research_options(bot_index, self.miners[bot_index].gold * 0.5);
+        let node = match self.rules.bot_difficulty {
+            BotDifficulty::Hard => options
+                .into_iter()
+                .min_by(|a, b| tech::tree().nodes[*a].cost.partial_cmp(&tech::tree().nodes[*b].cost).unwrap()),
+            _ if options.is_empty() || rng.gen_bool(0.5) => None,
+            _ => Some(options[rng.gen_range(0..options.len())]),
+        };
+        if let Some(node) = node {
+            self.perform(bot_index, Action::Research { node });
+        }
+
+        let bot = &self.miners[bot_index];
+        if bot.max_health_level < MAX_HEALTH_UPGRADES && bot.gold >= bot.max_health_upgrade_cost() * 2.0 && rng.gen_bool(0.25) {
+            self.perform(bot_index, Action::UpgradeMaxHealth);
+        }
+
+        if let Some(amount) = self.bot_bid(bot_index, 0.0, &mut rng) {
+            self.perform(bot_index, Action::Donate { amount });
+        }
+        self.bot_bank(bot_index, 0.0);
+        self.perform(bot_index, Action::EndTurn);
+    }
+
+    // Rolls this round's event, announces it and applies anything that happens straight away
+    fn begin_round(&mut self) {
+        let mut rng = events::round_rng(self.seed, self.current_round);
+        self.event = RoundEvent::roll(&mut rng);
+
+        let event = match self.event {
+            Some(event) => event,
+            None => return,
+        };
+        match event {
+            RoundEvent::CaveIn => {
+                let alive: Vec<usize> = (0..self.miners.len()).filter(|i| self.miners[*i].alive).collect();
+                if alive.is_empty() {
+                    return;
+                }
+                let trapped = alive[rng.gen_range(0..alive.len())];
+                let miner = &mut self.miners[trapped];
+                miner.stunned_for = miner.stunned_for.max(CAVE_IN_STUN);
+                let message = format!("Cave-In! {} is trapped for {} seconds.", miner.name, CAVE_IN_STUN.as_secs());
+                self.log(None, Some(trapped), message);
+            },
+            RoundEvent::TaxCollector => {
+                for miner in self.miners.iter_mut().filter(|miner| miner.alive) {
+                    miner.gold -= miner.gold * TAX_SHARE;
+                }
+                self.log(None, None, format!("Tax Collector! {}.", event.description()));
+            },
+            RoundEvent::GoldRush | RoundEvent::CharityDrive => {
+                self.log(None, None, format!("{}! {}.", event.name(), event.description()));
+            },
+        }
+    }
+
+    // Fresh match with the same rules. Names, colours and autopilot flags of human seats carry over.
+    pub fn restart(&mut self) {
+        let mut miners = spawn_miners(&self.rules);
+        for (new, old) in miners.iter_mut().zip(&self.miners) {
+            if !new.is_bot() {
+                new.name = old.name.clone();
+                new.color = old.color;
+                new.autopilot = old.autopilot;
+            }
+        }
+
+        *self = Game::with_miners(self.rules.clone(), miners);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STARTING_GOLD: f32 = 200.0;
+
+    // A match with one miner per bid, everyone donating their bid out of STARTING_GOLD
+    fn match_with_bids(auction: AuctionFormat, bids: &[f32]) -> Game {
+        let rules = MatchRules {
+            human_count: 1,
+            bot_count: bids.len() - 1,
+            auction,
+            seed: Some(7),
+            ..MatchRules::default()
+        };
+        let mut game = Game::new(rules);
+        for (miner, bid) in game.miners.iter_mut().zip(bids) {
+            miner.gold = STARTING_GOLD;
+            if *bid > 0.0 {
+                assert!(miner.contribute_gold(*bid, 1.0));
+            }
+        }
+        game
+    }
+
+    // Ranks the donations as `end_round` does and settles them
+    fn settle(game: &mut Game) {
+        let mut results: Vec<(usize, f32)> = game.miners.iter().enumerate().map(|(i, miner)| (i, miner.donated_gold)).collect();
+        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        game.settle_auction(&results);
+    }
+
+    fn paid(game: &Game) -> Vec<f32> {
+        game.miners.iter().map(|miner| STARTING_GOLD - miner.gold).collect()
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-3, "{:?} isn't {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn all_pay_and_sealed_keep_every_donation() {
+        for format in [AuctionFormat::AllPay, AuctionFormat::Sealed] {
+            let mut game = match_with_bids(format, &[100.0, 60.0, 20.0]);
+            settle(&mut game);
+            assert_close(&paid(&game), &[100.0, 60.0, 20.0]);
+        }
+    }
+
+    #[test]
+    fn second_price_winner_pays_the_runner_up_and_the_rest_are_refunded() {
+        let mut game = match_with_bids(AuctionFormat::SecondPrice, &[100.0, 60.0, 20.0]);
+        settle(&mut game);
+        assert_close(&paid(&game), &[60.0, 0.0, 0.0]);
+        assert_close(&[game.miners[0].donated_gold], &[60.0]);
+    }
+
+    #[test]
+    fn second_price_lone_bidder_pays_nothing() {
+        let mut game = match_with_bids(AuctionFormat::SecondPrice, &[100.0, 0.0, 0.0]);
+        settle(&mut game);
+        assert_close(&paid(&game), &[0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn second_price_with_no_donations_refunds_nothing_and_stays_finite() {
+        let mut game = match_with_bids(AuctionFormat::SecondPrice, &[0.0, 0.0]);
+        settle(&mut game);
+        assert_close(&paid(&game), &[0.0, 0.0]);
+        assert!(game.miners.iter().all(|miner| miner.gold.is_finite() && miner.donated_gold == 0.0));
+    }
+
+    #[test]
+    fn second_price_refund_is_a_share_of_the_gold_spent() {
+        // Credited 125 for 100 spent, against a runner-up credited 100: the winner keeps
+        // 1 - 100/125 of what they spent
+        let mut game = match_with_bids(AuctionFormat::SecondPrice, &[100.0, 100.0]);
+        game.miners[0].donated_gold = 125.0;
+        settle(&mut game);
+        assert_close(&paid(&game), &[80.0, 0.0]);
+    }
+
+    #[test]
+    fn refund_losers_winner_pays_in_full() {
+        let mut game = match_with_bids(AuctionFormat::RefundLosers, &[100.0, 60.0, 20.0]);
+        settle(&mut game);
+        assert_close(&paid(&game), &[100.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn match_total_counts_only_what_was_kept_once_the_round_ends() {
+        let mut game = match_with_bids(AuctionFormat::SecondPrice, &[100.0, 60.0, 20.0]);
+        assert!(game.miners.iter().all(|miner| miner.total_donated == 0.0));
+        game.end_round();
+        let totals: Vec<f32> = game.miners.iter().map(|miner| miner.total_donated).collect();
+        assert_close(&totals, &[60.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn a_redacted_snapshot_keeps_what_the_fog_hides_out_of_the_log() {
+        let rules = MatchRules { human_count: 1, bot_count: 2, fog_of_war: true, seed: Some(7), ..MatchRules::default() };
+        let mut game = Game::new(rules);
+        for miner in &mut game.miners {
+            miner.gold = 1000.0;
+        }
+        let node = game.miners[1].next_in_branch(tech::Branch::Speed).unwrap();
+        assert!(game.perform(1, Action::Research { node }));
+        assert!(game.use_item(1, Item::ClaimJump, Some(2)));
+        let node_name = tech::tree().nodes[node].name.as_str();
+
+        // Lines about what miner 1 did, as a viewer gets them
+        let lines = |viewer: Option<usize>| -> Vec<String> {
+            let game = game.redacted_for(viewer);
+            game.activity.iter().filter(|entry| entry.miner == Some(1)).map(|entry| entry.message.clone()).collect()
+        };
+        for viewer in [Some(0), None] {
+            let lines = lines(viewer);
+            assert_eq!(lines.len(), 2);
+            assert!(lines.iter().all(|line| !line.contains(node_name) && !line.contains("g)")), "{:?}", lines);
+        }
+        // Those involved still get the details
+        assert!(lines(Some(1)).iter().any(|line| line.contains(node_name)));
+        assert!(lines(Some(2)).iter().any(|line| line.ends_with("g)!")));
+    }
+
+    #[test]
+    fn a_redacted_snapshot_leaves_out_the_seed() {
+        let game = Game::new(MatchRules { seed: Some(7), ..MatchRules::default() }).redacted_for(Some(0));
+        assert_eq!((game.seed, game.rules.seed), (0, None));
+    }
+
+    // Two humans and no bots in a turn-based match, dealt their perks but yet to pick
+    fn turn_based_match() -> Game {
+        let rules = MatchRules { human_count: 2, bot_count: 0, turn_based: true, seed: Some(3), ..MatchRules::default() };
+        let game = Game::new(rules);
+        assert_eq!(game.game_state, GameState::Draft);
+        game
+    }
+
+    // Picks both perks, which mines the round, and gives everyone STARTING_GOLD to spend
+    fn orders_phase(mut game: Game) -> Game {
+        assert!(game.apply(0, Action::PickPerk { card: 0 }));
+        assert!(game.apply(1, Action::PickPerk { card: 0 }));
+        assert_eq!(game.game_state, GameState::Playing);
+        for miner in &mut game.miners {
+            miner.gold = STARTING_GOLD;
+        }
+        game
+    }
+
+    fn ore_mined(game: &Game, miner_index: usize) -> f32 {
+        game.miners[miner_index].ore.iter().sum()
+    }
+
+    #[test]
+    fn a_turn_based_round_is_mined_from_the_levels_as_soon_as_the_draft_ends() {
+        let game = orders_phase(turn_based_match());
+        assert!(ore_mined(&game, 0) > 0.0);
+        assert_eq!(game.round_elapsed, Duration::ZERO);
+        assert_eq!(ore_mined(&game, 0), ore_mined(&orders_phase(turn_based_match()), 0));
+
+        // A faster miner brings up more on the same rolls
+        let mut faster = turn_based_match();
+        let miner = &mut faster.miners[0];
+        miner.gold = 1000.0;
+        let node = miner.next_in_branch(tech::Branch::Speed).unwrap();
+        assert!(miner.research(node));
+        let faster = orders_phase(faster);
+        assert!(ore_mined(&faster, 0) > ore_mined(&game, 0));
+
+        // Nothing is left to strike by hand
+        let mut game = game;
+        assert!(!game.apply(0, Action::Strike));
+    }
+
+    #[test]
+    fn turn_based_bids_stay_sealed_until_everyone_has_ended_their_turn() {
+        let mut game = orders_phase(turn_based_match());
+        assert!(game.apply(0, Action::Donate { amount: 50.0 }));
+        assert_eq!(game.visible_donation(0), None);
+        assert_eq!(game.observe(Some(1), 0).donation, None);
+
+        // Seat 0's orders are final, but the round waits for seat 1
+        assert!(game.apply(0, Action::EndTurn));
+        assert!(!game.apply(0, Action::Donate { amount: 50.0 }));
+        assert_eq!(game.game_state, GameState::Playing);
+        assert!(game.apply(1, Action::Donate { amount: 20.0 }));
+        assert!(game.apply(1, Action::EndTurn));
+        assert_eq!(game.game_state, GameState::RoundEnd);
+        assert_eq!(game.round_winners, vec![0]);
+    }
+
+    #[test]
+    fn held_dynamite_and_claim_jumps_go_off_when_the_turn_closes() {
+        let mut game = orders_phase(turn_based_match());
+        assert!(game.apply(0, Action::UseItem { item: Item::ClaimJump, target: Some(1) }));
+        assert!(game.apply(0, Action::UseItem { item: Item::Dynamite, target: Some(1) }));
+        assert_eq!(game.pending_items.len(), 2);
+        assert_eq!(game.miners[1].gold, STARTING_GOLD);
+        assert!(game.miners[1].stunned_for.is_zero());
+
+        assert!(game.apply(0, Action::EndTurn));
+        assert!(game.apply(1, Action::EndTurn));
+        assert!(game.pending_items.is_empty());
+        assert_close(&[game.miners[1].gold], &[STARTING_GOLD * (1.0 - CLAIM_JUMP_SHARE)]);
+        assert_eq!(game.miners[1].stunned_for, DYNAMITE_STUN);
+    }
+}
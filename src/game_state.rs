@@ -1,269 +1,610 @@
 use ggez::{Context, GameResult};
-use ggez::event::EventHandler;
-use ggez::input::mouse::MouseButton;
-use rand::Rng;
-use std::time::{Duration, Instant};
-
-use crate::miner::{Miner, MinerType};
-use crate::ui;
-
-// Game constants
-pub const MAX_ROUNDS: usize = 15;
-pub const ROUND_DURATION: Duration = Duration::from_secs(60); // 1 minute
-pub const WINDOW_WIDTH: f32 = 800.0;
-pub const WINDOW_HEIGHT: f32 = 600.0;
-
-pub enum GameState {
-    Playing,
-    RoundEnd,
-    GameOver,
-}
+use ggez::graphics;
+use ggez::event::{self, EventHandler, KeyCode, KeyMods};
+use ggez::input::mouse::{self, CursorIcon, MouseButton};
+use std::time::Duration;
+
+use crate::game::{Action, Game, GameState};
+use crate::input::KeyBindings;
+use crate::match_file::FileSession;
+use crate::market::Ore;
+use crate::miner::{Miner, MAX_HEALTH_UPGRADES};
+use crate::net::{ClientMessage, Lobby, LobbyPlayer, NetClient, ServerUpdate, MAX_NAME_CHARS};
+use crate::observer::StateStream;
+use crate::settings::Settings;
+use crate::tech;
+use crate::theme::{self, Theme};
+use crate::ui::{self, ButtonId, Layout, LobbyButton};
+
+pub const CONTRIBUTION_AMOUNTS: [f32; 5] = [10.0, 50.0, 100.0, 500.0, 1000.0];
+pub const DONATION_PERCENTAGES: [f32; 3] = [0.25, 0.5, 0.75];
+pub const BANK_STEP: f32 = 100.0; // Most each bank button moves at a time
+const DONATION_INPUT_MAX_DIGITS: usize = 7;
+const OBSERVER_RESULTS_PAUSE: Duration = Duration::from_secs(5); // Results stay up this long in a bots-only match
+const STATE_STREAM_INTERVAL: Duration = Duration::from_millis(100);
 
 pub struct MainState {
-    pub player: Miner,
-    pub bots: Vec<Miner>,
-    pub current_round: usize,
-    pub round_start_time: Instant,
-    pub game_state: GameState,
-    pub round_results: Option<Vec<(usize, f32)>>, // (miner_index, donated_gold)
-    pub past_results: Vec<bool>, // true for win, false for loss
+    pub game: Game,
+    pub net: Option<NetClient>, // Set when the game runs on a server
+    pub lobby: Option<Lobby>, // Set while a networked room waits for its host to start
+    pub match_file: Option<FileSession>, // Set when playing a turn of a play-by-file match
+    pub local_seats: Vec<usize>, // Seats played at this machine, in key binding order
+    pub active_seat: usize, // Human whose panels the mouse controls
+    pub layout: Layout,
+    pub mouse_position: (f32, f32), // In layout coordinates
+    pub pressed_button: Option<ButtonId>, // Button the left mouse button went down on
+    pub focused_button: Option<ButtonId>, // Button selected with Tab
+    pub key_bindings: Vec<KeyBindings>, // One set per local seat
+    pub donation_input: String, // Digits typed into the custom donation field
+    pub target_choice: Option<usize>, // Rival picked for sabotage items; see `item_target`
+    pub show_tech_tree: bool, // The tech tree screen covers the game panels
+    pub name_input: String, // Name being typed in the lobby
+    pub settings: Settings,
+    pub theme: Theme,
+    pub state_stream: Option<StateStream>, // Local dashboards following the match
+    since_published: Duration,
+    results_shown: Duration, // How long the round results have been up while observing
+    watching: bool, // Every local player is out and chose to watch the bots finish
 }
 
 impl MainState {
-    pub fn new(_ctx: &mut Context) -> GameResult<MainState> {
-        let player = Miner::new(MinerType::Player);
-        let mut bots = Vec::new();
+    // A local game plays every human seat; a networked one only the seat the server gave us,
+    // if any. Spectators and players in the lobby have no seat.
+    pub fn new(ctx: &mut Context, game: Game, lobby: Option<Lobby>, net: Option<NetClient>) -> GameResult<MainState> {
+        let (local_seats, key_bindings): (Vec<usize>, _) = match &net {
+            Some(client) => (client.seat.into_iter().collect(), KeyBindings::load_all(1)),
+            // Someone watching a bots-only match still gets the shared shortcuts
+            None => ((0..game.rules.human_count).collect(), KeyBindings::load_all(game.rules.human_count.max(1))),
+        };
+    
+        let (pixel_width, pixel_height) = graphics::drawable_size(ctx);
+        let layout = Layout::for_window(pixel_width, pixel_height);
+        graphics::set_screen_coordinates(ctx, layout.screen_rect())?;
         
-        // Create 3 bot miners
-        for _ in 0..3 {
-            bots.push(Miner::new(MinerType::Bot));
-        }
+        let settings = Settings::load();
+        let theme = Theme::load_or_default(&settings.theme);
     
         Ok(MainState {
-            player,
-            bots,
-            current_round: 1,
-            round_start_time: Instant::now(),
-            game_state: GameState::Playing,
-            round_results: None,
-            past_results: Vec::new(),
+            active_seat: local_seats.first().copied().unwrap_or(0),
+            game,
+            net,
+            lobby,
+            match_file: None,
+            local_seats,
+            layout,
+            mouse_position: (0.0, 0.0),
+            pressed_button: None,
+            focused_button: None,
+            key_bindings,
+            donation_input: String::new(),
+            target_choice: None,
+            show_tech_tree: false,
+            name_input: String::new(),
+            settings,
+            theme,
+            state_stream: None,
+            since_published: Duration::ZERO,
+            results_shown: Duration::ZERO,
+            watching: false,
         })
     }
-    
 
-    pub fn bot_make_decision(&mut self, bot_index: usize) {
-        let bot = &mut self.bots[bot_index];
-        if !bot.alive {
-            return;
-        }
+    // The human whose stats, upgrades and donations are on screen
+    pub fn active_miner(&self) -> &Miner {
+        &self.game.miners[self.active_seat]
+    }
+
+    // Watching rather than playing: a spectator or a bots-only match
+    pub fn is_observer(&self) -> bool {
+        self.local_seats.is_empty()
+    }
 
-        let mut rng = rand::thread_rng();
-        let decision = rng.gen_range(0..3); // 0: Upgrade pickaxe, 1: Upgrade mine, 2: Contribute gold
+    // Key bindings of a seat played at this machine
+    pub fn bindings_for(&self, seat: usize) -> Option<&KeyBindings> {
+        let index = self.local_seats.iter().position(|local| *local == seat)?;
+        self.key_bindings.get(index)
+    }
 
-        match decision {
-            0 => {
-                if bot.pickaxe_level < 4 && bot.gold >= bot.pickaxe_upgrade_cost() {
-                    bot.upgrade_pickaxe();
-                }
+    // Sends an action to the server, or applies it straight away in a local game
+    fn submit(&mut self, seat: usize, action: Action) -> bool {
+        match &mut self.net {
+            Some(client) => {
+                client.send(ClientMessage::Action { action });
+                true
             },
-            1 => {
-                if bot.mine_level < 4 && bot.gold >= bot.mine_upgrade_cost() {
-                    bot.upgrade_mine();
+            None => {
+                let done = self.game.apply(seat, action);
+                if let (true, Some(session)) = (done, &mut self.match_file) {
+                    session.played(&self.game, action);
                 }
+                done
             },
-            2 => {
-                // Contribute a random portion of gold
-                let contribution_percentage = rng.gen_range(0.1..0.6); // 10% to 60% of current gold
-                let contribution = bot.gold * contribution_percentage;
-                bot.contribute_gold(contribution);
-            },
-            _ => {}
         }
     }
 
-    pub fn end_round(&mut self) {
-        // Collect all miners' donated gold amounts (including player)
-        let mut results = Vec::new();
-        
-        // Add player
-        results.push((0, self.player.donated_gold));
-        
-        // Add bots
-        for (i, bot) in self.bots.iter().enumerate() {
-            if bot.alive {
-                results.push((i + 1, bot.donated_gold));
-            }
+    // Plays one seat of a play-by-file match; the other players' seats wait for their own turns
+    pub fn play_from_file(&mut self, session: FileSession) {
+        self.local_seats = vec![session.seat];
+        self.active_seat = session.seat;
+        self.key_bindings.truncate(1);
+        self.match_file = Some(session);
+    }
+
+    fn send(&mut self, message: ClientMessage) {
+        if let Some(client) = &mut self.net {
+            client.send(message);
         }
-        
-        // Sort by donated gold (highest first)
-        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-        
-        // Record if the player won this round (was ranked #1)
-        let player_won = results.first().map_or(false, |(index, _)| *index == 0);
-        self.past_results.push(player_won);
-        
-        // Assign damage based on position
-        for (position, (miner_index, _)) in results.iter().enumerate() {
-            let damage = position as i32;
-            
-            if *miner_index == 0 {
-                // Player
-                self.player.take_damage(damage);
-            } else {
-                // Bot
-                self.bots[*miner_index - 1].take_damage(damage);
-            }
+    }
+
+    // Our entry in the lobby; None while spectating
+    pub fn lobby_player(&self) -> Option<&LobbyPlayer> {
+        let id = self.net.as_ref()?.id;
+        self.lobby.as_ref()?.player(id)
+    }
+
+    pub fn is_lobby_host(&self) -> bool {
+        match (&self.lobby, &self.net) {
+            (Some(lobby), Some(client)) => lobby.host() == Some(client.id),
+            _ => false,
         }
-        
-        // Reset donated gold
-        self.player.donated_gold = 0.0;
-        for bot in &mut self.bots {
-            bot.donated_gold = 0.0;
+    }
+
+    // Applies the latest lobby or snapshot from the server and follows seat changes
+    fn sync_with_server(&mut self, ctx: &mut Context) {
+        let client = match &mut self.net {
+            Some(client) => client,
+            None => return,
+        };
+
+        match client.poll() {
+            Some(ServerUpdate::Lobby(lobby)) => self.lobby = Some(lobby),
+            Some(ServerUpdate::Game(game)) => {
+                self.lobby = None;
+                self.game = game;
+            },
+            None => {},
         }
-        
-        // Store results for display
-        self.round_results = Some(results);
-        
-        // Check if player is dead
-        if !self.player.alive {
-            self.game_state = GameState::GameOver;
-        } else if self.current_round >= MAX_ROUNDS {
-            self.game_state = GameState::GameOver;
-        } else {
-            // Move to next round
-            self.game_state = GameState::RoundEnd;
+
+        let seats: Vec<usize> = client.seat.into_iter().collect();
+        if seats != self.local_seats {
+            self.active_seat = seats.first().copied().unwrap_or(0);
+            self.local_seats = seats;
+            self.donation_input.clear();
+            self.focused_button = None;
+        }
+
+        if !client.is_connected() {
+            eprintln!("Lost connection to the server");
+            event::quit(ctx);
         }
     }
 
-    pub fn start_next_round(&mut self) {
-        self.current_round += 1;
-        self.round_start_time = Instant::now();
-        self.game_state = GameState::Playing;
-        self.round_results = None;
+    // Continue and Restart wait for every player in a networked game
+    fn start_next_round(&mut self) {
+        self.results_shown = Duration::ZERO;
+        self.show_tech_tree = false;
+        if self.game.all_humans_out() {
+            self.watching = true;
+        }
+        if !self.submit(self.active_seat, Action::Continue) {
+            self.game.start_next_round();
+        }
     }
 
     pub fn restart_game(&mut self) {
-        self.player = Miner::new(MinerType::Player);
-        self.bots = Vec::new();
-        for _ in 0..3 {
-            self.bots.push(Miner::new(MinerType::Bot));
+        self.watching = false;
+        self.show_tech_tree = false;
+        if !self.submit(self.active_seat, Action::Restart) {
+            self.game.restart();
+            self.active_seat = self.local_seats.first().copied().unwrap_or(0);
+        }
+    }
+
+    /// Returns the button under the given screen position, whether or not it is enabled.
+    pub fn button_at(&self, x: f32, y: f32) -> Option<ButtonId> {
+        ui::visible_buttons(self)
+            .into_iter()
+            .find(|id| ui::button_rect(self, *id).contains([x, y]))
+    }
+
+    pub fn is_button_enabled(&self, id: ButtonId) -> bool {
+        self.is_action_enabled(self.active_seat, id)
+    }
+
+    pub fn press_button(&mut self, id: ButtonId) {
+        self.perform_action(self.active_seat, id);
+    }
+
+    // Whether the human in `seat` can use a button right now
+    pub fn is_action_enabled(&self, seat: usize, id: ButtonId) -> bool {
+        // Spectators can only look
+        let plays_seat = self.local_seats.contains(&seat);
+        let miner = &self.game.miners[seat];
+        // Orders are final once a turn-based turn is committed
+        let can_act = miner.alive && !miner.turn_done;
+
+        match id {
+            ButtonId::Lobby(button) => self.is_lobby_action_enabled(button),
+            ButtonId::CycleTheme => true,
+            // Someone watching bots locally still moves the match along
+            // A match played by file is only ever started afresh with `--new-match`
+            ButtonId::Restart if self.match_file.is_some() => false,
+            ButtonId::Continue | ButtonId::Restart if self.net.is_none() => true,
+            ButtonId::SkipToResults => self.net.is_none() && self.game.all_humans_out(),
+            _ if !plays_seat => false,
+            ButtonId::TechTree => true,
+            ButtonId::ResearchNext(branch) => {
+                can_act && miner.next_in_branch(branch).is_some_and(|node| miner.gold >= tech::tree().nodes[node].cost)
+            },
+            ButtonId::TechNode(node) => {
+                can_act && miner.can_research(node) && miner.gold >= tech::tree().nodes[node].cost
+            },
+            ButtonId::BuyHeal => can_act && miner.health < miner.max_health && miner.gold >= miner.heal_cost(),
+            ButtonId::BuyShield => can_act && !miner.shielded && miner.gold >= miner.shield_cost(),
+            ButtonId::UpgradeMaxHealth => {
+                can_act && miner.max_health_level < MAX_HEALTH_UPGRADES && miner.gold >= miner.max_health_upgrade_cost()
+            },
+            ButtonId::CycleTarget => can_act && self.game.rivals(seat).len() > 1,
+            ButtonId::UseItem(item) => {
+                can_act
+                    && self.game.available_items().contains(&item)
+                    && miner.cooldown_left(item).is_zero()
+                    && miner.gold >= item.cost()
+                    && (!item.needs_target() || self.item_target(seat).is_some())
+            },
+            ButtonId::SellOre(ore) => can_act && miner.sellable(ore) >= 1.0,
+            ButtonId::SellAll => can_act && Ore::ALL.iter().any(|ore| miner.sellable(*ore) >= 1.0),
+            ButtonId::PickPerk(i) => miner.alive && i < miner.perk_offer.len(),
+            ButtonId::Donate(i) => can_act && CONTRIBUTION_AMOUNTS[i] <= miner.gold,
+            ButtonId::DonatePercent(i) => can_act && self.percentage_donation(seat, i) >= 1.0,
+            ButtonId::DonateAll => can_act && miner.gold > 0.0,
+            // There is one custom amount, typed by whoever has the mouse, so other
+            // hot-seat players can't send it with their own keys
+            ButtonId::DonateCustom => {
+                can_act
+                    && seat == self.active_seat
+                    && self.custom_donation().is_some_and(|amount| amount <= miner.gold)
+            },
+            ButtonId::DonationField | ButtonId::DonationSlider => can_act && seat == self.active_seat,
+            ButtonId::Strike => can_act && !self.game.rules.turn_based && miner.stunned_for.is_zero(),
+            ButtonId::Deposit => can_act && miner.gold >= 1.0,
+            ButtonId::Withdraw => can_act && miner.deposit >= 1.0,
+            ButtonId::Borrow => can_act && miner.credit_left() >= 1.0,
+            ButtonId::RepayLoan => can_act && miner.loan > 0.0 && miner.gold >= 1.0,
+            ButtonId::EndTurn => can_act && self.game.rules.turn_based,
+            ButtonId::SelectSeat(other) => other != self.active_seat,
+            ButtonId::Continue | ButtonId::Restart => true,
         }
-        self.current_round = 1;
-        self.round_start_time = Instant::now();
-        self.game_state = GameState::Playing;
-        self.round_results = None;
-        self.past_results = Vec::new();
     }
 
-    pub fn handle_game_ui_click(&mut self, x: f32, y: f32) {
-        // Check pickaxe upgrade button
-        if x >= 50.0 && x <= 250.0 && y >= 150.0 && y <= 200.0 {
-            self.player.upgrade_pickaxe();
+    // The rival sabotage items act on: the one picked with the target button while
+    // they are still in the match, otherwise the first rival left
+    pub fn item_target(&self, seat: usize) -> Option<usize> {
+        let rivals = self.game.rivals(seat);
+        self.target_choice
+            .filter(|target| rivals.contains(target))
+            .or_else(|| rivals.first().copied())
+    }
+
+    fn cycle_target(&mut self, seat: usize) {
+        let rivals = self.game.rivals(seat);
+        let next = match self.item_target(seat).and_then(|target| rivals.iter().position(|i| *i == target)) {
+            Some(position) => rivals.get((position + 1) % rivals.len()),
+            None => rivals.first(),
+        };
+        self.target_choice = next.copied();
+    }
+
+    fn is_lobby_action_enabled(&self, button: LobbyButton) -> bool {
+        match button {
+            LobbyButton::Name | LobbyButton::Color | LobbyButton::Ready => self.lobby_player().is_some(),
+            LobbyButton::Start => self.is_lobby_host() && self.lobby.as_ref().is_some_and(|lobby| lobby.can_start()),
+            LobbyButton::RuleDown(_) | LobbyButton::RuleUp(_) => self.is_lobby_host(),
         }
-        
-        // Check mine upgrade button
-        if x >= 50.0 && x <= 250.0 && y >= 220.0 && y <= 270.0 {
-            self.player.upgrade_mine();
+    }
+
+    // Performs a button's action on behalf of the human in `seat`
+    pub fn perform_action(&mut self, seat: usize, id: ButtonId) {
+        if !self.is_action_enabled(seat, id) {
+            return;
         }
-        
-        // Check contribute buttons
-        if x >= 400.0 && x <= 550.0 {
-            let contribution_amounts = [10.0, 50.0, 100.0, 500.0, 1000.0];
-            
-            // Check numeric contribution options
-            for (i, amount) in contribution_amounts.iter().enumerate() {
-                let y_pos = 180.0 + (i as f32 * 40.0);
-                
-                if y >= y_pos && y <= y_pos + 30.0 && *amount <= self.player.gold {
-                    self.player.contribute_gold(*amount);
-                    break;
+
+        match id {
+            ButtonId::ResearchNext(branch) => {
+                if let Some(node) = self.game.miners[seat].next_in_branch(branch) {
+                    self.submit(seat, Action::Research { node });
                 }
-            }
-            
-            // Check "All" option
-            let all_y_pos = 180.0 + (contribution_amounts.len() as f32 * 40.0);
-            
-            if y >= all_y_pos && y <= all_y_pos + 30.0 && self.player.gold > 0.0 {
-                self.player.contribute_gold(self.player.gold);
-            }
+            },
+            ButtonId::TechNode(node) => {
+                self.submit(seat, Action::Research { node });
+            },
+            ButtonId::TechTree => {
+                // Focus may be on a button the other screen doesn't have
+                self.show_tech_tree = !self.show_tech_tree;
+                self.focused_button = None;
+            },
+            ButtonId::BuyHeal => {
+                self.submit(seat, Action::BuyHeal);
+            },
+            ButtonId::BuyShield => {
+                self.submit(seat, Action::BuyShield);
+            },
+            ButtonId::UpgradeMaxHealth => {
+                self.submit(seat, Action::UpgradeMaxHealth);
+            },
+            ButtonId::CycleTarget => self.cycle_target(seat),
+            ButtonId::UseItem(item) => {
+                let target = if item.needs_target() { self.item_target(seat) } else { None };
+                self.submit(seat, Action::UseItem { item, target });
+            },
+            ButtonId::SellOre(ore) => {
+                self.submit(seat, Action::Sell { ore });
+            },
+            ButtonId::SellAll => {
+                self.submit(seat, Action::SellAll);
+            },
+            ButtonId::PickPerk(card) => {
+                // The mouse moves on to the next hot-seat player still picking
+                if self.submit(seat, Action::PickPerk { card }) && self.net.is_none() && seat == self.active_seat {
+                    if let Some(next) = self.local_seats.iter().find(|s| self.game.miners[**s].is_drafting()) {
+                        self.active_seat = *next;
+                    }
+                }
+            },
+            ButtonId::Donate(i) => {
+                self.submit(seat, Action::Donate { amount: CONTRIBUTION_AMOUNTS[i] });
+            },
+            ButtonId::DonatePercent(i) => {
+                let amount = self.percentage_donation(seat, i);
+                self.submit(seat, Action::Donate { amount });
+            },
+            ButtonId::DonateAll => {
+                self.submit(seat, Action::DonateAll);
+            },
+            ButtonId::Strike => {
+                self.submit(seat, Action::Strike);
+            },
+            ButtonId::Deposit => {
+                let amount = self.game.miners[seat].gold.min(BANK_STEP);
+                self.submit(seat, Action::Deposit { amount });
+            },
+            ButtonId::Withdraw => {
+                let amount = self.game.miners[seat].deposit.min(BANK_STEP);
+                self.submit(seat, Action::Withdraw { amount });
+            },
+            ButtonId::Borrow => {
+                let amount = self.game.miners[seat].credit_left().min(BANK_STEP);
+                self.submit(seat, Action::Borrow { amount });
+            },
+            ButtonId::RepayLoan => {
+                // As much of the loan as the gold on hand covers
+                let miner = &self.game.miners[seat];
+                let amount = miner.gold.min(miner.loan);
+                self.submit(seat, Action::Repay { amount });
+            },
+            ButtonId::EndTurn => {
+                // Like the draft, the mouse moves on to the next hot-seat player still deciding
+                if self.submit(seat, Action::EndTurn) && self.net.is_none() && seat == self.active_seat {
+                    let deciding = |s: &&usize| self.game.miners[**s].alive && !self.game.miners[**s].turn_done;
+                    if let Some(next) = self.local_seats.iter().find(deciding) {
+                        self.active_seat = *next;
+                    }
+                }
+            },
+            ButtonId::DonateCustom => {
+                let amount = self.custom_donation().unwrap_or(0.0);
+                if self.submit(seat, Action::Donate { amount }) {
+                    self.donation_input.clear();
+                }
+            },
+            ButtonId::DonationField => self.focused_button = Some(ButtonId::DonationField),
+            ButtonId::DonationSlider => {
+                let (x, _) = self.mouse_position;
+                self.set_donation_from_slider(x);
+            },
+            ButtonId::SelectSeat(other) => {
+                // The custom amount belongs to whoever typed it
+                self.active_seat = other;
+                self.donation_input.clear();
+            },
+            ButtonId::Continue => self.start_next_round(),
+            ButtonId::SkipToResults => self.game.fast_forward(),
+            ButtonId::Restart => self.restart_game(),
+            ButtonId::CycleTheme => self.cycle_theme(),
+            ButtonId::Lobby(button) => self.perform_lobby_action(button),
         }
     }
 
-    pub fn handle_round_end_ui_click(&mut self, x: f32, y: f32) {
-        if let Some(results) = &self.round_results {
-            let mut y_offset = 150.0;
-            
-            // Count number of results
-            y_offset += 30.0 * results.len() as f32 + 30.0;
-            
-            // Debugging output (useful for development)
-            // println!("Click at ({}, {}), button at y: {} to {}", 
-            //          x, y, y_offset + 30.0, y_offset + 70.0);
-            
-            // Make the continue button larger and more forgiving with a wider hit area
-            // This helps fix the issue with the continue button not always responding
-            let button_x_min = WINDOW_WIDTH / 2.0 - 100.0; // Wider x range
-            let button_x_max = WINDOW_WIDTH / 2.0 + 100.0;
-            let button_y_min = y_offset + 20.0; // Start a bit higher
-            let button_y_max = y_offset + 80.0; // End a bit lower
-            
-            if x >= button_x_min && x <= button_x_max &&
-               y >= button_y_min && y <= button_y_max {
-                self.start_next_round();
-            }
+    fn perform_lobby_action(&mut self, button: LobbyButton) {
+        let (player, rules) = match (self.lobby_player(), &self.lobby) {
+            (Some(player), Some(lobby)) => (player.clone(), lobby.rules.clone()),
+            _ => return,
+        };
+
+        match button {
+            LobbyButton::Name => {
+                self.name_input = player.name;
+                self.focused_button = Some(ButtonId::Lobby(LobbyButton::Name));
+            },
+            LobbyButton::Color => self.send(ClientMessage::SetColor { color: player.color + 1 }),
+            LobbyButton::Ready => self.send(ClientMessage::SetReady { ready: !player.ready }),
+            LobbyButton::Start => self.send(ClientMessage::Start),
+            LobbyButton::RuleDown(field) | LobbyButton::RuleUp(field) => {
+                let mut rules = rules;
+                rules.adjust(field, matches!(button, LobbyButton::RuleUp(_)));
+                self.send(ClientMessage::SetRules { rules });
+            },
         }
     }
 
-    pub fn handle_game_over_ui_click(&mut self, x: f32, y: f32) {
-        // Check restart button
-        if x >= WINDOW_WIDTH / 2.0 - 75.0 && x <= WINDOW_WIDTH / 2.0 + 75.0 &&
-           y >= WINDOW_HEIGHT / 2.0 + 30.0 && y <= WINDOW_HEIGHT / 2.0 + 70.0 {
-            self.restart_game();
+    // Keys typed while the lobby name field has focus; returns false if not consumed
+    fn handle_name_field_key(&mut self, keycode: KeyCode) -> bool {
+        match keycode {
+            KeyCode::Back => {
+                self.name_input.pop();
+            },
+            KeyCode::Return | KeyCode::NumpadEnter => {
+                let name = std::mem::take(&mut self.name_input);
+                self.send(ClientMessage::SetName { name });
+                self.focused_button = None;
+            },
+            KeyCode::Escape => self.focused_button = None,
+            KeyCode::Tab => return false,
+            _ => {}, // Characters arrive through text_input_event
+        }
+        true
+    }
+
+    pub fn cycle_theme(&mut self) {
+        let next = theme::next_theme_id(&self.settings.theme);
+        match Theme::load(next) {
+            Ok(theme) => {
+                self.theme = theme;
+                self.settings.theme = next.to_string();
+                self.settings.save();
+            },
+            Err(message) => eprintln!("Failed to switch theme: {}", message),
+        }
+    }
+
+    // Whole gold pieces only, so the donation panel never shows fractions
+    pub fn percentage_donation(&self, seat: usize, index: usize) -> f32 {
+        (self.game.miners[seat].gold * DONATION_PERCENTAGES[index]).floor()
+    }
+
+    // Amount typed into (or picked with the slider for) the custom donation field
+    pub fn custom_donation(&self) -> Option<f32> {
+        self.donation_input.parse::<u32>().ok().filter(|amount| *amount > 0).map(|amount| amount as f32)
+    }
+
+    // Fraction of the player's gold the custom amount represents, for the slider handle
+    pub fn custom_donation_fraction(&self) -> f32 {
+        let gold = self.active_miner().gold;
+        if gold <= 0.0 {
+            return 0.0;
+        }
+        (self.custom_donation().unwrap_or(0.0) / gold).clamp(0.0, 1.0)
+    }
+
+    pub fn set_donation_from_slider(&mut self, x: f32) {
+        let track = ui::button_rect(self, ButtonId::DonationSlider);
+        let fraction = ((x - track.x) / track.w).clamp(0.0, 1.0);
+        self.set_donation_fraction(fraction);
+    }
+
+    fn set_donation_fraction(&mut self, fraction: f32) {
+        let amount = (self.active_miner().gold * fraction.clamp(0.0, 1.0)).round();
+        self.donation_input = if amount >= 1.0 { format!("{:.0}", amount) } else { String::new() };
+    }
+
+    // Keys typed while the custom donation field has focus; returns false if not consumed
+    fn handle_donation_field_key(&mut self, keycode: KeyCode) -> bool {
+        match keycode {
+            KeyCode::Back => {
+                self.donation_input.pop();
+            },
+            KeyCode::Return | KeyCode::NumpadEnter => self.press_button(ButtonId::DonateCustom),
+            KeyCode::Escape => self.focused_button = None,
+            KeyCode::Tab => return false,
+            _ => {}, // Characters arrive through text_input_event
+        }
+        true
+    }
+
+    // Moves keyboard focus to the next (or previous) enabled button, wrapping around
+    pub fn cycle_focus(&mut self, backwards: bool) {
+        let mut buttons: Vec<ButtonId> = ui::visible_buttons(self)
+            .into_iter()
+            .filter(|id| self.is_button_enabled(*id))
+            .collect();
+
+        if buttons.is_empty() {
+            self.focused_button = None;
+            return;
+        }
+
+        if backwards {
+            buttons.reverse();
+        }
+
+        let next = match self.focused_button.and_then(|id| buttons.iter().position(|b| *b == id)) {
+            Some(i) => buttons[(i + 1) % buttons.len()],
+            None => buttons[0],
+        };
+        self.focused_button = Some(next);
+    }
+
+    // Show a hand cursor whenever the mouse rests on something clickable
+    fn update_cursor(&self, ctx: &mut Context) {
+        let (x, y) = self.mouse_position;
+        let clickable = self.button_at(x, y).is_some_and(|id| self.is_button_enabled(id));
+        let cursor = if clickable { CursorIcon::Hand } else { CursorIcon::Default };
+
+        if mouse::cursor_type(ctx) != cursor {
+            mouse::set_cursor_type(ctx, cursor);
         }
     }
 }
 
 impl EventHandler for MainState {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        // Only update player and bots when in Playing state
-        // This fixes issue with gold accumulating during round end screen
-        match self.game_state {
-            GameState::Playing => {
-                // Update player and bots
-                self.player.update(ctx);
-                for bot in &mut self.bots {
-                    bot.update(ctx);
-                }
-                
-                // Make random decisions for bots
-                for i in 0..self.bots.len() {
-                    self.bot_make_decision(i);
-                }
+        match &mut self.net {
+            // The server runs the clock; just show its latest state
+            Some(_) => self.sync_with_server(ctx),
+            None => {
+                let dt = ggez::timer::delta(ctx);
+                self.game.tick(dt);
 
-                // Check if round is over
-                let now = Instant::now();
-                let round_elapsed = now.duration_since(self.round_start_time);
-                if round_elapsed >= ROUND_DURATION {
-                    self.end_round();
+                // Nobody is at the table to press Continue in a bots-only match, and
+                // eliminated players who chose to watch shouldn't have to keep pressing it
+                if (self.is_observer() || self.watching) && self.game.game_state == GameState::RoundEnd {
+                    self.results_shown += dt;
+                    if self.results_shown >= OBSERVER_RESULTS_PAUSE {
+                        self.results_shown = Duration::ZERO;
+                        self.game.start_next_round();
+                    }
                 }
             },
-            GameState::RoundEnd => {
-                // Wait for player to continue - no updates to miners
-            },
-            GameState::GameOver => {
-                // Wait for player to restart - no updates to miners
-            },
         }
 
+        if let Some(stream) = &self.state_stream {
+            self.since_published += ggez::timer::delta(ctx);
+            if self.lobby.is_none() && self.since_published >= STATE_STREAM_INTERVAL {
+                self.since_published = Duration::ZERO;
+                stream.publish(&self.game);
+            }
+        }
+
+        // Buttons appear, move and change affordability without the mouse moving
+        self.update_cursor(ctx);
+
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        use ggez::graphics::{self, Color};
-        graphics::clear(ctx, Color::WHITE);
+        graphics::clear(ctx, self.theme.background);
 
         // Draw UI based on game state
-        match self.game_state {
+        if self.lobby.is_some() {
+            ui::draw_lobby_ui(self, ctx)?;
+            graphics::present(ctx)?;
+            return Ok(());
+        }
+
+        match self.game.game_state {
+            GameState::Draft | GameState::Playing | GameState::GameOver if self.is_observer() => {
+                ui::draw_observer_ui(self, ctx)?;
+            },
+            // Players who are out watch the bots on the observer table
+            GameState::Draft | GameState::Playing if self.game.all_humans_out() => {
+                ui::draw_observer_ui(self, ctx)?;
+            },
+            GameState::Draft => {
+                ui::draw_draft_ui(self, ctx)?;
+            },
+            GameState::Playing if self.show_tech_tree => {
+                ui::draw_tech_tree_ui(self, ctx)?;
+            },
             GameState::Playing => {
                 ui::draw_game_ui(self, ctx)?;
             },
@@ -286,21 +627,147 @@ impl EventHandler for MainState {
         x: f32,
         y: f32,
     ) {
+        let (x, y) = self.layout.logical_position(x, y);
+
         if button == MouseButton::Left {
-            match self.game_state {
-                GameState::Playing => {
-                    // Handle UI clicks during gameplay
-                    self.handle_game_ui_click(x, y);
-                },
-                GameState::RoundEnd => {
-                    // Handle round end UI clicks
-                    self.handle_round_end_ui_click(x, y);
-                },
-                GameState::GameOver => {
-                    // Handle game over UI clicks
-                    self.handle_game_over_ui_click(x, y);
-                },
+            self.mouse_position = (x, y);
+            self.pressed_button = self.button_at(x, y).filter(|id| self.is_button_enabled(*id));
+
+            // Clicking anywhere else takes focus away from the text fields
+            let field_focused = matches!(
+                self.focused_button,
+                Some(ButtonId::DonationField) | Some(ButtonId::Lobby(LobbyButton::Name))
+            );
+            if field_focused && self.pressed_button != self.focused_button {
+                self.focused_button = None;
             }
+
+            // The slider follows the mouse from the moment it is grabbed
+            if self.pressed_button == Some(ButtonId::DonationSlider) {
+                self.set_donation_from_slider(x);
+            }
+        }
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) {
+        let (x, y) = self.layout.logical_position(x, y);
+
+        if button == MouseButton::Left {
+            // Only fire if the button is released over the same button it was pressed on
+            if let Some(id) = self.pressed_button.take() {
+                if self.button_at(x, y) == Some(id) {
+                    self.press_button(id);
+                }
+            }
+        }
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        keymods: KeyMods,
+        repeat: bool,
+    ) {
+        let visible = ui::visible_buttons(self);
+        if self.focused_button == Some(ButtonId::DonationField)
+            && visible.contains(&ButtonId::DonationField)
+            && self.handle_donation_field_key(keycode)
+        {
+            return;
+        }
+
+        let name_field = ButtonId::Lobby(LobbyButton::Name);
+        if self.focused_button == Some(name_field)
+            && visible.contains(&name_field)
+            && self.handle_name_field_key(keycode)
+        {
+            return;
+        }
+
+        // Arrow keys nudge the slider in 5% steps while it has focus
+        if self.focused_button == Some(ButtonId::DonationSlider) {
+            let step = match keycode {
+                KeyCode::Left => Some(-0.05),
+                KeyCode::Right => Some(0.05),
+                _ => None,
+            };
+            if let Some(step) = step {
+                self.set_donation_fraction(self.custom_donation_fraction() + step);
+                return;
+            }
+        }
+
+        match keycode {
+            // Escape backs out of the tech tree before it quits the game
+            KeyCode::Escape if self.show_tech_tree => {
+                self.show_tech_tree = false;
+                self.focused_button = None;
+            },
+            KeyCode::Escape => event::quit(ctx),
+            KeyCode::Tab => self.cycle_focus(keymods.contains(KeyMods::SHIFT)),
+            KeyCode::Return | KeyCode::Space if self.focused_button.is_some() => {
+                let focused = self.focused_button.filter(|id| ui::visible_buttons(self).contains(id));
+                if let Some(id) = focused {
+                    self.press_button(id);
+                }
+            },
+            _ => {
+                // Several buttons can share a key as long as they never show at the same time.
+                // Every hot-seat player has their own bindings and acts on their own miner.
+                // Holding the strike key down doesn't keep striking.
+                for index in 0..self.key_bindings.len() {
+                    let seat = self.local_seats.get(index).copied().unwrap_or(self.active_seat);
+                    let bound = self.key_bindings[index].buttons_for(keycode);
+                    let pressed = bound.into_iter().find(|id| visible.contains(id) && !(repeat && *id == ButtonId::Strike));
+                    if let Some(id) = pressed {
+                        self.perform_action(seat, id);
+                    }
+                }
+            },
+        }
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) {
+        if self.focused_button == Some(ButtonId::Lobby(LobbyButton::Name))
+            && !character.is_control()
+            && self.name_input.chars().count() < MAX_NAME_CHARS
+        {
+            self.name_input.push(character);
+            return;
+        }
+
+        if self.focused_button == Some(ButtonId::DonationField)
+            && character.is_ascii_digit()
+            && self.donation_input.len() < DONATION_INPUT_MAX_DIGITS
+        {
+            // No leading zeros
+            if !(self.donation_input.is_empty() && character == '0') {
+                self.donation_input.push(character);
+            }
+        }
+    }
+
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32, _dx: f32, _dy: f32) {
+        self.mouse_position = self.layout.logical_position(x, y);
+        let (x, _) = self.mouse_position;
+
+        if self.pressed_button == Some(ButtonId::DonationSlider) {
+            self.set_donation_from_slider(x);
+        }
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        // Keep drawing in layout units so panels, fonts and hit areas all follow the window
+        self.layout = Layout::for_window(width, height);
+        if let Err(e) = graphics::set_screen_coordinates(ctx, self.layout.screen_rect()) {
+            eprintln!("Failed to resize the drawing area: {}", e);
         }
     }
 }
\ No newline at end of file
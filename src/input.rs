@@ -0,0 +1,393 @@
+use ggez::event::KeyCode;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::game_state::{CONTRIBUTION_AMOUNTS, DONATION_PERCENTAGES};
+use crate::items::Item;
+use crate::market::Ore;
+use crate::perks::DRAFT_HAND;
+use crate::rules::RuleField;
+use crate::tech::Branch;
+use crate::ui::{ButtonId, LobbyButton, QUICK_RESEARCH};
+
+pub const KEY_BINDINGS_FILE: &str = "keybindings.toml";
+
+// Keys that can be bound in the config file, looked up by their `Debug` name
+const BINDABLE_KEYS: [KeyCode; 62] = [
+    KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G,
+    KeyCode::H, KeyCode::I, KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N,
+    KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R, KeyCode::S, KeyCode::T, KeyCode::U,
+    KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+    KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+    KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+    KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    KeyCode::Return, KeyCode::Space, KeyCode::Back, KeyCode::Minus, KeyCode::Equals,
+    KeyCode::Slash, KeyCode::Period, KeyCode::Comma, KeyCode::Semicolon, KeyCode::Grave,
+    KeyCode::Apostrophe, KeyCode::LBracket, KeyCode::RBracket, KeyCode::Backslash,
+];
+
+// Parses a key name as written in the config file ("Q", "1", "F2", "Space")
+pub fn parse_key(name: &str) -> Option<KeyCode> {
+    let name = name.trim();
+    let name = if name.len() == 1 && name.chars().all(|c| c.is_ascii_digit()) {
+        format!("Key{}", name)
+    } else {
+        name.to_string()
+    };
+
+    BINDABLE_KEYS
+        .iter()
+        .copied()
+        .find(|key| format!("{:?}", key).eq_ignore_ascii_case(&name))
+}
+
+// Short label shown on buttons
+pub fn key_label(key: KeyCode) -> String {
+    let name = format!("{:?}", key);
+    match name.strip_prefix("Key") {
+        Some(digit) if !digit.is_empty() => digit.to_string(),
+        _ => name,
+    }
+}
+
+// Name used for a button's action in the config file
+pub fn action_name(id: ButtonId) -> String {
+    match id {
+        ButtonId::ResearchNext(branch) => format!("research_{}", branch_name(branch)),
+        ButtonId::TechTree => "tech_tree".to_string(),
+        ButtonId::TechNode(node) => format!("research_node{}", node + 1),
+        ButtonId::BuyHeal => "buy_heal".to_string(),
+        ButtonId::BuyShield => "buy_shield".to_string(),
+        ButtonId::UpgradeMaxHealth => "upgrade_max_health".to_string(),
+        ButtonId::CycleTarget => "cycle_target".to_string(),
+        ButtonId::UseItem(item) => format!("use_{}", item_name(item)),
+        ButtonId::SellOre(ore) => format!("sell_{}", ore_name(ore)),
+        ButtonId::SellAll => "sell_all".to_string(),
+        ButtonId::PickPerk(i) => format!("pick_perk{}", i + 1),
+        ButtonId::Donate(i) => format!("donate_{:.0}", CONTRIBUTION_AMOUNTS[i]),
+        ButtonId::DonatePercent(i) => format!("donate_{:.0}_percent", DONATION_PERCENTAGES[i] * 100.0),
+        ButtonId::DonateAll => "donate_all".to_string(),
+        ButtonId::DonationField => "edit_donation".to_string(),
+        ButtonId::DonationSlider => "donation_slider".to_string(),
+        ButtonId::DonateCustom => "donate_custom".to_string(),
+        ButtonId::Strike => "strike".to_string(),
+        ButtonId::Deposit => "deposit".to_string(),
+        ButtonId::Withdraw => "withdraw".to_string(),
+        ButtonId::Borrow => "borrow".to_string(),
+        ButtonId::RepayLoan => "repay_loan".to_string(),
+        ButtonId::EndTurn => "end_turn".to_string(),
+        ButtonId::Continue => "continue".to_string(),
+        ButtonId::SkipToResults => "skip_to_results".to_string(),
+        ButtonId::Restart => "restart".to_string(),
+        ButtonId::CycleTheme => "cycle_theme".to_string(),
+        ButtonId::SelectSeat(seat) => format!("select_player{}", seat + 1),
+        ButtonId::Lobby(LobbyButton::Name) => "edit_name".to_string(),
+        ButtonId::Lobby(LobbyButton::Color) => "cycle_color".to_string(),
+        ButtonId::Lobby(LobbyButton::Ready) => "toggle_ready".to_string(),
+        ButtonId::Lobby(LobbyButton::Start) => "start_match".to_string(),
+        ButtonId::Lobby(LobbyButton::RuleDown(field)) => format!("{}_down", rule_name(field)),
+        ButtonId::Lobby(LobbyButton::RuleUp(field)) => format!("{}_up", rule_name(field)),
+    }
+}
+
+fn item_name(item: Item) -> &'static str {
+    match item {
+        Item::Dynamite => "dynamite",
+        Item::ClaimJump => "claim_jump",
+        Item::Decoy => "decoy",
+        Item::Spyglass => "spyglass",
+        Item::Informant => "informant",
+    }
+}
+
+fn ore_name(ore: Ore) -> &'static str {
+    match ore {
+        Ore::Copper => "copper",
+        Ore::Silver => "silver",
+        Ore::Gold => "gold",
+        Ore::Gems => "gems",
+    }
+}
+
+fn branch_name(branch: Branch) -> &'static str {
+    match branch {
+        Branch::Speed => "speed",
+        Branch::Yield => "yield",
+        Branch::Crit => "crit",
+        Branch::Interest => "interest",
+        Branch::Donation => "donation",
+        Branch::Defense => "defense",
+    }
+}
+
+fn rule_name(field: RuleField) -> &'static str {
+    match field {
+        RuleField::Rounds => "rounds",
+        RuleField::RoundLength => "round_length",
+        RuleField::Bots => "bots",
+        RuleField::Difficulty => "difficulty",
+        RuleField::Mode => "mode",
+        RuleField::Pace => "pace",
+        RuleField::Auction => "auction",
+        RuleField::Fog => "fog_of_war",
+        RuleField::Reserves => "reserves",
+    }
+}
+
+fn bindable_actions() -> Vec<ButtonId> {
+    let mut actions = vec![
+        ButtonId::TechTree,
+        ButtonId::BuyHeal,
+        ButtonId::BuyShield,
+        ButtonId::UpgradeMaxHealth,
+        ButtonId::CycleTarget,
+    ];
+    actions.extend(QUICK_RESEARCH.map(ButtonId::ResearchNext));
+    actions.extend(Item::ALL.map(ButtonId::UseItem));
+    actions.extend(Ore::ALL.map(ButtonId::SellOre));
+    actions.push(ButtonId::SellAll);
+    actions.extend((0..DRAFT_HAND).map(ButtonId::PickPerk));
+    actions.extend((0..CONTRIBUTION_AMOUNTS.len()).map(ButtonId::Donate));
+    actions.extend((0..DONATION_PERCENTAGES.len()).map(ButtonId::DonatePercent));
+    actions.extend([
+        ButtonId::DonateAll,
+        ButtonId::DonationField,
+        ButtonId::DonateCustom,
+        ButtonId::Strike,
+        ButtonId::Deposit,
+        ButtonId::Withdraw,
+        ButtonId::Borrow,
+        ButtonId::RepayLoan,
+        ButtonId::EndTurn,
+        ButtonId::Continue,
+        ButtonId::SkipToResults,
+        ButtonId::Restart,
+        ButtonId::CycleTheme,
+        ButtonId::Lobby(LobbyButton::Name),
+        ButtonId::Lobby(LobbyButton::Color),
+        ButtonId::Lobby(LobbyButton::Ready),
+        ButtonId::Lobby(LobbyButton::Start),
+    ]);
+    actions
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    bindings: HashMap<ButtonId, KeyCode>,
+}
+
+impl Default for KeyBindings {
+    // Bindings for the first (or only) player, who also gets the shared actions
+    fn default() -> Self {
+        let mut bindings = HashMap::new();
+        bindings.insert(ButtonId::ResearchNext(Branch::Speed), KeyCode::P);
+        bindings.insert(ButtonId::ResearchNext(Branch::Yield), KeyCode::M);
+        bindings.insert(ButtonId::TechTree, KeyCode::Slash);
+        bindings.insert(ButtonId::BuyHeal, KeyCode::H);
+        bindings.insert(ButtonId::BuyShield, KeyCode::B);
+        bindings.insert(ButtonId::UpgradeMaxHealth, KeyCode::V);
+        bindings.insert(ButtonId::CycleTarget, KeyCode::Equals);
+        bindings.insert(ButtonId::UseItem(Item::Dynamite), KeyCode::Key9);
+        bindings.insert(ButtonId::UseItem(Item::ClaimJump), KeyCode::Key0);
+        bindings.insert(ButtonId::UseItem(Item::Decoy), KeyCode::Minus);
+        bindings.insert(ButtonId::UseItem(Item::Spyglass), KeyCode::Comma);
+        bindings.insert(ButtonId::UseItem(Item::Informant), KeyCode::Apostrophe);
+
+        let sell_keys = [KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10];
+        for (ore, key) in Ore::ALL.iter().zip(sell_keys) {
+            bindings.insert(ButtonId::SellOre(*ore), key);
+        }
+        bindings.insert(ButtonId::SellAll, KeyCode::Period);
+
+        // Perk cards only show on the draft screen, so they share the donation keys
+        let number_keys = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5];
+        for (i, key) in number_keys.iter().enumerate().take(CONTRIBUTION_AMOUNTS.len()) {
+            bindings.insert(ButtonId::Donate(i), *key);
+        }
+        for (i, key) in number_keys.iter().enumerate().take(DRAFT_HAND) {
+            bindings.insert(ButtonId::PickPerk(i), *key);
+        }
+
+        let percent_keys = [KeyCode::Key6, KeyCode::Key7, KeyCode::Key8];
+        for (i, key) in percent_keys.iter().enumerate().take(DONATION_PERCENTAGES.len()) {
+            bindings.insert(ButtonId::DonatePercent(i), *key);
+        }
+
+        bindings.insert(ButtonId::DonateAll, KeyCode::A);
+        bindings.insert(ButtonId::DonationField, KeyCode::E);
+        bindings.insert(ButtonId::Strike, KeyCode::Space);
+        bindings.insert(ButtonId::Deposit, KeyCode::LBracket);
+        bindings.insert(ButtonId::Withdraw, KeyCode::RBracket);
+        bindings.insert(ButtonId::Borrow, KeyCode::Backslash);
+        bindings.insert(ButtonId::RepayLoan, KeyCode::F11);
+        // Enter still presses the focused button when there is one
+        bindings.insert(ButtonId::EndTurn, KeyCode::Return);
+        bindings.insert(ButtonId::Continue, KeyCode::C);
+        bindings.insert(ButtonId::SkipToResults, KeyCode::F);
+        bindings.insert(ButtonId::Restart, KeyCode::R);
+        bindings.insert(ButtonId::CycleTheme, KeyCode::T);
+        bindings.insert(ButtonId::Lobby(LobbyButton::Name), KeyCode::N);
+        bindings.insert(ButtonId::Lobby(LobbyButton::Color), KeyCode::K);
+        bindings.insert(ButtonId::Lobby(LobbyButton::Ready), KeyCode::Y);
+        bindings.insert(ButtonId::Lobby(LobbyButton::Start), KeyCode::G);
+
+        KeyBindings { bindings }
+    }
+}
+
+impl KeyBindings {
+    // Defaults for a hot-seat seat. Later seats only get the actions that act on
+    // their own miner, on keys away from the first player's.
+    pub fn default_for(seat: usize) -> Self {
+        let keys = match seat {
+            0 => return KeyBindings::default(),
+            1 => [KeyCode::Q, KeyCode::W, KeyCode::Z, KeyCode::X, KeyCode::S, KeyCode::D, KeyCode::Grave, KeyCode::Y],
+            2 => [KeyCode::U, KeyCode::I, KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::O, KeyCode::Semicolon, KeyCode::N],
+            _ => [KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6, KeyCode::F12, KeyCode::G],
+        };
+        let actions = [
+            ButtonId::ResearchNext(Branch::Speed),
+            ButtonId::ResearchNext(Branch::Yield),
+            ButtonId::Donate(0),
+            ButtonId::Donate(2),
+            ButtonId::DonatePercent(0),
+            ButtonId::DonateAll,
+            ButtonId::SellAll,
+            ButtonId::Strike,
+        ];
+
+        // The draft reuses the first keys, which only research while the round is on
+        let mut bindings: HashMap<ButtonId, KeyCode> = actions.into_iter().zip(keys).collect();
+        for (i, key) in keys.iter().enumerate().take(DRAFT_HAND) {
+            bindings.insert(ButtonId::PickPerk(i), *key);
+        }
+        // Turn-based rounds have no Strike button, so its key ends the turn instead
+        bindings.insert(ButtonId::EndTurn, keys[7]);
+
+        KeyBindings { bindings }
+    }
+
+    // Loads one set of bindings per seat from the config file on top of the defaults.
+    // Top-level entries belong to player 1, `[player2]` to `[player4]` tables to the others.
+    // A missing file is not an error; a broken one is reported and ignored.
+    pub fn load_all(seats: usize) -> Vec<KeyBindings> {
+        let defaults: Vec<KeyBindings> = (0..seats).map(KeyBindings::default_for).collect();
+
+        let contents = match fs::read_to_string(KEY_BINDINGS_FILE) {
+            Ok(contents) => contents,
+            Err(_) => return defaults,
+        };
+
+        match KeyBindings::apply_config(defaults.clone(), &contents) {
+            Ok(key_bindings) => key_bindings,
+            Err(message) => {
+                eprintln!("Ignoring {}: {}", KEY_BINDINGS_FILE, message);
+                defaults
+            },
+        }
+    }
+
+    fn apply_config(mut key_bindings: Vec<KeyBindings>, contents: &str) -> Result<Vec<KeyBindings>, String> {
+        let table: toml::value::Table = toml::from_str(contents).map_err(|e| e.to_string())?;
+
+        for (name, value) in table {
+            match value {
+                toml::Value::String(key_name) => key_bindings[0].bind(&name, &key_name)?,
+                toml::Value::Table(entries) => {
+                    let seat = name
+                        .strip_prefix("player")
+                        .and_then(|number| number.parse::<usize>().ok())
+                        .filter(|number| *number >= 1)
+                        .ok_or_else(|| format!("unknown section `[{}]`", name))?
+                        - 1;
+
+                    // Sections for seats that aren't playing are fine to leave in
+                    if let Some(seat_bindings) = key_bindings.get_mut(seat) {
+                        for (action, key_name) in entries {
+                            let key_name = key_name
+                                .as_str()
+                                .ok_or_else(|| format!("`{}` must be a key name", action))?;
+                            seat_bindings.bind(&action, key_name)?;
+                        }
+                    }
+                },
+                _ => return Err(format!("`{}` must be a key name", name)),
+            }
+        }
+
+        Ok(key_bindings)
+    }
+
+    // Applies one `action = "Key"` entry, e.g. `research_speed = "Q"`
+    fn bind(&mut self, action: &str, key_name: &str) -> Result<(), String> {
+        let id = bindable_actions()
+            .into_iter()
+            .find(|id| action_name(*id) == action)
+            .ok_or_else(|| format!("unknown action `{}`", action))?;
+        let key = parse_key(key_name).ok_or_else(|| format!("unknown key `{}`", key_name))?;
+
+        self.bindings.insert(id, key);
+        Ok(())
+    }
+
+    pub fn key_for(&self, id: ButtonId) -> Option<KeyCode> {
+        self.bindings.get(&id).copied()
+    }
+
+    // All buttons bound to a key; the caller decides which one is visible right now
+    pub fn buttons_for(&self, key: KeyCode) -> Vec<ButtonId> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| **bound == key)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(contents: &str) -> Result<Vec<KeyBindings>, String> {
+        KeyBindings::apply_config((0..2).map(KeyBindings::default_for).collect(), contents)
+    }
+
+    #[test]
+    fn top_level_entries_rebind_the_first_player_only() {
+        let key_bindings = apply("donate_all = \"X\"").unwrap();
+        assert_eq!(key_bindings[0].key_for(ButtonId::DonateAll), Some(KeyCode::X));
+        assert_eq!(key_bindings[0].key_for(ButtonId::Continue), Some(KeyCode::C));
+        assert_eq!(key_bindings[1].key_for(ButtonId::DonateAll), Some(KeyCode::D));
+    }
+
+    #[test]
+    fn player_sections_rebind_their_own_seat() {
+        let key_bindings = apply("[player2]\ndonate_all = \"F2\"\ndonate_10 = \"7\"").unwrap();
+        assert_eq!(key_bindings[1].key_for(ButtonId::DonateAll), Some(KeyCode::F2));
+        assert_eq!(key_bindings[1].key_for(ButtonId::Donate(0)), Some(KeyCode::Key7));
+        assert_eq!(key_bindings[0].key_for(ButtonId::DonateAll), Some(KeyCode::A));
+    }
+
+    #[test]
+    fn sections_for_seats_not_playing_are_ignored() {
+        assert!(apply("[player4]\ndonate_all = \"F2\"").is_ok());
+    }
+
+    #[test]
+    fn mistakes_are_reported() {
+        assert_eq!(apply("dance = \"X\"").unwrap_err(), "unknown action `dance`");
+        assert_eq!(apply("donate_all = \"Hyper\"").unwrap_err(), "unknown key `Hyper`");
+        assert_eq!(apply("donate_all = 3").unwrap_err(), "`donate_all` must be a key name");
+        assert_eq!(apply("[player0]\ndonate_all = \"X\"").unwrap_err(), "unknown section `[player0]`");
+        assert_eq!(apply("[bots]\ndonate_all = \"X\"").unwrap_err(), "unknown section `[bots]`");
+        assert!(apply("donate_all = ").is_err());
+    }
+
+    #[test]
+    fn every_extra_seat_can_end_a_turn() {
+        for seat in 1..4 {
+            assert!(KeyBindings::default_for(seat).key_for(ButtonId::EndTurn).is_some());
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// Consumables a miner can buy and use straight away to get in a rival's way,
+// or, with fog of war on, to see through it
+pub const DYNAMITE_STUN: Duration = Duration::from_secs(5); // Target can't mine for this long
+pub const CLAIM_JUMP_SHARE: f32 = 0.25; // Of the target's unspent gold
+pub const SPYGLASS_REVEAL: Duration = Duration::from_secs(20); // Target's numbers stay visible this long
+pub const INFORMANT_REVEAL: Duration = Duration::from_secs(10); // Every rival's numbers, for this long
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Item {
+    Dynamite,
+    ClaimJump,
+    Decoy, // Others keep seeing the donation from before it was used until the round ends
+    Spyglass, // Scouts one rival
+    Informant, // Scouts every rival, briefly
+}
+
+impl Item {
+    pub const ALL: [Item; 5] = [Item::Dynamite, Item::ClaimJump, Item::Decoy, Item::Spyglass, Item::Informant];
+    pub const SABOTAGE: [Item; 3] = [Item::Dynamite, Item::ClaimJump, Item::Decoy];
+    pub const SCOUTING: [Item; 2] = [Item::Spyglass, Item::Informant]; // Only sold with fog of war on
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Item::Dynamite => "Dynamite",
+            Item::ClaimJump => "Claim Jump",
+            Item::Decoy => "Decoy",
+            Item::Spyglass => "Spyglass",
+            Item::Informant => "Informant",
+        }
+    }
+
+    pub fn cost(self) -> f32 {
+        match self {
+            Item::Dynamite => 80.0,
+            Item::ClaimJump => 120.0,
+            Item::Decoy => 50.0,
+            Item::Spyglass => 40.0,
+            Item::Informant => 100.0,
+        }
+    }
+
+    // Time before the same miner can use the item again
+    pub fn cooldown(self) -> Duration {
+        match self {
+            Item::Dynamite => Duration::from_secs(20),
+            Item::ClaimJump => Duration::from_secs(30),
+            Item::Decoy => Duration::from_secs(30),
+            Item::Spyglass => Duration::from_secs(20),
+            Item::Informant => Duration::from_secs(45),
+        }
+    }
+
+    pub fn needs_target(self) -> bool {
+        !matches!(self, Item::Decoy | Item::Informant)
+    }
+
+    pub fn is_scouting(self) -> bool {
+        Item::SCOUTING.contains(&self)
+    }
+
+    // Position in `Miner::item_cooldowns`
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
@@ -2,21 +2,279 @@ use ggez::{ContextBuilder, GameResult};
 use ggez::event;
 use ggez::conf::{WindowSetup, WindowMode};
 
+mod events;
+mod game;
+mod input;
+mod items;
+mod market;
+mod match_file;
 mod miner;
+mod net;
+mod observer;
+mod perks;
+mod reserve;
+mod rules;
+mod settings;
+mod tech;
+mod theme;
+mod yields;
 mod game_state;
 mod ui;
 
+use game::Game;
 use game_state::MainState;
+use match_file::{FileSession, MatchFile};
+use net::{NetClient, Server, ServerUpdate};
+use observer::StateStream;
+use rules::MatchRules;
+use ui::{DESIGN_WIDTH, DESIGN_HEIGHT, MIN_WINDOW_WIDTH, MIN_WINDOW_HEIGHT};
 
-const WINDOW_WIDTH: f32 = 800.0;
-const WINDOW_HEIGHT: f32 = 600.0;
+const USAGE: &str = "Usage: [--players 1-4] [--bots 0-7] [--rounds 1-30] [--round-length 15-180] \
+[--difficulty easy|normal|hard] [--last-standing] [--seed N] \
+[--auction all-pay|sealed|second-price|refund-losers] [--fog-of-war] [--turn-based] [--reserves unlimited|own|shared] [--server [ADDRESS] | --host [ADDRESS] | --connect ADDRESS | --spectate [ADDRESS]] \
+[--name NAME] [--observer-socket PATH] \
+[--new-key FILE | --new-match FILE --player-names NAME,NAME... --player-keys KEY,KEY... | --play-turn FILE --name NAME --key-file FILE | --verify FILE]";
+
+// Where the match runs: in this window, on a headless server, or on someone else's server
+enum Mode {
+    Local,
+    Watch, // Bots-only match in this window
+    Server(String),
+    Host { address: String, name: String }, // Server in the background plus a window to play in
+    Client { address: String, name: String, spectate: bool },
+    NewKey { path: String }, // Writes a player's key for play-by-file matches and exits
+    NewMatch { path: String, players: Vec<String>, keys: Vec<String> }, // Writes a play-by-file match and exits
+    PlayTurn { path: String, name: String, key_file: String }, // One player's turn of a play-by-file match
+    Verify { path: String }, // Checks a play-by-file match without a window
+}
+
+// Pulls the networking and play-by-file flags out of the arguments and leaves the match rules for MatchRules.
+// Also returns the path of the observer socket, if one was asked for.
+fn parse_mode(args: Vec<String>) -> Result<(Mode, Option<String>, Vec<String>), String> {
+    let mut server = None;
+    let mut host = None;
+    let mut connect = None;
+    let mut spectate = None;
+    let mut name = None;
+    let mut observer_socket = None;
+    let mut new_match = None;
+    let mut play_turn = None;
+    let mut verify = None;
+    let mut player_names = None;
+    let mut player_keys = None;
+    let mut key_file = None;
+    let mut new_key = None;
+    let mut rest = Vec::new();
+    let mut args = args.into_iter().peekable();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--server" => {
+                // The address is optional; anything that isn't another flag is one
+                let address = args.next_if(|next| !next.starts_with("--"));
+                server = Some(address.unwrap_or_else(|| net::DEFAULT_ADDRESS.to_string()));
+            },
+            "--host" => {
+                let address = args.next_if(|next| !next.starts_with("--"));
+                host = Some(address.unwrap_or_else(|| net::DEFAULT_ADDRESS.to_string()));
+            },
+            "--connect" => connect = Some(args.next().ok_or("`--connect` needs an address")?),
+            // Without an address there is no one else's match to watch, so the bots play one here
+            "--spectate" => spectate = Some(args.next_if(|next| !next.starts_with("--"))),
+            "--observer-socket" => observer_socket = Some(args.next().ok_or("`--observer-socket` needs a path")?),
+            "--name" => name = Some(args.next().ok_or("`--name` needs a value")?),
+            "--new-match" => new_match = Some(args.next().ok_or("`--new-match` needs a file")?),
+            "--play-turn" => play_turn = Some(args.next().ok_or("`--play-turn` needs a file")?),
+            "--verify" => verify = Some(args.next().ok_or("`--verify` needs a file")?),
+            "--player-names" => {
+                let names = args.next().ok_or("`--player-names` needs a comma-separated list")?;
+                player_names = Some(names.split(',').map(|name| name.trim().to_string()).collect::<Vec<String>>());
+            },
+            "--player-keys" => {
+                let keys = args.next().ok_or("`--player-keys` needs a comma-separated list")?;
+                player_keys = Some(keys.split(',').map(|key| key.trim().to_string()).collect::<Vec<String>>());
+            },
+            "--key-file" => key_file = Some(args.next().ok_or("`--key-file` needs a file")?),
+            "--new-key" => new_key = Some(args.next().ok_or("`--new-key` needs a file")?),
+            _ => rest.push(arg),
+        }
+    }
+
+    // Play-by-file matches need no connection at all
+    let networked = server.is_some() || host.is_some() || connect.is_some() || spectate.is_some();
+    if new_key.is_some() || new_match.is_some() || play_turn.is_some() || verify.is_some() {
+        if networked {
+            return Err("play-by-file matches can't be combined with network play".to_string());
+        }
+        let mode = match (new_key, new_match, play_turn, verify) {
+            (Some(path), None, None, None) => Mode::NewKey { path },
+            (None, Some(path), None, None) => {
+                let players = player_names.ok_or("`--new-match` needs `--player-names`")?;
+                let keys = player_keys.ok_or("`--new-match` needs everyone's `--player-keys`")?;
+                Mode::NewMatch { path, players, keys }
+            },
+            (None, None, Some(path), None) => {
+                let name = name.ok_or("`--play-turn` needs the `--name` you play under")?;
+                let key_file = key_file.ok_or("`--play-turn` needs your `--key-file`")?;
+                Mode::PlayTurn { path, name, key_file }
+            },
+            (None, None, None, Some(path)) => Mode::Verify { path },
+            _ => return Err("only one of `--new-key`, `--new-match`, `--play-turn` and `--verify` can be used".to_string()),
+        };
+        return Ok((mode, observer_socket, rest));
+    }
+
+    // An empty name lets the server pick one
+    let name = name.unwrap_or_default();
+    let mode = match (server, host, connect, spectate) {
+        (None, None, None, None) => Mode::Local,
+        (Some(address), None, None, None) => Mode::Server(address),
+        (None, Some(address), None, None) => Mode::Host { address, name },
+        (None, None, Some(address), None) => Mode::Client { address, name, spectate: false },
+        (None, None, None, Some(Some(address))) => Mode::Client { address, name, spectate: true },
+        (None, None, None, Some(None)) => Mode::Watch,
+        _ => return Err("only one of `--server`, `--host`, `--connect` and `--spectate` can be used".to_string()),
+    };
+    Ok((mode, observer_socket, rest))
+}
 
 fn main() -> GameResult {
+    let parsed = parse_mode(std::env::args().skip(1).collect())
+        .and_then(|(mode, socket, rest)| Ok((mode, socket, MatchRules::from_args(rest)?)));
+    let (mode, observer_socket, mut rules) = match parsed {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        },
+    };
+
+    let state_stream = match observer_socket {
+        Some(path) => {
+            let stream = StateStream::bind(&path)?;
+            println!("Streaming match state to {}", path);
+            Some(stream)
+        },
+        None => None,
+    };
+
+    // A server has no window; it just runs matches for whoever connects
+    let mut session = None;
+    let mut file_game = None;
+    let connection = match mode {
+        Mode::Local => None,
+        Mode::NewKey { path } => {
+            match match_file::new_key(&path) {
+                Ok(public_key) => {
+                    println!("Saved your key to {}; keep it to yourself", path);
+                    println!("Your public key for the match file is {}", public_key);
+                    return Ok(());
+                },
+                Err(message) => {
+                    eprintln!("{}", message);
+                    std::process::exit(1);
+                },
+            }
+        },
+        Mode::NewMatch { path, players, keys } => {
+            let created = MatchFile::create(rules, players, keys).and_then(|file| file.save(&path).map(|_| file));
+            match created {
+                Ok(file) => {
+                    let names: Vec<&str> = file.players.iter().map(|player| player.name.as_str()).collect();
+                    println!("Created {} for {}", path, names.join(", "));
+                    println!("History hash {}", file.history_hash());
+                    return Ok(());
+                },
+                Err(message) => {
+                    eprintln!("{}", message);
+                    std::process::exit(1);
+                },
+            }
+        },
+        Mode::Verify { path } => {
+            let checked = MatchFile::load(&path).and_then(|file| file.replay().map(|game| (file, game)));
+            match checked {
+                Ok((file, game)) => {
+                    println!("{}: {} turns, all valid", path, file.turns.len());
+                    println!("History hash {}", file.history_hash());
+                    if game.game_state == game::GameState::GameOver {
+                        println!("The match is over; {} won", game.miners[game.standings()[0]].name);
+                    } else {
+                        let waiting: Vec<&str> =
+                            match_file::waiting_seats(&game).into_iter().map(|seat| file.players[seat].name.as_str()).collect();
+                        println!("{}, waiting for {}", game.round_label(), waiting.join(", "));
+                    }
+                    return Ok(());
+                },
+                Err(message) => {
+                    eprintln!("{}: {}", path, message);
+                    std::process::exit(1);
+                },
+            }
+        },
+        Mode::PlayTurn { path, name, key_file } => {
+            match FileSession::open(&path, &key_file, &name) {
+                Ok((opened, game)) => {
+                    session = Some(opened);
+                    file_game = Some(game);
+                },
+                Err(message) => {
+                    eprintln!("{}: {}", path, message);
+                    std::process::exit(1);
+                },
+            }
+            None
+        },
+        Mode::Watch => {
+            rules.human_count = 0;
+            None
+        },
+        Mode::Server(address) => {
+            let mut server = Server::bind(address.as_str(), rules)?;
+            println!("Listening on {}", server.local_addr());
+            if let Some(stream) = state_stream {
+                server.stream_state_to(stream);
+            }
+            server.run();
+            return Ok(());
+        },
+        Mode::Host { address, name } => {
+            // The host's rules become the room's starting rules
+            let mut server = Server::bind(address.as_str(), rules.clone())?;
+            let mut local_addr = server.local_addr();
+            println!("Hosting on {}", local_addr);
+            std::thread::spawn(move || server.run());
+
+            // Listening on every interface still means joining through loopback here
+            if local_addr.ip().is_unspecified() {
+                local_addr.set_ip(std::net::Ipv4Addr::LOCALHOST.into());
+            }
+            Some(NetClient::connect(local_addr, &name, false)?)
+        },
+        Mode::Client { address, name, spectate } => Some(NetClient::connect(address.as_str(), &name, spectate)?),
+    };
+
+    let (game, lobby, net) = match connection {
+        None => (file_game.unwrap_or_else(|| Game::new(rules)), None, None),
+        Some((client, ServerUpdate::Lobby(lobby))) => (Game::new(lobby.rules.clone()), Some(lobby), Some(client)),
+        Some((client, ServerUpdate::Game(game))) => (game, None, Some(client)),
+    };
+
     let (mut ctx, event_loop) = ContextBuilder::new("placeholder_title", "Daniel Zheng")
         .window_setup(WindowSetup::default().title("Placeholder Title"))
-        .window_mode(WindowMode::default().dimensions(WINDOW_WIDTH, WINDOW_HEIGHT))
+        .window_mode(
+            WindowMode::default()
+                .dimensions(DESIGN_WIDTH, DESIGN_HEIGHT)
+                .min_dimensions(MIN_WINDOW_WIDTH, MIN_WINDOW_HEIGHT)
+                .resizable(true)
+        )
         .build()?;
     
-    let state = MainState::new(&mut ctx)?;
+    let mut state = MainState::new(&mut ctx, game, lobby, net)?;
+    state.state_stream = state_stream;
+    if let Some(session) = session {
+        state.play_from_file(session);
+    }
     event::run(ctx, event_loop, state)
 }
\ No newline at end of file
@@ -0,0 +1,160 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// Prices drift back towards the base price at this rate per second
+const PRICE_REVERSION: f32 = 0.05;
+// Random swing per second, as a share of the base price
+const PRICE_VOLATILITY: f32 = 0.04;
+// Selling this much gold's worth (at base price) knocks about 63% off the price
+const MARKET_DEPTH: f32 = 400.0;
+const MIN_PRICE_FACTOR: f32 = 0.3;
+const MAX_PRICE_FACTOR: f32 = 2.5;
+
+// Share of each swing's value that comes up as each ore, by mine level (the number
+// of yield nodes researched). Deeper mines reach the rarer ores.
+const ORE_MIX: [[f32; 4]; 5] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.7, 0.3, 0.0, 0.0],
+    [0.5, 0.3, 0.2, 0.0],
+    [0.35, 0.3, 0.25, 0.1],
+    [0.2, 0.3, 0.3, 0.2],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ore {
+    Copper,
+    Silver,
+    Gold,
+    Gems,
+}
+
+impl Ore {
+    pub const ALL: [Ore; 4] = [Ore::Copper, Ore::Silver, Ore::Gold, Ore::Gems];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Ore::Copper => "Copper",
+            Ore::Silver => "Silver",
+            Ore::Gold => "Gold",
+            Ore::Gems => "Gems",
+        }
+    }
+
+    // What the market pays per unit when nobody has been selling
+    pub fn base_price(self) -> f32 {
+        match self {
+            Ore::Copper => 1.0,
+            Ore::Silver => 4.0,
+            Ore::Gold => 10.0,
+            Ore::Gems => 25.0,
+        }
+    }
+
+    // Position in `Miner::ore` and `Market::prices`
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    // Units of this ore in a swing worth `value` at base prices
+    pub fn per_swing(self, mine_level: usize, value: f32) -> f32 {
+        let mix = ORE_MIX[mine_level.min(ORE_MIX.len() - 1)];
+        value * mix[self.index()] / self.base_price()
+    }
+}
+
+// Where ore turns into gold. Shared by every miner, so one miner's sales
+// lower the price for everyone until it recovers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Market {
+    pub prices: [f32; 4], // Gold per unit, indexed by `Ore::index`
+    pub trend: [f32; 4], // Smoothed price change per second, so the UI can show which way prices are heading
+}
+
+impl Default for Market {
+    fn default() -> Self {
+        Market {
+            prices: Ore::ALL.map(Ore::base_price),
+            trend: [0.0; 4],
+        }
+    }
+}
+
+impl Market {
+    pub fn price(&self, ore: Ore) -> f32 {
+        self.prices[ore.index()]
+    }
+
+    // Gold a pile of ore would fetch at today's prices, ignoring the price drop of selling it
+    pub fn value(&self, ore: &[f32; 4]) -> f32 {
+        Ore::ALL.iter().map(|o| ore[o.index()].floor() * self.price(*o)).sum()
+    }
+
+    // Draws the price noise from `rng`, so a seeded match can move prices the same way every time
+    pub fn update<R: Rng>(&mut self, dt: Duration, rng: &mut R) {
+        let dt = dt.as_secs_f32();
+
+        for ore in Ore::ALL {
+            let base = ore.base_price();
+            let price = &mut self.prices[ore.index()];
+            let before = *price;
+
+            let noise = rng.gen_range(-1.0..1.0) * PRICE_VOLATILITY * base * dt.sqrt();
+            *price += (base - *price) * PRICE_REVERSION * dt + noise;
+            *price = price.clamp(base * MIN_PRICE_FACTOR, base * MAX_PRICE_FACTOR);
+
+            // Smoothed over about a second so the direction doesn't flicker
+            let trend = &mut self.trend[ore.index()];
+            *trend += ((*price - before) / dt.max(0.001) - *trend) * dt.min(1.0);
+        }
+    }
+
+    // Sells `units` of an ore; returns the gold paid. The price falls as the sale goes
+    // through, so one big sale fetches less than the same ore sold in small lots over time.
+    pub fn sell(&mut self, ore: Ore, units: f32) -> f32 {
+        if units <= 0.0 {
+            return 0.0;
+        }
+
+        let base = ore.base_price();
+        let price = &mut self.prices[ore.index()];
+        let start = *price;
+        let drop = (-units * base / MARKET_DEPTH).exp();
+        *price = (start * drop).max(base * MIN_PRICE_FACTOR);
+
+        // Paid at the average of the price before and after
+        units * (start + *price) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selling_nothing_pays_nothing() {
+        let mut market = Market::default();
+        assert_eq!(market.sell(Ore::Gold, 0.0), 0.0);
+        assert_eq!(market.price(Ore::Gold), Ore::Gold.base_price());
+    }
+
+    #[test]
+    fn a_sale_pays_between_the_old_and_new_price_and_pushes_it_down() {
+        let mut market = Market::default();
+        let start = market.price(Ore::Silver);
+        let paid = market.sell(Ore::Silver, 50.0);
+        let end = market.price(Ore::Silver);
+        assert!(end < start);
+        assert!(paid > 50.0 * end && paid < 50.0 * start);
+        // Only the ore sold moves
+        assert_eq!(market.price(Ore::Copper), Ore::Copper.base_price());
+    }
+
+    #[test]
+    fn a_flooded_market_bottoms_out() {
+        let mut market = Market::default();
+        market.sell(Ore::Gems, 1_000_000.0);
+        assert_eq!(market.price(Ore::Gems), Ore::Gems.base_price() * MIN_PRICE_FACTOR);
+    }
+}
@@ -0,0 +1,441 @@
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+
+use crate::game::{Action, Game, GameState};
+use crate::rules::{MatchRules, MAX_HUMANS};
+use crate::{perks, tech, yields};
+
+// Play-by-file matches: a turn-based match played by passing files around. The match file
+// holds the rules and the players, and every turn goes in a turn file of its own next to it
+// (`mine.json`, then `mine.turn-001.json`, `mine.turn-002.json` and so on). Loading the match
+// replays the turns from the start, so a match that loads is a match played by the rules.
+// Each turn extends a hash of the whole history and is signed with its player's own key,
+// checked against the public keys in the match file, so nobody can forge another player's
+// turn or change one after the fact. The match file also holds a hash of the tech tree, perk
+// deck and yield table, which every machine replaying the match has to share.
+//
+// Bumped whenever the file changes shape or the same turns would replay into a different
+// match, as when mining yields started being rolled; files of any other version are refused.
+pub const MATCH_FILE_VERSION: u32 = 6;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchFile {
+    pub version: u32,
+    pub rules: MatchRules, // Always turn-based with a seed, so the match replays the same everywhere
+    pub players: Vec<Player>, // The human seats, in seat order
+    pub tables: String, // Hash of the tech tree, perk deck and yield table the match is played with, in hex
+    #[serde(skip)]
+    pub turns: Vec<Turn>, // Read from and written to the turn files
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Player {
+    pub name: String,
+    pub key: String, // Ed25519 public key every one of their turns is checked against, in hex
+}
+
+// What one player did between two commits: their actions up to and including
+// an End Turn or a perk pick
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Turn {
+    pub seat: usize,
+    pub round: usize,
+    pub actions: Vec<Action>,
+    pub hash: String, // History hash with this turn added, in hex
+    pub signature: String, // Ed25519 signature of `hash` by the seat's player, in hex
+}
+
+impl MatchFile {
+    // A new match for the named players and their public keys; whoever has the file first moves first
+    pub fn create(mut rules: MatchRules, names: Vec<String>, keys: Vec<String>) -> Result<MatchFile, String> {
+        if names.is_empty() || names.len() > MAX_HUMANS {
+            return Err(format!("a match needs 1 to {} players, got {}", MAX_HUMANS, names.len()));
+        }
+        if names.iter().enumerate().any(|(i, name)| name.is_empty() || names[..i].contains(name)) {
+            return Err("every player needs a name of their own".to_string());
+        }
+        if keys.len() != names.len() {
+            return Err(format!("{} players need {} keys, got {}", names.len(), names.len(), keys.len()));
+        }
+        if let Some(name) = names.iter().zip(&keys).find_map(|(name, key)| parse_public_key(key).is_none().then_some(name)) {
+            return Err(format!("{}'s key isn't a public key from `--new-key`", name));
+        }
+
+        rules.human_count = names.len();
+        rules.turn_based = true;
+        rules.seed = Some(rules.seed.unwrap_or_else(rand::random));
+        let players = names.into_iter().zip(keys).map(|(name, key)| Player { name, key }).collect();
+        Ok(MatchFile {
+            version: MATCH_FILE_VERSION,
+            rules,
+            players,
+            tables: tables_hash(),
+            turns: Vec::new(),
+        })
+    }
+
+    // Reads the match file and every turn file after it, up to the first one missing
+    pub fn load(path: &str) -> Result<MatchFile, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("can't read {}: {}", path, e))?;
+        let mut file: MatchFile =
+            serde_json::from_str(&contents).map_err(|e| format!("{} is not a match file: {}", path, e))?;
+        if file.version != MATCH_FILE_VERSION {
+            return Err(format!("{} is a version {} match file, this game reads version {}", path, file.version, MATCH_FILE_VERSION));
+        }
+
+        loop {
+            let turn_path = turn_path(path, file.turns.len() + 1);
+            let contents = match fs::read_to_string(&turn_path) {
+                Ok(contents) => contents,
+                Err(_) => break,
+            };
+            let turn = serde_json::from_str(&contents).map_err(|e| format!("{} is not a turn file: {}", turn_path, e))?;
+            file.turns.push(turn);
+        }
+        Ok(file)
+    }
+
+    // Writes the match file; turns are saved one by one with `save_turn`
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let contents = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, contents).map_err(|e| format!("can't write {}: {}", path, e))
+    }
+
+    // Writes the latest turn to its own file; returns the file's path
+    pub fn save_turn(&self, path: &str) -> Result<String, String> {
+        let turn = self.turns.last().ok_or("there is no turn to save")?;
+        let turn_path = turn_path(path, self.turns.len());
+        let contents = serde_json::to_string_pretty(turn).map_err(|e| e.to_string())?;
+        fs::write(&turn_path, contents).map_err(|e| format!("can't write {}: {}", turn_path, e))?;
+        Ok(turn_path)
+    }
+
+    // Hash of the rules, players and tables, which the first turn builds on
+    fn genesis(&self) -> Vec<u8> {
+        let header = serde_json::to_vec(&(self.version, &self.rules, &self.players, &self.tables)).expect("match rules encode");
+        Sha256::digest(header).to_vec()
+    }
+
+    // Hash of the whole match so far. Players can compare it to check they hold the same match.
+    pub fn history_hash(&self) -> String {
+        match self.turns.last() {
+            Some(turn) => turn.hash.clone(),
+            None => to_hex(&self.genesis()),
+        }
+    }
+
+    // Checks every hash and signature and replays every turn; returns the match as it stands
+    pub fn replay(&self) -> Result<Game, String> {
+        if !self.rules.turn_based || self.rules.seed.is_none() || self.rules.human_count != self.players.len() {
+            return Err("the rules don't fit a play-by-file match".to_string());
+        }
+        // Nobody else checked these, and a huge round or bot count would hang whoever replays the match
+        if self.rules != self.rules.clone().clamped() {
+            return Err("the rules are out of range".to_string());
+        }
+        if self.tables != tables_hash() {
+            return Err(format!("the match is played with another {}, {} or {} than this one", tech::TECH_TREE_FILE, perks::PERKS_FILE, yields::YIELDS_FILE));
+        }
+        let keys = self
+            .players
+            .iter()
+            .map(|player| parse_public_key(&player.key).ok_or_else(|| format!("{}'s key is broken", player.name)))
+            .collect::<Result<Vec<VerifyingKey>, String>>()?;
+
+        let names: Vec<String> = self.players.iter().map(|player| player.name.clone()).collect();
+        let mut game = Game::named(self.rules.clone(), &names);
+        let mut hash = self.genesis();
+        for (number, turn) in self.turns.iter().enumerate().map(|(i, turn)| (i + 1, turn)) {
+            hash = turn_hash(&hash, turn.seat, turn.round, &turn.actions);
+            if to_hex(&hash) != turn.hash {
+                return Err(format!("turn {} doesn't follow from the turns before it", number));
+            }
+            let key = keys.get(turn.seat).ok_or_else(|| format!("turn {}: there is no player in seat {}", number, turn.seat + 1))?;
+            if !verify(key, &hash, &turn.signature) {
+                return Err(format!("turn {} wasn't signed by {}", number, self.players[turn.seat].name));
+            }
+            play(&mut game, turn).map_err(|message| format!("turn {}: {}", number, message))?;
+        }
+
+        Ok(game)
+    }
+
+    // Adds a turn that has just been played, signed with the player's key
+    pub fn record(&mut self, key: &SigningKey, seat: usize, round: usize, actions: Vec<Action>) {
+        let previous = match self.turns.last() {
+            Some(turn) => from_hex(&turn.hash).unwrap_or_default(),
+            None => self.genesis(),
+        };
+        let hash = turn_hash(&previous, seat, round, &actions);
+        self.turns.push(Turn {
+            seat,
+            round,
+            actions,
+            hash: to_hex(&hash),
+            signature: to_hex(&key.sign(&hash).to_bytes()),
+        });
+    }
+}
+
+// A new key for a player: the private half goes to `path` and stays with them,
+// the public half goes in the match file. Returns the public key.
+pub fn new_key(path: &str) -> Result<String, String> {
+    let key = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
+    fs::write(path, to_hex(&key.to_bytes())).map_err(|e| format!("can't write {}: {}", path, e))?;
+    Ok(to_hex(key.verifying_key().as_bytes()))
+}
+
+pub fn load_key(path: &str) -> Result<SigningKey, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("can't read {}: {}", path, e))?;
+    from_hex(contents.trim())
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .map(|bytes| SigningKey::from_bytes(&bytes))
+        .ok_or_else(|| format!("{} is not a key file from `--new-key`", path))
+}
+
+// Hash of the tables loaded here, whether from disk or the built-in ones. They decide what
+// every turn does, so replaying a match with other tables would give another match, and
+// a network client with other tables would read nodes and cards the server doesn't have.
+pub fn tables_hash() -> String {
+    let tables = serde_json::to_vec(&(tech::tree(), perks::deck(), yields::table())).expect("tables encode");
+    to_hex(&Sha256::digest(tables))
+}
+
+// `mine.json` keeps its first turn in `mine.turn-001.json`
+fn turn_path(path: &str, number: usize) -> String {
+    let stem = path.strip_suffix(".json").unwrap_or(path);
+    format!("{}.turn-{:03}.json", stem, number)
+}
+
+// A turn ends with the action that leaves the player nothing more to do for now
+pub fn is_commit(action: &Action) -> bool {
+    matches!(action, Action::EndTurn | Action::PickPerk { .. })
+}
+
+// Human seats with a move to make: a perk card to pick or orders to commit
+pub fn waiting_seats(game: &Game) -> Vec<usize> {
+    (0..game.rules.human_count)
+        .filter(|seat| {
+            let miner = &game.miners[*seat];
+            miner.alive
+                && match game.game_state {
+                    GameState::Draft => miner.is_drafting(),
+                    GameState::Playing => !miner.turn_done,
+                    GameState::RoundEnd | GameState::GameOver => false,
+                }
+        })
+        .collect()
+}
+
+// Plays one turn, checking that it was the seat's move and that every action went through.
+// Round results need nobody's move, so the match goes straight on to the next round.
+fn play(game: &mut Game, turn: &Turn) -> Result<(), String> {
+    let name = match game.miners.get(turn.seat) {
+        Some(miner) if turn.seat < game.rules.human_count => miner.name.clone(),
+        _ => return Err(format!("there is no player in seat {}", turn.seat + 1)),
+    };
+    if game.game_state == GameState::GameOver {
+        return Err("the match is already over".to_string());
+    }
+    if turn.round != game.current_round || !waiting_seats(game).contains(&turn.seat) {
+        return Err(format!("it wasn't {}'s move", name));
+    }
+
+    let (last, rest) = turn.actions.split_last().ok_or_else(|| format!("{} did nothing", name))?;
+    if !is_commit(last) || rest.iter().any(is_commit) {
+        return Err(format!("{}'s turn doesn't end with End Turn or a perk pick", name));
+    }
+    for action in &turn.actions {
+        if !game.apply(turn.seat, *action) {
+            return Err(format!("{} couldn't {:?} at that point", name, action));
+        }
+    }
+
+    while game.game_state == GameState::RoundEnd {
+        game.start_next_round();
+    }
+    Ok(())
+}
+
+// Each turn's hash covers the one before it, so changing any turn changes every hash after it
+fn turn_hash(previous: &[u8], seat: usize, round: usize, actions: &[Action]) -> Vec<u8> {
+    let turn = serde_json::to_vec(&(seat, round, actions)).expect("actions encode");
+    let mut hasher = Sha256::new();
+    hasher.update(previous);
+    hasher.update(turn);
+    hasher.finalize().to_vec()
+}
+
+fn parse_public_key(text: &str) -> Option<VerifyingKey> {
+    let bytes = <[u8; 32]>::try_from(from_hex(text)?).ok()?;
+    VerifyingKey::from_bytes(&bytes).ok()
+}
+
+fn verify(key: &VerifyingKey, hash: &[u8], signature: &str) -> bool {
+    let signature = match from_hex(signature).and_then(|bytes| Signature::from_slice(&bytes).ok()) {
+        Some(signature) => signature,
+        None => return false,
+    };
+    key.verify(hash, &signature).is_ok()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| text.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+        .collect()
+}
+
+// One player's side of a play-by-file match: notes what they do and saves every finished turn
+pub struct FileSession {
+    pub path: String,
+    key: SigningKey,
+    pub file: MatchFile,
+    pub seat: usize,
+    actions: Vec<Action>, // Since the last commit
+    round: usize, // Of the first of `actions`
+}
+
+impl FileSession {
+    // Loads and checks the match, then finds the named player's seat in it and makes sure
+    // the key is theirs
+    pub fn open(path: &str, key_path: &str, name: &str) -> Result<(FileSession, Game), String> {
+        let file = MatchFile::load(path)?;
+        let game = file.replay()?;
+        let key = load_key(key_path)?;
+        let names: Vec<&str> = file.players.iter().map(|player| player.name.as_str()).collect();
+        let seat = names
+            .iter()
+            .position(|player| *player == name)
+            .ok_or_else(|| format!("`{}` isn't playing in this match; the players are {}", name, names.join(", ")))?;
+        if parse_public_key(&file.players[seat].key) != Some(key.verifying_key()) {
+            return Err(format!("{} isn't {}'s key", key_path, name));
+        }
+
+        let session = FileSession {
+            path: path.to_string(),
+            key,
+            file,
+            seat,
+            actions: Vec::new(),
+            round: game.current_round,
+        };
+        Ok((session, game))
+    }
+
+    // Notes an action that went through; a commit ends the turn and saves it to a new turn file
+    pub fn played(&mut self, game: &Game, action: Action) {
+        if self.actions.is_empty() {
+            self.round = game.current_round;
+        }
+        self.actions.push(action);
+        if !is_commit(&action) {
+            return;
+        }
+
+        let actions = std::mem::take(&mut self.actions);
+        self.file.record(&self.key, self.seat, self.round, actions);
+        match self.file.save_turn(&self.path) {
+            Ok(turn_path) => println!("Saved your turn to {}, history hash {}", turn_path, self.file.history_hash()),
+            Err(message) => eprintln!("Failed to save your turn: {}", message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A two-player match with a few turns played, and each player's key
+    fn played_match() -> (MatchFile, [SigningKey; 2]) {
+        let keys = [SigningKey::from_bytes(&[1; 32]), SigningKey::from_bytes(&[2; 32])];
+        let public: Vec<String> = keys.iter().map(|key| to_hex(key.verifying_key().as_bytes())).collect();
+        let rules = MatchRules { bot_count: 1, max_rounds: 3, seed: Some(5), ..MatchRules::default() };
+        let mut file = MatchFile::create(rules, vec!["Ada".to_string(), "Grace".to_string()], public).unwrap();
+
+        for _ in 0..4 {
+            play_next(&mut file, &keys);
+        }
+        (file, keys)
+    }
+
+    // Plays the next turn of the first player with a move to make
+    fn play_next(file: &mut MatchFile, keys: &[SigningKey; 2]) {
+        let game = file.replay().unwrap();
+        let seat = waiting_seats(&game)[0];
+        let action = if game.game_state == GameState::Draft { Action::PickPerk { card: 0 } } else { Action::EndTurn };
+        file.record(&keys[seat], seat, game.current_round, vec![action]);
+    }
+
+    #[test]
+    fn an_honest_match_replays() {
+        let (file, _) = played_match();
+        let game = file.replay().unwrap();
+        assert!(game.current_round > 1);
+    }
+
+    #[test]
+    fn a_changed_turn_breaks_the_chain() {
+        let (mut file, _) = played_match();
+        file.turns[1].actions.insert(0, Action::SellAll);
+        assert_eq!(file.replay().unwrap_err(), "turn 2 doesn't follow from the turns before it");
+    }
+
+    #[test]
+    fn a_player_cant_sign_for_another() {
+        let (mut file, keys) = played_match();
+        let turn = file.turns.pop().unwrap();
+        let forger = 1 - turn.seat;
+        file.record(&keys[forger], turn.seat, turn.round, turn.actions);
+        let name = &file.players[turn.seat].name;
+        assert_eq!(file.replay().unwrap_err(), format!("turn {} wasn't signed by {}", file.turns.len(), name));
+    }
+
+    #[test]
+    fn turns_out_of_order_are_refused() {
+        let (mut file, _) = played_match();
+        file.turns.swap(0, 1);
+        assert!(file.replay().is_err());
+    }
+
+    #[test]
+    fn a_turn_played_out_of_turn_is_refused_even_when_signed() {
+        let (mut file, keys) = played_match();
+        play_next(&mut file, &keys);
+        let game = file.replay().unwrap();
+        let idle = (0..2).find(|seat| !waiting_seats(&game).contains(seat)).unwrap();
+        file.record(&keys[idle], idle, game.current_round, vec![Action::EndTurn]);
+        let name = &file.players[idle].name;
+        assert_eq!(file.replay().unwrap_err(), format!("turn {}: it wasn't {}'s move", file.turns.len(), name));
+    }
+
+    #[test]
+    fn rules_out_of_range_are_refused_before_anything_is_played() {
+        for tamper in [
+            |rules: &mut MatchRules| rules.round_seconds = u64::MAX,
+            |rules: &mut MatchRules| rules.bot_count = usize::MAX,
+            |rules: &mut MatchRules| rules.human_count = MAX_HUMANS + 1,
+        ] {
+            let (mut file, _) = played_match();
+            tamper(&mut file.rules);
+            assert!(file.replay().is_err());
+        }
+        let (mut file, _) = played_match();
+        file.rules.max_rounds = 1_000_000;
+        assert_eq!(file.replay().unwrap_err(), "the rules are out of range");
+    }
+
+    #[test]
+    fn turn_files_sit_next_to_the_match_file() {
+        assert_eq!(turn_path("games/mine.json", 7), "games/mine.turn-007.json");
+    }
+}
@@ -1,130 +1,733 @@
-use ggez::Context;
-use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::items::Item;
+use crate::market::Ore;
+use crate::perks::{self, PerkEffects};
+use crate::reserve::Reserve;
+use crate::tech::{self, Branch, Stats, BASE_STATS};
+use crate::yields;
 
 // Constants moved to this module
 pub const STARTING_HEALTH: i32 = 10;
+pub const PLAYER_COLOR_COUNT: usize = 8; // Colours players can pick from in the lobby
+pub const HEAL_AMOUNT: i32 = 3;
+pub const MAX_HEALTH_STEP: i32 = 2; // Added by each max health upgrade
+pub const MAX_HEALTH_UPGRADES: usize = 3;
+pub const DEPOSIT_INTEREST: f32 = 0.05; // Paid on bank deposits at the end of each round
+pub const LOAN_INTEREST: f32 = 0.1; // Added to an outstanding loan at the end of each round
+pub const MAX_LOAN: f32 = 400.0; // Most a miner can owe the bank, interest included
+pub const LOAN_ROUNDS: usize = 3; // A loan falls due at the end of its third round, the one it was taken in included
+pub const LOAN_DEFAULT_DAMAGE: i32 = 2; // Health lost when a loan can't be paid back; shields don't help
+pub const MAX_DAMAGE_REDUCTION: i32 = 2; // Research and a perk card together take no more than this off a round's damage
+pub const STRIKE_SHARE: f32 = 0.4; // A strike at the rock is worth this share of a swing
+pub const COMBO_WINDOW: Duration = Duration::from_millis(600); // Strikes closer together than this build a combo
+pub const MAX_COMBO: usize = 10;
+pub const COMBO_STEP: f32 = 0.1; // Each strike in a combo adds this to the multiplier
+const STRIKE_FATIGUE: f32 = 40.0; // After this many strikes in a round each one is worth half, after twice as many a third
+pub const CRIT_FLASH: Duration = Duration::from_millis(800); // How long a crit stays highlighted
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MinerType {
-    Player,
+    Human(usize), // Hot-seat seat number, 0 for the first player
     Bot,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Miner {
     pub miner_type: MinerType,
+    pub name: String,
     pub gold: f32,
-    pub donated_gold: f32,
-    pub pickaxe_level: usize,
-    pub mine_level: usize,
-    pub last_mine_time: Instant,
+    pub deposit: f32, // In the bank, out of reach of claim jumps and taxes
+    pub loan: f32, // Owed to the bank, interest included
+    pub loan_due: Option<usize>, // Round at whose end the loan has to be paid back
+    pub ore: [f32; 4], // Unsold ore, indexed by `Ore::index`; fractions carry over to the next swing
+    pub reserve: Option<Reserve>, // What is left in this miner's own mine, when mines run dry
+    pub donated_gold: f32, // This round, as credited
+    pub donation_spent: f32, // Gold actually paid in this round, which some auction formats refund
+    pub total_donated: f32, // Whole match, counted once each round is settled so it gives no sealed bid away
+    pub techs: Vec<usize>, // Researched tech tree nodes, in the order they were bought
+    pub redacted_techs: usize, // Researched nodes left out of a network snapshot, so the tier still shows
+    pub mine_timer: Duration, // Time spent on the current swing
+    pub combo: usize, // Strikes in a row, each within COMBO_WINDOW of the last
+    pub combo_timer: Duration, // Time left to keep the combo going
+    pub strikes: usize, // This round, for the fatigue
+    pub last_strike: f32, // Value of the latest strike, at base ore prices
+    pub rolls: u64, // Yield rolls so far this match; the next one is drawn from the seed and this
+    pub last_crit: f32, // Value of the latest critical swing or strike, at base ore prices
+    pub crit_flash: Duration, // Time left to show it
     pub health: i32,
+    pub max_health: i32,
+    pub max_health_level: usize,
+    pub heals_bought: usize, // Each heal costs more than the last
+    pub shields_bought: usize, // Likewise for shields, so a match can't stall behind them
+    pub shielded: bool, // Takes no damage at the end of this round
+    pub stunned_for: Duration, // Can't mine until this runs out
+    pub item_cooldowns: [Duration; 5], // Indexed by `Item::index`
+    pub revealed: Vec<(usize, Duration)>, // Rivals this miner can see through the fog of war, and for how long
+    pub decoy: Option<f32>, // Donation others see this round instead of the real one
+    pub saving_for: Option<Item>, // Item the bot logic holds its gold back for
+    pub researching: Option<usize>, // Tech tree node the bot logic saves up for
+    pub perk_offer: Vec<usize>, // Perk cards dealt for the coming round, empty once one is picked
+    pub perk: Option<usize>, // Perk card kept for this round
+    pub turn_done: bool, // Committed this turn's orders in a turn-based match
     pub alive: bool,
+    pub autopilot: bool, // Human seat with nobody at it, played by the bot logic
+    pub color: Option<usize>, // Index into the player colour palette, picked in the lobby
+    pub eliminated_in: Option<usize>, // Round in which health ran out
 }
 
 impl Miner {
-    pub fn new(miner_type: MinerType) -> Self {
+    pub fn new(miner_type: MinerType, name: String) -> Self {
         Miner {
             miner_type,
+            name,
             gold: 0.0,
+            deposit: 0.0,
+            loan: 0.0,
+            loan_due: None,
+            ore: [0.0; 4],
+            reserve: None,
             donated_gold: 0.0,
-            pickaxe_level: 0,
-            mine_level: 0,
-            last_mine_time: Instant::now(),
+            donation_spent: 0.0,
+            total_donated: 0.0,
+            techs: Vec::new(),
+            redacted_techs: 0,
+            mine_timer: Duration::ZERO,
+            combo: 0,
+            combo_timer: Duration::ZERO,
+            strikes: 0,
+            last_strike: 0.0,
+            rolls: 0,
+            last_crit: 0.0,
+            crit_flash: Duration::ZERO,
             health: STARTING_HEALTH,
+            max_health: STARTING_HEALTH,
+            max_health_level: 0,
+            heals_bought: 0,
+            shields_bought: 0,
+            shielded: false,
+            stunned_for: Duration::ZERO,
+            item_cooldowns: [Duration::ZERO; 5],
+            revealed: Vec::new(),
+            decoy: None,
+            saving_for: None,
+            researching: None,
+            perk_offer: Vec::new(),
+            perk: None,
+            turn_done: false,
             alive: true,
+            autopilot: false,
+            color: None,
+            eliminated_in: None,
         }
     }
 
-    pub fn mine_rate(&self) -> Duration {
-        match self.pickaxe_level {
-            0 => Duration::from_secs_f32(1.0),    // 1 sec (base)
-            1 => Duration::from_secs_f32(0.75),   // 0.75 sec
-            2 => Duration::from_secs_f32(0.5),    // 0.5 sec
-            3 => Duration::from_secs_f32(0.25),   // 0.25 sec
-            4 => Duration::from_secs_f32(0.1),    // 0.1 sec
-            _ => Duration::from_secs_f32(1.0),    // Default to base in case
+    pub fn is_bot(&self) -> bool {
+        self.miner_type == MinerType::Bot
+    }
+
+    // Bots and abandoned human seats make their own decisions
+    pub fn is_automated(&self) -> bool {
+        self.is_bot() || self.autopilot
+    }
+
+    // Base stats plus everything researched so far, then this round's perk
+    pub fn stats(&self) -> Stats {
+        let tree = tech::tree();
+        let mut stats = BASE_STATS;
+        stats.crit_chance += yields::table().base_crit_chance;
+        for &node in &self.techs {
+            stats.add(&tree.nodes[node].effects);
         }
+
+        let perk = self.perk_effects();
+        stats.mining_speed *= 1.0 + perk.speed_bonus;
+        stats.gold_per_swing *= 1.0 + perk.yield_bonus;
+        stats.crit_chance += perk.crit_chance;
+        stats.donation_bonus += perk.donation_bonus;
+        stats.damage_reduction = (stats.damage_reduction + perk.damage_reduction).min(MAX_DAMAGE_REDUCTION);
+        stats
+    }
+
+    // Effects of the perk card kept this round, all zero without one
+    pub fn perk_effects(&self) -> PerkEffects {
+        self.perk.map(|card| perks::deck().cards[card].effects).unwrap_or_default()
     }
 
-    pub fn gold_per_mine(&self) -> f32 {
-        match self.mine_level {
-            0 => 2.0,  // 2g (base)
-            1 => 3.0,  // 3g
-            2 => 5.0,  // 5g
-            3 => 8.0,  // 8g
-            4 => 15.0, // 15g
-            _ => 2.0,  // Default to base in case
+    // Dealt cards and none picked yet
+    pub fn is_drafting(&self) -> bool {
+        !self.perk_offer.is_empty()
+    }
+
+    pub fn mine_rate(&self) -> Duration {
+        Duration::from_secs_f32(1.0 / self.stats().mining_speed.max(0.1))
+    }
+
+    // Gold mining is expected to bring in over the given time at base ore prices, with the
+    // mine's yield rolls and crits averaged. A mine running dry brings in less.
+    pub fn expected_income(&self, seconds: f32) -> f32 {
+        let stats = self.stats();
+        let richness = self.reserve.map_or(1.0, |reserve| reserve.richness());
+        let factor = yields::table().expected_factor(self.mine_level(), stats.crit_chance);
+        stats.mining_speed * stats.gold_per_swing * factor * richness * seconds
+    }
+
+    // Rolls the next swing or strike from this miner's seed: its multiplier and whether it was a crit
+    fn roll_swing(&mut self, seed: u64) -> (f32, bool) {
+        let mut rng = yields::roll_rng(seed, self.rolls);
+        self.rolls += 1;
+        yields::table().roll_swing(self.mine_level(), self.stats().crit_chance, &mut rng)
+    }
+
+    // Shows off a crit worth `value`
+    fn flash_crit(&mut self, value: f32) {
+        self.last_crit = value;
+        self.crit_flash = CRIT_FLASH;
+    }
+
+    pub fn crit_showing(&self) -> bool {
+        !self.crit_flash.is_zero()
+    }
+
+    // How deep the mine goes, which decides the ores it turns up: one level per yield node
+    pub fn mine_level(&self) -> usize {
+        tech::tree().branch(Branch::Yield).filter(|(index, _)| self.has_researched(*index)).count()
+    }
+
+    // Whole units of an ore ready to sell
+    pub fn sellable(&self, ore: Ore) -> f32 {
+        self.ore[ore.index()].floor()
+    }
+
+    pub fn has_researched(&self, node: usize) -> bool {
+        self.techs.contains(&node)
+    }
+
+    // Not bought yet and every prerequisite is
+    pub fn can_research(&self, node: usize) -> bool {
+        match tech::tree().nodes.get(node) {
+            Some(tech_node) => {
+                !self.has_researched(node)
+                    && tech_node.prerequisites.iter().all(|&required| self.has_researched(required))
+            },
+            None => false,
         }
     }
 
-    pub fn pickaxe_upgrade_cost(&self) -> f32 {
-        match self.pickaxe_level {
-            0 => 200.0,  // Level 1: 200g
-            1 => 400.0,  // Level 2: 400g
-            2 => 800.0,  // Level 3: 800g
-            3 => 1600.0, // Level 4: 1600g
+    // First node of the branch that can be researched now, top to bottom
+    pub fn next_in_branch(&self, branch: Branch) -> Option<usize> {
+        tech::tree()
+            .branch(branch)
+            .map(|(index, _)| index)
+            .find(|&index| self.can_research(index))
+    }
+
+    pub fn heal_cost(&self) -> f32 {
+        60.0 * 2f32.powi(self.heals_bought as i32) // 60g, 120g, 240g, ...
+    }
+
+    pub fn shield_cost(&self) -> f32 {
+        100.0 * 2f32.powi(self.shields_bought as i32) // 100g, 200g, 400g, ...
+    }
+
+    pub fn max_health_upgrade_cost(&self) -> f32 {
+        match self.max_health_level {
+            0 => 150.0, // Level 1: 150g
+            1 => 300.0, // Level 2: 300g
+            2 => 600.0, // Level 3: 600g
             _ => f32::MAX, // Can't upgrade further
         }
     }
 
-    pub fn mine_upgrade_cost(&self) -> f32 {
-        match self.mine_level {
-            0 => 100.0,  // Level 1: 100g
-            1 => 300.0,  // Level 2: 300g
-            2 => 600.0,  // Level 3: 600g
-            3 => 1000.0, // Level 4: 1000g
-            _ => f32::MAX, // Can't upgrade further
+    pub fn cooldown_left(&self, item: Item) -> Duration {
+        self.item_cooldowns[item.index()]
+    }
+
+    // Scouted and still visible through the fog
+    pub fn has_revealed(&self, rival: usize) -> bool {
+        self.revealed.iter().any(|(revealed, _)| *revealed == rival)
+    }
+
+    // Keeps a rival visible for at least `duration`
+    pub fn reveal(&mut self, rival: usize, duration: Duration) {
+        match self.revealed.iter_mut().find(|(revealed, _)| *revealed == rival) {
+            Some((_, left)) => *left = (*left).max(duration),
+            None => self.revealed.push((rival, duration)),
         }
     }
 
-    pub fn update(&mut self, _ctx: &Context) {
+    // Rough research level rivals can always make out, from 1 upwards: one tier per four nodes
+    pub fn tier(&self) -> usize {
+        1 + (self.techs.len() + self.redacted_techs) / 4
+    }
+
+    // What rivals see of this round's donation; nothing at all while it is hidden
+    pub fn shown_donation(&self) -> f32 {
+        if self.donation_hidden() {
+            return 0.0;
+        }
+        self.decoy.unwrap_or(self.donated_gold)
+    }
+
+    pub fn donation_hidden(&self) -> bool {
+        self.perk_effects().hidden_donation
+    }
+
+    // `yield_factor` scales every swing, for round events, and `seed` (see `yields::miner_seed`)
+    // decides its rolls. Swings come out of the common deposit when there is one, otherwise
+    // out of the miner's own reserve.
+    pub fn update(&mut self, dt: Duration, yield_factor: f32, seed: u64, common_deposit: Option<&mut Reserve>) {
         if !self.alive {
             return;
         }
 
-        let now = Instant::now();
-        let elapsed = now.duration_since(self.last_mine_time);
+        for cooldown in &mut self.item_cooldowns {
+            *cooldown = cooldown.saturating_sub(dt);
+        }
+        for (_, left) in &mut self.revealed {
+            *left = left.saturating_sub(dt);
+        }
+        self.revealed.retain(|(_, left)| !left.is_zero());
+        self.combo_timer = self.combo_timer.saturating_sub(dt);
+        self.crit_flash = self.crit_flash.saturating_sub(dt);
+
+        // Dynamite stops the pick mid-swing
+        if !self.stunned_for.is_zero() {
+            self.stunned_for = self.stunned_for.saturating_sub(dt);
+            return;
+        }
+
+        self.mine_timer += dt;
         
-        if elapsed >= self.mine_rate() {
-            // Mine gold
-            self.gold += self.gold_per_mine();
-            self.last_mine_time = now;
+        if self.mine_timer >= self.mine_rate() {
+            // Mine ore, more or less as the roll goes; it only becomes gold at the market
+            let (roll, crit) = self.roll_swing(seed);
+            let value = self.stats().gold_per_swing * yield_factor * roll;
+            let mine_level = self.mine_level();
+            let value = match common_deposit.or(self.reserve.as_mut()) {
+                Some(reserve) => reserve.extract(value),
+                None => value,
+            };
+            for ore in Ore::ALL {
+                self.ore[ore.index()] += ore.per_swing(mine_level, value);
+            }
+            if crit {
+                self.flash_crit(value);
+            }
+            self.mine_timer = Duration::ZERO;
+        }
+    }
+
+    // Mines a stretch of a turn-based round at once: the ore for the time not spent stunned,
+    // with one yield roll for the whole stretch and crits averaged in rather than rolled.
+    // Timers run down as if that much of the round had been played out.
+    pub fn mine_for(&mut self, seconds: f32, yield_factor: f32, seed: u64, common_deposit: Option<&mut Reserve>) {
+        if !self.alive {
+            return;
+        }
+
+        let stretch = Duration::from_secs_f32(seconds);
+        for cooldown in &mut self.item_cooldowns {
+            *cooldown = cooldown.saturating_sub(stretch);
+        }
+        self.revealed.clear();
+
+        let mining = (seconds - self.stunned_for.as_secs_f32()).max(0.0);
+        self.stunned_for = self.stunned_for.saturating_sub(stretch);
+        self.mine_timer = Duration::ZERO;
+        self.crit_flash = Duration::ZERO;
+
+        let stats = self.stats();
+        let mine_level = self.mine_level();
+        let table = yields::table();
+        let roll = table.roll(mine_level, &mut yields::roll_rng(seed, self.rolls));
+        self.rolls += 1;
+        let value = stats.mining_speed * mining * stats.gold_per_swing * roll * table.crit_factor(stats.crit_chance) * yield_factor;
+        let value = match common_deposit.or(self.reserve.as_mut()) {
+            Some(reserve) => reserve.extract(value),
+            None => value,
+        };
+        for ore in Ore::ALL {
+            self.ore[ore.index()] += ore.per_swing(mine_level, value);
+        }
+    }
+
+    // Multiplier the current combo gives a strike
+    pub fn combo_multiplier(&self) -> f32 {
+        1.0 + self.combo as f32 * COMBO_STEP
+    }
+
+    // Whether the next strike keeps the combo going
+    pub fn combo_running(&self) -> bool {
+        !self.combo_timer.is_zero()
+    }
+
+    // Strikes the rock by hand for ore on top of the steady swings, rolled like a swing. Quick
+    // strikes build a combo; the more strikes in a round, the less each one brings up.
+    // Stunned miners can't.
+    pub fn strike(&mut self, yield_factor: f32, seed: u64, common_deposit: Option<&mut Reserve>) -> bool {
+        if !self.alive || !self.stunned_for.is_zero() {
+            return false;
+        }
+
+        self.combo = if self.combo_running() { (self.combo + 1).min(MAX_COMBO) } else { 0 };
+        self.combo_timer = COMBO_WINDOW;
+        let fatigue = 1.0 / (1.0 + self.strikes as f32 / STRIKE_FATIGUE);
+        self.strikes += 1;
+
+        let (roll, crit) = self.roll_swing(seed);
+        let value = self.stats().gold_per_swing * STRIKE_SHARE * self.combo_multiplier() * fatigue * roll * yield_factor;
+        let mine_level = self.mine_level();
+        let value = match common_deposit.or(self.reserve.as_mut()) {
+            Some(reserve) => reserve.extract(value),
+            None => value,
+        };
+        for ore in Ore::ALL {
+            self.ore[ore.index()] += ore.per_swing(mine_level, value);
+        }
+        self.last_strike = value;
+        if crit {
+            self.flash_crit(value);
+        }
+        true
+    }
+
+    pub fn research(&mut self, node: usize) -> bool {
+        if !self.can_research(node) {
+            return false;
+        }
+
+        let cost = tech::tree().nodes[node].cost;
+        if self.gold < cost {
+            return false;
+        }
+
+        self.gold -= cost;
+        self.techs.push(node);
+        true
+    }
+
+    // Keeps one of the dealt cards; `choice` is its position in the offer
+    pub fn pick_perk(&mut self, choice: usize) -> bool {
+        let card = match self.perk_offer.get(choice) {
+            Some(card) => *card,
+            None => return false,
+        };
+
+        self.perk = Some(card);
+        self.perk_offer.clear();
+        self.gold += perks::deck().cards[card].effects.gold;
+        true
+    }
+
+    pub fn buy_heal(&mut self) -> bool {
+        if self.health >= self.max_health || self.gold < self.heal_cost() {
+            return false;
+        }
+
+        self.gold -= self.heal_cost();
+        self.heals_bought += 1;
+        self.health = (self.health + HEAL_AMOUNT).min(self.max_health);
+        true
+    }
+
+    pub fn buy_shield(&mut self) -> bool {
+        if self.shielded || self.gold < self.shield_cost() {
+            return false;
         }
+
+        self.gold -= self.shield_cost();
+        self.shields_bought += 1;
+        self.shielded = true;
+        true
     }
 
-    pub fn upgrade_pickaxe(&mut self) -> bool {
-        if self.pickaxe_level >= 4 || self.gold < self.pickaxe_upgrade_cost() {
+    // Raises the health cap and fills the new room straight away
+    pub fn upgrade_max_health(&mut self) -> bool {
+        if self.max_health_level >= MAX_HEALTH_UPGRADES || self.gold < self.max_health_upgrade_cost() {
             return false;
         }
 
-        self.gold -= self.pickaxe_upgrade_cost();
-        self.pickaxe_level += 1;
+        self.gold -= self.max_health_upgrade_cost();
+        self.max_health_level += 1;
+        self.max_health += MAX_HEALTH_STEP;
+        self.health += MAX_HEALTH_STEP;
         true
     }
 
-    pub fn upgrade_mine(&mut self) -> bool {
-        if self.mine_level >= 4 || self.gold < self.mine_upgrade_cost() {
+    // Donation research and `credit_factor` (for round events) credit more than the gold actually spent
+    pub fn contribute_gold(&mut self, amount: f32, credit_factor: f32) -> bool {
+        if amount <= 0.0 || amount > self.gold {
             return false;
         }
 
-        self.gold -= self.mine_upgrade_cost();
-        self.mine_level += 1;
+        let credited = amount * (1.0 + self.stats().donation_bonus) * credit_factor;
+        self.gold -= amount;
+        self.donation_spent += amount;
+        self.donated_gold += credited;
         true
     }
 
-    pub fn contribute_gold(&mut self, amount: f32) {
-        if amount <= self.gold {
-            self.gold -= amount;
-            self.donated_gold += amount;
+    // Hands back a share of this round's donation; the refunded part won't count towards the match total
+    pub fn refund_donation(&mut self, share: f32) -> f32 {
+        let refund = self.donation_spent * share;
+        self.gold += refund;
+        self.donated_gold -= self.donated_gold * share;
+        refund
+    }
+
+    pub fn deposit_gold(&mut self, amount: f32) -> bool {
+        if amount <= 0.0 || amount > self.gold {
+            return false;
+        }
+
+        self.gold -= amount;
+        self.deposit += amount;
+        true
+    }
+
+    pub fn withdraw(&mut self, amount: f32) -> bool {
+        if amount <= 0.0 || amount > self.deposit {
+            return false;
+        }
+
+        self.deposit -= amount;
+        self.gold += amount;
+        true
+    }
+
+    // More borrowing doesn't put off the day the first loan falls due
+    pub fn borrow(&mut self, amount: f32, due: usize) -> bool {
+        if amount <= 0.0 || self.loan + amount > MAX_LOAN {
+            return false;
+        }
+
+        self.gold += amount;
+        self.loan += amount;
+        self.loan_due.get_or_insert(due);
+        true
+    }
+
+    // Most the bank would still lend
+    pub fn credit_left(&self) -> f32 {
+        (MAX_LOAN - self.loan).max(0.0)
+    }
+
+    pub fn repay(&mut self, amount: f32) -> bool {
+        let amount = amount.min(self.loan);
+        if amount <= 0.0 || amount > self.gold {
+            return false;
+        }
+
+        self.gold -= amount;
+        self.loan -= amount;
+        if self.loan < 0.01 {
+            self.loan = 0.0;
+            self.loan_due = None;
+        }
+        true
+    }
+
+    // The bank's end of round: deposits earn interest and loans grow. A loan that has fallen due
+    // is paid off from gold, then from deposits; whatever can't be paid is written off and
+    // costs health. Returns the unpaid amount in that case.
+    pub fn settle_bank(&mut self, round: usize) -> Option<f32> {
+        self.deposit += self.deposit * DEPOSIT_INTEREST;
+        if self.loan <= 0.0 {
+            return None;
+        }
+
+        self.loan += self.loan * LOAN_INTEREST;
+        if self.loan_due.is_some_and(|due| round < due) {
+            return None;
+        }
+
+        let from_gold = self.gold.min(self.loan);
+        self.gold -= from_gold;
+        self.loan -= from_gold;
+        let from_deposit = self.deposit.min(self.loan);
+        self.deposit -= from_deposit;
+        self.loan -= from_deposit;
+
+        self.loan_due = None;
+        let unpaid = std::mem::take(&mut self.loan);
+        if unpaid < 0.01 {
+            return None;
+        }
+
+        self.health = (self.health - LOAN_DEFAULT_DAMAGE).max(0);
+        if self.health == 0 {
+            self.alive = false;
         }
+        Some(unpaid)
+    }
+
+    // Paid on unspent gold at the end of a round
+    pub fn pay_interest(&mut self) {
+        self.gold += self.gold * self.stats().interest;
     }
 
+    // Damage reduction never takes a hit below 1, so miners who all have some still
+    // lose health and a last standing match can finish
     pub fn take_damage(&mut self, damage: i32) {
-        self.health -= damage;
+        if self.shielded {
+            return;
+        }
+
+        self.health -= (damage - self.stats().damage_reduction).max(damage.min(1));
         if self.health <= 0 {
             self.alive = false;
             self.health = 0;
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn borrower(loan: f32, due: usize) -> Miner {
+        let mut miner = Miner::new(MinerType::Bot, "Borrower".to_string());
+        miner.loan = loan;
+        miner.loan_due = Some(due);
+        miner
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    // Every technique and the best perk card for shrugging off damage
+    fn thick_skinned() -> Miner {
+        let mut miner = Miner::new(MinerType::Bot, "Tough".to_string());
+        miner.techs = (0..tech::tree().nodes.len()).collect();
+        let cards = &perks::deck().cards;
+        miner.perk = (0..cards.len()).max_by_key(|card| cards[*card].effects.damage_reduction);
+        miner
+    }
+
+    #[test]
+    fn damage_reduction_is_capped() {
+        assert_eq!(thick_skinned().stats().damage_reduction, MAX_DAMAGE_REDUCTION);
+    }
+
+    #[test]
+    fn damage_always_costs_at_least_one_health() {
+        let mut miner = thick_skinned();
+        let health = miner.health;
+        miner.take_damage(1);
+        assert_eq!(miner.health, health - 1);
+        miner.take_damage(0);
+        assert_eq!(miner.health, health - 1);
+    }
+
+    #[test]
+    fn deposits_earn_interest() {
+        let mut miner = Miner::new(MinerType::Bot, "Saver".to_string());
+        miner.deposit = 100.0;
+        assert_eq!(miner.settle_bank(1), None);
+        assert!(close(miner.deposit, 100.0 * (1.0 + DEPOSIT_INTEREST)));
+    }
+
+    #[test]
+    fn a_loan_accrues_interest_until_it_is_due() {
+        let mut miner = borrower(100.0, 3);
+        miner.gold = 500.0;
+        assert_eq!(miner.settle_bank(2), None);
+        assert!(close(miner.loan, 100.0 * (1.0 + LOAN_INTEREST)));
+        assert_eq!(miner.gold, 500.0);
+    }
+
+    #[test]
+    fn a_due_loan_is_paid_from_gold_then_the_deposit() {
+        let mut miner = borrower(100.0, 3);
+        miner.gold = 60.0;
+        miner.deposit = 100.0;
+        assert_eq!(miner.settle_bank(3), None);
+        assert_eq!(miner.gold, 0.0);
+        assert!(close(miner.deposit, 100.0 * (1.0 + DEPOSIT_INTEREST) - (110.0 - 60.0)));
+        assert_eq!((miner.loan, miner.loan_due), (0.0, None));
+        assert_eq!(miner.health, STARTING_HEALTH);
+    }
+
+    #[test]
+    fn an_unpaid_loan_is_a_default_even_behind_a_shield() {
+        let mut miner = borrower(100.0, 3);
+        miner.gold = 10.0;
+        miner.shielded = true;
+        let unpaid = miner.settle_bank(3).unwrap();
+        assert!(close(unpaid, 100.0));
+        assert_eq!((miner.loan, miner.loan_due), (0.0, None));
+        assert_eq!(miner.health, STARTING_HEALTH - LOAN_DEFAULT_DAMAGE);
+        assert!(miner.alive);
+    }
+
+    #[test]
+    fn a_default_can_eliminate() {
+        let mut miner = borrower(100.0, 3);
+        miner.health = LOAN_DEFAULT_DAMAGE;
+        assert!(miner.settle_bank(3).is_some());
+        assert_eq!(miner.health, 0);
+        assert!(!miner.alive);
+    }
+
+    // A miner's first strike of a round, with nothing in the way
+    fn striker() -> Miner {
+        let mut miner = Miner::new(MinerType::Bot, "Striker".to_string());
+        miner.reserve = None;
+        miner
+    }
+
+    #[test]
+    fn strikes_inside_the_combo_window_build_a_combo() {
+        let mut miner = striker();
+        assert!(miner.strike(1.0, 5, None));
+        miner.update(COMBO_WINDOW / 2, 1.0, 5, None);
+        assert!(miner.combo_running());
+        assert!(miner.strike(1.0, 5, None));
+        assert_eq!(miner.combo, 1);
+
+        // Letting the window run out starts over
+        miner.update(COMBO_WINDOW, 1.0, 5, None);
+        assert!(!miner.combo_running());
+        assert!(miner.strike(1.0, 5, None));
+        assert_eq!(miner.combo, 0);
+    }
+
+    #[test]
+    fn a_combo_tops_out() {
+        let mut miner = striker();
+        for _ in 0..MAX_COMBO + 5 {
+            miner.strike(1.0, 5, None);
+        }
+        assert_eq!(miner.combo, MAX_COMBO);
+    }
+
+    // Strikes compared on the same roll, so only the combo and fatigue tell them apart
+    #[test]
+    fn a_combo_adds_to_a_strike_and_fatigue_takes_away() {
+        let mut fresh = striker();
+        fresh.strike(1.0, 5, None);
+
+        let mut combo = striker();
+        combo.combo_timer = COMBO_WINDOW;
+        combo.strike(1.0, 5, None);
+        assert!(close(combo.last_strike / fresh.last_strike, 1.0 + COMBO_STEP));
+
+        for (strikes, share) in [(STRIKE_FATIGUE, 0.5), (2.0 * STRIKE_FATIGUE, 1.0 / 3.0)] {
+            let mut tired = striker();
+            tired.strikes = strikes as usize;
+            tired.strike(1.0, 5, None);
+            assert!(close(tired.last_strike / fresh.last_strike, share));
+        }
+    }
+
+    #[test]
+    fn a_stunned_miner_cant_strike() {
+        let mut miner = striker();
+        miner.stunned_for = Duration::from_secs(1);
+        assert!(!miner.strike(1.0, 5, None));
+        assert_eq!((miner.strikes, miner.ore), (0, [0.0; 4]));
+    }
+}
@@ -0,0 +1,818 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::game::{Action, Game, GameState};
+use crate::match_file::tables_hash;
+use crate::miner::PLAYER_COLOR_COUNT;
+use crate::observer::StateStream;
+use crate::rules::{MatchRules, MAX_HUMANS};
+use crate::{perks, tech, yields};
+
+// Bumped whenever a message or the snapshot layout changes shape
+pub const PROTOCOL_VERSION: u32 = 20;
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+pub const MAX_NAME_CHARS: usize = 16;
+
+const TICK_INTERVAL: Duration = Duration::from_millis(16); // ~60 simulation steps a second
+const SNAPSHOT_INTERVAL: Duration = Duration::from_millis(50); // ~20 snapshots a second
+const READY_TIMEOUT: Duration = Duration::from_secs(15); // Longest the results screen waits for players
+const WRITE_TIMEOUT: Duration = Duration::from_millis(500); // A client that stops reading is dropped after this, not waited on
+const MAX_MESSAGE_BYTES: u64 = 1 << 20; // Far more than a snapshot takes; a longer line is a protocol error
+
+// Every message is one line of JSON, tagged with its `type`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Hello {
+        version: u32,
+        name: String,
+        #[serde(default)]
+        spectate: bool, // Only watch, even if there is room to play
+        #[serde(default)]
+        tables: String, // Hash of the tech tree, perk deck and yield table; both ends need the same
+    },
+    SetName { name: String },
+    SetColor { color: usize },
+    SetReady { ready: bool },
+    SetRules { rules: MatchRules }, // Host only
+    Start, // Host only
+    Action { action: Action },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Welcome {
+        version: u32,
+        id: usize,
+        #[serde(default)]
+        tables: String,
+    },
+    Lobby { lobby: Lobby },
+    Seat { seat: Option<usize> }, // Which miner this client plays, if any
+    Snapshot { game: Game },
+    Rejected { reason: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LobbyPlayer {
+    pub id: usize,
+    pub name: String,
+    pub color: usize,
+    pub ready: bool,
+}
+
+// The room before a match: its rules and who is waiting to play. The first player is the host.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lobby {
+    pub rules: MatchRules,
+    pub players: Vec<LobbyPlayer>,
+    pub spectators: usize,
+}
+
+impl Lobby {
+    pub fn host(&self) -> Option<usize> {
+        self.players.first().map(|player| player.id)
+    }
+
+    pub fn player(&self, id: usize) -> Option<&LobbyPlayer> {
+        self.players.iter().find(|player| player.id == id)
+    }
+
+    pub fn can_start(&self) -> bool {
+        !self.players.is_empty() && self.players.iter().all(|player| player.ready)
+    }
+}
+
+fn write_message<T: Serialize>(stream: &mut TcpStream, message: &T) -> io::Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())
+}
+
+fn read_message<T, R>(reader: &mut R) -> io::Result<Option<T>>
+where
+    T: for<'de> Deserialize<'de>,
+    R: BufRead,
+{
+    let mut line = String::new();
+    if reader.by_ref().take(MAX_MESSAGE_BYTES).read_line(&mut line)? == 0 {
+        return Ok(None); // Connection closed
+    }
+    // Cut off at the limit, or by the connection closing halfway through
+    if !line.ends_with('\n') {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "message too long or cut short"));
+    }
+    serde_json::from_str(&line).map(Some).map_err(io::Error::from)
+}
+
+// Nodes and cards are sent by index, so both ends have to load the same tables
+fn tables_mismatch() -> String {
+    format!(
+        "the server plays with another {}, {} or {} than this client",
+        tech::TECH_TREE_FILE,
+        perks::PERKS_FILE,
+        yields::YIELDS_FILE
+    )
+}
+
+// Names are trimmed and shortened; an empty one keeps the old name
+fn clean_name(name: &str) -> Option<String> {
+    let name: String = name.trim().chars().filter(|c| !c.is_control()).take(MAX_NAME_CHARS).collect();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+enum ServerEvent {
+    Connected(usize, TcpStream),
+    Message(usize, ClientMessage),
+    Disconnected(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Connecting, // Waiting for the hello
+    Player,
+    Spectator,
+}
+
+struct Connection {
+    id: usize,
+    stream: TcpStream,
+    role: Role,
+    name: String,
+    color: usize,
+    ready: bool,
+    seat: Option<usize>, // Miner played during a match
+    spectate_only: bool, // Asked to watch; never moved into the lobby
+}
+
+// Hosts a room: players gather in the lobby, the host starts the match, and the
+// server runs it for everyone. Anyone joining mid-match watches until the next lobby.
+pub struct Server {
+    rules: MatchRules, // Rules for the next match, as set in the lobby
+    game: Option<Game>, // None while in the lobby
+    local_addr: SocketAddr,
+    connections: Vec<Connection>,
+    events: Receiver<ServerEvent>,
+    ready_seats: HashSet<usize>, // Seats that asked to continue or restart
+    waiting_time: Duration, // Time spent on the current results screen
+    since_snapshot: Duration,
+    lobby_changed: bool,
+    state_stream: Option<StateStream>, // Local dashboards following the match
+}
+
+impl Server {
+    pub fn bind<A: ToSocketAddrs>(address: A, rules: MatchRules) -> io::Result<Server> {
+        let listener = TcpListener::bind(address)?;
+        let local_addr = listener.local_addr()?;
+        let (sender, events) = mpsc::channel();
+
+        thread::spawn(move || accept_connections(listener, sender));
+
+        Ok(Server {
+            rules,
+            game: None,
+            local_addr,
+            connections: Vec::new(),
+            events,
+            ready_seats: HashSet::new(),
+            waiting_time: Duration::ZERO,
+            since_snapshot: Duration::ZERO,
+            lobby_changed: false,
+            state_stream: None,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn stream_state_to(&mut self, stream: StateStream) {
+        self.state_stream = Some(stream);
+    }
+
+    // Serves forever at a fixed tick rate
+    pub fn run(&mut self) {
+        let mut last_step = Instant::now();
+        loop {
+            thread::sleep(TICK_INTERVAL);
+            let now = Instant::now();
+            self.step(now - last_step);
+            last_step = now;
+        }
+    }
+
+    // Handles pending client messages, then advances the match by `dt`
+    pub fn step(&mut self, dt: Duration) {
+        let state_before = self.game.as_ref().map(|game| game.game_state);
+
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                ServerEvent::Connected(id, stream) => {
+                    self.connections.push(Connection {
+                        id,
+                        stream,
+                        role: Role::Connecting,
+                        name: String::new(),
+                        color: 0,
+                        ready: false,
+                        seat: None,
+                        spectate_only: false,
+                    });
+                },
+                ServerEvent::Message(id, message) => self.handle_message(id, message),
+                ServerEvent::Disconnected(id) => self.disconnect(id),
+            }
+        }
+
+        // A match nobody is playing any more goes back to the lobby
+        if self.game.is_some() && self.players_seated() == 0 {
+            self.return_to_lobby();
+        }
+
+        let waiting = match &mut self.game {
+            Some(game) if matches!(game.game_state, GameState::Draft | GameState::Playing) => {
+                game.tick(dt);
+                false
+            },
+            Some(_) => true, // Results or game over screen
+            None => false,
+        };
+
+        if waiting {
+            self.waiting_time += dt;
+            if self.waiting_time >= READY_TIMEOUT || self.everyone_ready() {
+                self.advance();
+            }
+        }
+
+        if let Some(game) = &self.game {
+            self.since_snapshot += dt;
+            if self.since_snapshot >= SNAPSHOT_INTERVAL || Some(game.game_state) != state_before {
+                self.since_snapshot = Duration::ZERO;
+                if let Some(stream) = &self.state_stream {
+                    stream.publish(game);
+                }
+                self.send_snapshots();
+            }
+        } else if self.lobby_changed {
+            self.lobby_changed = false;
+            self.broadcast(&ServerMessage::Lobby { lobby: self.lobby() });
+        }
+    }
+
+    pub fn lobby(&self) -> Lobby {
+        let players = self
+            .connections
+            .iter()
+            .filter(|connection| connection.role == Role::Player)
+            .map(|connection| LobbyPlayer {
+                id: connection.id,
+                name: connection.name.clone(),
+                color: connection.color,
+                ready: connection.ready,
+            })
+            .collect();
+
+        // A seed would let players work out the events and rolls to come
+        Lobby {
+            rules: MatchRules { seed: None, ..self.rules.clone() },
+            players,
+            spectators: self.connections.iter().filter(|connection| connection.role == Role::Spectator).count(),
+        }
+    }
+
+    fn handle_message(&mut self, id: usize, message: ClientMessage) {
+        let (role, seat) = match self.connection(id) {
+            Some(connection) => (connection.role, connection.seat),
+            None => return,
+        };
+        let in_lobby = self.game.is_none();
+        let is_host = self.lobby().host() == Some(id);
+
+        match message {
+            ClientMessage::Hello { version, name, spectate, tables } if role == Role::Connecting => {
+                self.join(id, version, &name, spectate, &tables)
+            },
+            ClientMessage::SetName { name } if in_lobby && role == Role::Player => {
+                if let (Some(name), Some(connection)) = (clean_name(&name), self.connection_mut(id)) {
+                    connection.name = name;
+                }
+                self.lobby_changed = true;
+            },
+            ClientMessage::SetColor { color } if in_lobby && role == Role::Player => {
+                if let Some(connection) = self.connection_mut(id) {
+                    connection.color = color % PLAYER_COLOR_COUNT;
+                }
+                self.lobby_changed = true;
+            },
+            ClientMessage::SetReady { ready } if in_lobby && role == Role::Player => {
+                if let Some(connection) = self.connection_mut(id) {
+                    connection.ready = ready;
+                }
+                self.lobby_changed = true;
+            },
+            ClientMessage::SetRules { rules } if in_lobby && is_host => {
+                // The seed is the server's own; clients never get to see it
+                let seed = self.rules.seed;
+                self.rules = MatchRules { seed, ..rules.clamped() };
+                // Everyone confirms the new rules again
+                for connection in &mut self.connections {
+                    connection.ready = false;
+                }
+                self.lobby_changed = true;
+            },
+            ClientMessage::Start if in_lobby && is_host && self.lobby().can_start() => self.start_match(),
+            ClientMessage::Action { action } => {
+                let (game, seat) = match (&mut self.game, seat) {
+                    (Some(game), Some(seat)) => (game, seat),
+                    _ => return, // Spectators and stale lobby clicks
+                };
+                match (action, game.game_state) {
+                    (Action::Continue, GameState::RoundEnd) | (Action::Restart, GameState::GameOver) => {
+                        self.ready_seats.insert(seat);
+                    },
+                    _ => {
+                        game.apply(seat, action);
+                    },
+                }
+            },
+            ClientMessage::Hello { .. } => self.reject(id, "unexpected hello"),
+            // Lobby messages that arrive just after the match started, or from non-hosts
+            _ => {},
+        }
+    }
+
+    fn join(&mut self, id: usize, version: u32, name: &str, spectate: bool, tables: &str) {
+        if version != PROTOCOL_VERSION {
+            let reason = format!("server speaks protocol version {}, client {}", PROTOCOL_VERSION, version);
+            self.reject(id, &reason);
+            return;
+        }
+        if tables != tables_hash() {
+            self.reject(id, &tables_mismatch());
+            return;
+        }
+
+        let lobby = self.lobby();
+        let used_colors: Vec<usize> = lobby.players.iter().map(|player| player.color).collect();
+        let color = (0..PLAYER_COLOR_COUNT).find(|color| !used_colors.contains(color)).unwrap_or(0);
+        let role = if !spectate && self.game.is_none() && lobby.players.len() < MAX_HUMANS {
+            Role::Player
+        } else {
+            Role::Spectator
+        };
+        let default_name = format!("Player {}", id + 1);
+
+        // Late joiners watch, so they see what a spectator would
+        let snapshot = self.game.as_ref().map(|game| game.redacted_for(None));
+        let connection = match self.connection_mut(id) {
+            Some(connection) => connection,
+            None => return,
+        };
+        connection.role = role;
+        connection.spectate_only = spectate;
+        connection.color = color;
+        connection.name = clean_name(name).unwrap_or(default_name);
+        println!("{} joined as a {}", connection.name, if role == Role::Player { "player" } else { "spectator" });
+
+        let mut sent = write_message(&mut connection.stream, &ServerMessage::Welcome { version: PROTOCOL_VERSION, id, tables: tables_hash() });
+        // Late joiners go straight to watching the match
+        if let (Some(game), Ok(())) = (snapshot, &sent) {
+            sent = write_message(&mut connection.stream, &ServerMessage::Snapshot { game });
+        }
+
+        if sent.is_err() {
+            self.disconnect(id);
+        }
+        self.lobby_changed = true;
+    }
+
+    fn start_match(&mut self) {
+        let mut rules = self.rules.clone();
+        rules.human_count = self.lobby().players.len();
+        let mut game = Game::new(rules);
+
+        let players = self.connections.iter_mut().filter(|connection| connection.role == Role::Player);
+        for (seat, connection) in players.enumerate() {
+            let miner = &mut game.miners[seat];
+            miner.name = connection.name.clone();
+            miner.color = Some(connection.color);
+            connection.seat = Some(seat);
+        }
+        println!("Match started with {} players and {} bots", game.rules.human_count, game.rules.bot_count);
+
+        self.game = Some(game);
+        self.ready_seats.clear();
+        self.waiting_time = Duration::ZERO;
+        self.send_seats();
+    }
+
+    // Back to the lobby after a match; spectators take any free places
+    fn return_to_lobby(&mut self) {
+        self.game = None;
+        self.ready_seats.clear();
+
+        let mut players = self.connections.iter().filter(|connection| connection.role == Role::Player).count();
+        for connection in &mut self.connections {
+            connection.seat = None;
+            connection.ready = false;
+            if connection.role == Role::Spectator && !connection.spectate_only && players < MAX_HUMANS {
+                connection.role = Role::Player;
+                players += 1;
+            }
+        }
+
+        self.send_seats();
+        self.lobby_changed = true;
+    }
+
+    fn send_seats(&mut self) {
+        let mut failed = Vec::new();
+        for connection in self.connections.iter_mut().filter(|connection| connection.role != Role::Connecting) {
+            let message = ServerMessage::Seat { seat: connection.seat };
+            if write_message(&mut connection.stream, &message).is_err() {
+                failed.push(connection.id);
+            }
+        }
+
+        for id in failed {
+            self.disconnect(id);
+        }
+    }
+
+    fn reject(&mut self, id: usize, reason: &str) {
+        if let Some(connection) = self.connection_mut(id) {
+            let rejected = ServerMessage::Rejected { reason: reason.to_string() };
+            let _ = write_message(&mut connection.stream, &rejected);
+        }
+        self.disconnect(id);
+    }
+
+    // Drops a connection; a seat in a running match goes back to the bot logic
+    fn disconnect(&mut self, id: usize) {
+        let index = match self.connections.iter().position(|connection| connection.id == id) {
+            Some(index) => index,
+            None => return,
+        };
+
+        let connection = self.connections.remove(index);
+        let _ = connection.stream.shutdown(std::net::Shutdown::Both);
+        if connection.role != Role::Connecting {
+            println!("{} left", connection.name);
+        }
+
+        if let (Some(seat), Some(game)) = (connection.seat, &mut self.game) {
+            game.miners[seat].autopilot = true;
+            self.ready_seats.remove(&seat);
+        }
+        self.lobby_changed = true;
+    }
+
+    fn connection(&self, id: usize) -> Option<&Connection> {
+        self.connections.iter().find(|connection| connection.id == id)
+    }
+
+    fn connection_mut(&mut self, id: usize) -> Option<&mut Connection> {
+        self.connections.iter_mut().find(|connection| connection.id == id)
+    }
+
+    fn players_seated(&self) -> usize {
+        self.connections.iter().filter(|connection| connection.seat.is_some()).count()
+    }
+
+    fn everyone_ready(&self) -> bool {
+        self.connections
+            .iter()
+            .filter_map(|connection| connection.seat)
+            .all(|seat| self.ready_seats.contains(&seat))
+    }
+
+    // Leaves the results or game over screen
+    fn advance(&mut self) {
+        let game = match &mut self.game {
+            Some(game) => game,
+            None => return,
+        };
+
+        match game.game_state {
+            GameState::RoundEnd => game.start_next_round(),
+            GameState::GameOver => {
+                self.return_to_lobby();
+                return;
+            },
+            GameState::Draft | GameState::Playing => {},
+        }
+        self.ready_seats.clear();
+        self.waiting_time = Duration::ZERO;
+    }
+
+    // Sends every client the match as their seat sees it, so nothing hidden from them
+    // crosses the wire
+    fn send_snapshots(&mut self) {
+        let game = match &self.game {
+            Some(game) => game,
+            None => return,
+        };
+
+        let spectator_view = ServerMessage::Snapshot { game: game.redacted_for(None) };
+        let mut failed = Vec::new();
+        for connection in self.connections.iter_mut().filter(|connection| connection.role != Role::Connecting) {
+            let sent = match connection.seat {
+                Some(seat) => write_message(&mut connection.stream, &ServerMessage::Snapshot { game: game.redacted_for(Some(seat)) }),
+                None => write_message(&mut connection.stream, &spectator_view),
+            };
+            if sent.is_err() {
+                failed.push(connection.id);
+            }
+        }
+
+        for id in failed {
+            self.disconnect(id);
+        }
+    }
+
+    fn broadcast(&mut self, message: &ServerMessage) {
+        let mut failed = Vec::new();
+        for connection in self.connections.iter_mut().filter(|connection| connection.role != Role::Connecting) {
+            if write_message(&mut connection.stream, message).is_err() {
+                failed.push(connection.id);
+            }
+        }
+
+        for id in failed {
+            self.disconnect(id);
+        }
+    }
+}
+
+fn accept_connections(listener: TcpListener, events: Sender<ServerEvent>) {
+    for (id, stream) in listener.incoming().enumerate() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Failed to accept a connection: {}", e);
+                continue;
+            },
+        };
+        let _ = stream.set_nodelay(true);
+        // One stuck client mustn't hold up every other one: a write that times out fails
+        // like any other, and a failed write drops the client
+        if let Err(e) = stream.set_write_timeout(Some(WRITE_TIMEOUT)) {
+            eprintln!("Failed to accept a connection: {}", e);
+            continue;
+        }
+
+        let reader = match stream.try_clone() {
+            Ok(reader) => reader,
+            Err(e) => {
+                eprintln!("Failed to accept a connection: {}", e);
+                continue;
+            },
+        };
+
+        if events.send(ServerEvent::Connected(id, stream)).is_err() {
+            return; // Server is gone
+        }
+
+        let events = events.clone();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            while let Ok(Some(message)) = read_message(&mut reader) {
+                if events.send(ServerEvent::Message(id, message)).is_err() {
+                    return;
+                }
+            }
+            let _ = events.send(ServerEvent::Disconnected(id));
+        });
+    }
+}
+
+// What the screen should show, as last reported by the server
+pub enum ServerUpdate {
+    Lobby(Lobby),
+    Game(Game),
+}
+
+// A player's connection to a server. The lobby or game on screen is whatever the
+// server last sent; actions go to the server instead of changing it locally.
+pub struct NetClient {
+    pub id: usize,
+    pub seat: Option<usize>, // None in the lobby and while spectating
+    stream: TcpStream,
+    messages: Receiver<ServerMessage>,
+    connected: bool,
+}
+
+impl NetClient {
+    // Joins a server and waits for it to accept us and send the lobby or match to show.
+    // Spectators never get a seat.
+    pub fn connect<A: ToSocketAddrs>(address: A, name: &str, spectate: bool) -> io::Result<(NetClient, ServerUpdate)> {
+        let mut stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+
+        let hello = ClientMessage::Hello {
+            version: PROTOCOL_VERSION,
+            name: name.to_string(),
+            spectate,
+            tables: tables_hash(),
+        };
+        write_message(&mut stream, &hello)?;
+
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut id = None;
+        let mut seat = None;
+        let first_update = loop {
+            match read_message(&mut reader)? {
+                Some(ServerMessage::Welcome { id: welcome_id, tables, .. }) => {
+                    if tables != tables_hash() {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, tables_mismatch()));
+                    }
+                    id = Some(welcome_id);
+                },
+                Some(ServerMessage::Seat { seat: new_seat }) => seat = new_seat,
+                Some(ServerMessage::Lobby { lobby }) => break ServerUpdate::Lobby(lobby),
+                Some(ServerMessage::Snapshot { game }) => break ServerUpdate::Game(game),
+                Some(ServerMessage::Rejected { reason }) => {
+                    return Err(io::Error::new(io::ErrorKind::ConnectionRefused, reason));
+                },
+                None => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "server closed the connection")),
+            }
+        };
+        let id = id.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "expected a welcome from the server"))?;
+
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            while let Ok(Some(message)) = read_message(&mut reader) {
+                if sender.send(message).is_err() {
+                    return;
+                }
+            }
+        });
+
+        let client = NetClient {
+            id,
+            seat,
+            stream,
+            messages,
+            connected: true,
+        };
+        Ok((client, first_update))
+    }
+
+    pub fn send(&mut self, message: ClientMessage) {
+        if write_message(&mut self.stream, &message).is_err() {
+            self.connected = false;
+        }
+    }
+
+    // The newest lobby or snapshot received since the last poll, if any
+    pub fn poll(&mut self) -> Option<ServerUpdate> {
+        let mut latest = None;
+        loop {
+            match self.messages.try_recv() {
+                Ok(ServerMessage::Lobby { lobby }) => latest = Some(ServerUpdate::Lobby(lobby)),
+                Ok(ServerMessage::Snapshot { game }) => latest = Some(ServerUpdate::Game(game)),
+                Ok(ServerMessage::Seat { seat }) => self.seat = seat,
+                Ok(ServerMessage::Rejected { reason }) => {
+                    eprintln!("Server closed the connection: {}", reason);
+                    self.connected = false;
+                },
+                Ok(ServerMessage::Welcome { .. }) => {},
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.connected = false;
+                    break;
+                },
+            }
+        }
+        latest
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Polls until the server sends an update that passes `check`
+    fn wait_for(client: &mut NetClient, check: impl Fn(&ServerUpdate) -> bool) -> ServerUpdate {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if let Some(update) = client.poll() {
+                if check(&update) {
+                    return update;
+                }
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        panic!("the server never sent the expected update");
+    }
+
+    fn lobby_of(update: &ServerUpdate) -> Option<&Lobby> {
+        match update {
+            ServerUpdate::Lobby(lobby) => Some(lobby),
+            ServerUpdate::Game(_) => None,
+        }
+    }
+
+    fn game_of(update: &ServerUpdate) -> Option<&Game> {
+        match update {
+            ServerUpdate::Game(game) => Some(game),
+            ServerUpdate::Lobby(_) => None,
+        }
+    }
+
+    // Runs a server on a spare local port for the rest of the test run
+    fn serve() -> SocketAddr {
+        let mut server = Server::bind("127.0.0.1:0", MatchRules::default()).unwrap();
+        let address = server.local_addr();
+        thread::spawn(move || loop {
+            server.step(TICK_INTERVAL);
+            thread::sleep(TICK_INTERVAL);
+        });
+        address
+    }
+
+    #[test]
+    fn lobby_start_and_actions_round_trip_over_loopback() {
+        let address = serve();
+
+        let (mut ada, first) = NetClient::connect(address, "Ada", false).unwrap();
+        assert!(lobby_of(&first).is_some());
+        let (mut grace, _) = NetClient::connect(address, "Grace", false).unwrap();
+
+        // Both show up in the lobby, the first one as host
+        let update = wait_for(&mut ada, |update| lobby_of(update).is_some_and(|lobby| lobby.players.len() == 2));
+        let lobby = lobby_of(&update).unwrap();
+        let names: Vec<&str> = lobby.players.iter().map(|player| player.name.as_str()).collect();
+        assert_eq!(names, ["Ada", "Grace"]);
+        assert_eq!(lobby.host(), Some(ada.id));
+
+        ada.send(ClientMessage::SetReady { ready: true });
+        grace.send(ClientMessage::SetReady { ready: true });
+        wait_for(&mut ada, |update| lobby_of(update).is_some_and(Lobby::can_start));
+        ada.send(ClientMessage::Start);
+
+        // Each gets a seat of their own and the match
+        wait_for(&mut ada, |update| game_of(update).is_some());
+        wait_for(&mut grace, |update| game_of(update).is_some());
+        assert_eq!(ada.seat, Some(0));
+        assert_eq!(grace.seat, Some(1));
+
+        for client in [&mut ada, &mut grace] {
+            client.send(ClientMessage::Action { action: Action::PickPerk { card: 0 } });
+        }
+        wait_for(&mut ada, |update| game_of(update).is_some_and(|game| game.game_state == GameState::Playing));
+
+        // An action goes to the server and comes back in the next snapshots, for everyone
+        ada.send(ClientMessage::Action { action: Action::Strike });
+        wait_for(&mut ada, |update| game_of(update).is_some_and(|game| game.miners[0].strikes == 1));
+        wait_for(&mut grace, |update| game_of(update).is_some_and(|game| game.miners[0].strikes == 1));
+    }
+
+    #[test]
+    fn a_client_with_other_tables_is_turned_away() {
+        let mut stream = TcpStream::connect(serve()).unwrap();
+        let hello = ClientMessage::Hello {
+            version: PROTOCOL_VERSION,
+            name: "Ada".to_string(),
+            spectate: false,
+            tables: "someone else's tables".to_string(),
+        };
+        write_message(&mut stream, &hello).unwrap();
+        match read_message(&mut BufReader::new(stream)).unwrap() {
+            Some(ServerMessage::Rejected { reason }) => assert_eq!(reason, tables_mismatch()),
+            other => panic!("expected a rejection, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn a_line_past_the_limit_is_refused() {
+        let mut line = vec![b' '; MAX_MESSAGE_BYTES as usize];
+        line.push(b'\n');
+        let message: io::Result<Option<ClientMessage>> = read_message(&mut &line[..]);
+        assert_eq!(message.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let message: Option<ClientMessage> = read_message(&mut &b"{\"type\":\"start\"}\n"[..]).unwrap();
+        assert!(matches!(message, Some(ClientMessage::Start)));
+    }
+
+    #[test]
+    fn the_seed_stays_on_the_server() {
+        let server = Server::bind("127.0.0.1:0", MatchRules { seed: Some(9), ..MatchRules::default() }).unwrap();
+        assert_eq!(server.lobby().rules.seed, None);
+    }
+}
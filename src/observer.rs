@@ -0,0 +1,180 @@
+use serde::Serialize;
+use std::io;
+
+use crate::events::RoundEvent;
+use crate::game::{Game, GameState};
+use crate::perks;
+use crate::tech;
+
+// One line of the observer stream: the public state of a match at one moment.
+// Kept separate from the network snapshot so dashboards don't break when the
+// game's internals change.
+#[derive(Debug, Serialize)]
+pub struct Frame<'a> {
+    pub round: usize,
+    pub max_rounds: usize,
+    pub last_standing: bool, // `max_rounds` doesn't apply; the match runs until one miner is left
+    pub turn_based: bool, // `time_left_secs` doesn't apply; the round waits for every miner's orders
+    pub state: GameState,
+    pub time_left_secs: f32,
+    pub event: Option<RoundEvent>, // This round's modifier, if any
+    pub ore_prices: [f32; 4], // Copper, silver, gold, gems
+    pub common_deposit: Option<f32>, // Share of the shared mine left, when the reserves are shared
+    pub miners: Vec<MinerFrame<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MinerFrame<'a> {
+    pub name: &'a str,
+    pub bot: bool,
+    pub alive: bool,
+    pub health: i32,
+    pub max_health: i32,
+    pub shielded: bool,
+    pub total_donated: f32, // Settled rounds only, so it gives away no bid of the round in play
+    pub eliminated_in: Option<usize>,
+    pub turn_done: bool, // Orders committed, in a turn-based match
+    pub tier: usize, // Rough research level, shown even in the fog of war
+    pub perk: Option<&'a str>, // Id of this round's perk card; picks are announced to everyone
+    // Everything from here on is None while it is hidden from rivals
+    pub gold: Option<f32>,
+    pub deposit: Option<f32>, // In the bank
+    pub loan: Option<f32>, // Owed to the bank
+    pub ore: Option<[f32; 4]>, // Unsold whole units, in the same order as `ore_prices`
+    pub reserve: Option<f32>, // Share of the miner's own mine left, when each mine has its own reserve
+    pub donated: Option<f32>, // This round so far
+    pub swings_per_sec: Option<f32>,
+    pub gold_per_swing: Option<f32>,
+    pub techs: Option<Vec<&'a str>>, // Ids of researched tech tree nodes
+}
+
+impl<'a> Frame<'a> {
+    pub fn of(game: &'a Game) -> Frame<'a> {
+        Frame {
+            round: game.current_round,
+            max_rounds: game.rules.max_rounds,
+            last_standing: game.rules.last_standing,
+            turn_based: game.rules.turn_based,
+            state: game.game_state,
+            time_left_secs: game.time_left().as_secs_f32(),
+            event: game.event,
+            ore_prices: game.market.prices,
+            common_deposit: game.common_deposit.map(|deposit| deposit.fraction()),
+            miners: game
+                .miners
+                .iter()
+                .enumerate()
+                .map(|(i, miner)| {
+                    // The stream is public, so it only shows what any rival could see
+                    let view = game.observe(None, i);
+                    MinerFrame {
+                        name: &miner.name,
+                        bot: miner.is_bot(),
+                        alive: miner.alive,
+                        health: miner.health,
+                        max_health: miner.max_health,
+                        shielded: miner.shielded,
+                        total_donated: miner.total_donated,
+                        eliminated_in: miner.eliminated_in,
+                        turn_done: miner.turn_done,
+                        tier: view.tier,
+                        perk: miner.perk.map(|card| perks::deck().cards[card].id.as_str()),
+                        gold: view.gold,
+                        deposit: view.gold.map(|_| miner.deposit),
+                        loan: view.gold.map(|_| miner.loan),
+                        ore: view.ore,
+                        reserve: view.stats.and(miner.reserve).map(|reserve| reserve.fraction()),
+                        donated: view.donation,
+                        swings_per_sec: view.stats.map(|stats| stats.mining_speed),
+                        gold_per_swing: view.stats.map(|stats| stats.gold_per_swing),
+                        techs: view
+                            .stats
+                            .map(|_| miner.techs.iter().map(|&node| tech::tree().nodes[node].id.as_str()).collect()),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+// Publishes frames as JSON lines to every process connected to a Unix socket.
+// Readers only listen; anything they write is ignored.
+#[cfg(unix)]
+pub struct StateStream {
+    path: std::path::PathBuf,
+    subscribers: std::sync::Arc<std::sync::Mutex<Vec<std::os::unix::net::UnixStream>>>,
+}
+
+#[cfg(unix)]
+impl StateStream {
+    pub fn bind(path: &str) -> io::Result<StateStream> {
+        use std::os::unix::net::UnixListener;
+        use std::sync::{Arc, Mutex};
+
+        // A socket file left behind by an earlier run would make the bind fail
+        let _ = std::fs::remove_file(path);
+        let listener = UnixListener::bind(path)?;
+        let subscribers = Arc::new(Mutex::new(Vec::new()));
+
+        let accepted = Arc::clone(&subscribers);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // A reader too slow to keep up is dropped rather than stalling the game
+                if stream.set_nonblocking(true).is_err() {
+                    continue;
+                }
+                if let Ok(mut subscribers) = accepted.lock() {
+                    subscribers.push(stream);
+                }
+            }
+        });
+
+        Ok(StateStream {
+            path: path.into(),
+            subscribers,
+        })
+    }
+
+    pub fn publish(&self, game: &Game) {
+        use std::io::Write;
+
+        let mut subscribers = match self.subscribers.lock() {
+            Ok(subscribers) => subscribers,
+            Err(_) => return,
+        };
+        if subscribers.is_empty() {
+            return;
+        }
+
+        let mut line = match serde_json::to_string(&Frame::of(game)) {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Failed to encode observer frame: {}", e);
+                return;
+            },
+        };
+        line.push('\n');
+
+        // Readers that went away or fell behind are dropped
+        subscribers.retain_mut(|stream| stream.write_all(line.as_bytes()).is_ok());
+    }
+}
+
+#[cfg(unix)]
+impl Drop for StateStream {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(not(unix))]
+pub struct StateStream;
+
+#[cfg(not(unix))]
+impl StateStream {
+    pub fn bind(_path: &str) -> io::Result<StateStream> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "the observer stream needs Unix sockets"))
+    }
+
+    pub fn publish(&self, _game: &Game) {}
+}
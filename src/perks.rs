@@ -0,0 +1,125 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::sync::OnceLock;
+
+pub const PERKS_FILE: &str = "perks.toml";
+pub const DRAFT_HAND: usize = 3; // Cards dealt to each miner
+
+// Shipped cards, used when there is no `perks.toml` next to the executable or it
+// is broken. Like the tech tree, everyone in a network match needs the same deck.
+const BUILTIN_PERKS: &str = include_str!("../perks.toml");
+
+// What a card does for the round it is kept
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PerkEffects {
+    pub speed_bonus: f32, // Share added to mining speed
+    pub yield_bonus: f32, // Share added to ore per swing
+    pub crit_chance: f32,
+    pub donation_bonus: f32,
+    pub damage_reduction: i32,
+    pub hidden_donation: bool,
+    pub gold: f32, // Paid once, when picked
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PerkCard {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub pool: String,
+    #[serde(default)]
+    pub effects: PerkEffects,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PerkDeck {
+    pools: BTreeMap<String, u32>, // Pool name to draw weight; sorted so seeded deals repeat
+    #[serde(rename = "card")]
+    pub cards: Vec<PerkCard>,
+}
+
+impl PerkDeck {
+    fn parse(contents: &str) -> Result<PerkDeck, String> {
+        let deck: PerkDeck = toml::from_str(contents).map_err(|e| e.to_string())?;
+
+        let mut ids = HashSet::new();
+        for card in &deck.cards {
+            if !ids.insert(card.id.as_str()) {
+                return Err(format!("`{}` is defined twice", card.id));
+            }
+            match deck.pools.get(&card.pool) {
+                Some(weight) if *weight > 0 => {},
+                Some(_) => return Err(format!("pool `{}` of `{}` has no weight", card.pool, card.id)),
+                None => return Err(format!("`{}` is in pool `{}`, which isn't listed in [pools]", card.id, card.pool)),
+            }
+        }
+
+        Ok(deck)
+    }
+
+    // Loads the deck from disk, falling back to the shipped one if it is missing or broken
+    fn load() -> PerkDeck {
+        let contents = match fs::read_to_string(PERKS_FILE) {
+            Ok(contents) => contents,
+            Err(_) => return PerkDeck::parse(BUILTIN_PERKS).expect("embedded perk deck is valid"),
+        };
+
+        PerkDeck::parse(&contents).unwrap_or_else(|message| {
+            eprintln!("Ignoring {}: {}", PERKS_FILE, message);
+            PerkDeck::parse(BUILTIN_PERKS).expect("embedded perk deck is valid")
+        })
+    }
+
+    // Up to DRAFT_HAND different cards: a pool is picked by weight among those with
+    // cards left to deal, then a card from it
+    pub fn deal<R: Rng>(&self, rng: &mut R) -> Vec<usize> {
+        let mut hand: Vec<usize> = Vec::new();
+
+        while hand.len() < DRAFT_HAND {
+            let left = |pool: &str| {
+                (0..self.cards.len())
+                    .filter(|i| self.cards[*i].pool == pool && !hand.contains(i))
+                    .collect::<Vec<usize>>()
+            };
+            let pools: Vec<(&String, u32)> = self
+                .pools
+                .iter()
+                .filter(|(pool, _)| !left(pool).is_empty())
+                .map(|(pool, weight)| (pool, *weight))
+                .collect();
+            let total: u32 = pools.iter().map(|(_, weight)| weight).sum();
+            if total == 0 {
+                break; // Fewer cards than a full hand
+            }
+
+            let mut roll = rng.gen_range(0..total);
+            let pool = pools
+                .iter()
+                .find(|(_, weight)| {
+                    if roll < *weight {
+                        true
+                    } else {
+                        roll -= weight;
+                        false
+                    }
+                })
+                .map(|(pool, _)| pool.as_str())
+                .unwrap_or_default();
+            let cards = left(pool);
+            hand.push(cards[rng.gen_range(0..cards.len())]);
+        }
+
+        hand
+    }
+}
+
+// The deck every draft deals from, loaded on first use
+pub fn deck() -> &'static PerkDeck {
+    static DECK: OnceLock<PerkDeck> = OnceLock::new();
+    DECK.get_or_init(PerkDeck::load)
+}
@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+
+use crate::rules::{MatchRules, ReserveMode, SUDDEN_DEATH_ROUND};
+
+// Gold (at base ore prices) a mine holds for every second of play the match is
+// expected to last, so longer matches get deeper mines
+const RESERVE_PER_SECOND: f32 = 3.0;
+// Below this share of the reserve every swing comes up poorer...
+const RICH_SHARE: f32 = 0.5;
+// ...down to this share of a full swing, until the reserve runs dry
+const MIN_RICHNESS: f32 = 0.2;
+
+// Gold left in the ground, in gold at base ore prices. Each mine has one of its own,
+// or every miner draws on one common deposit.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Reserve {
+    pub left: f32,
+    pub size: f32, // What it started with
+}
+
+impl Reserve {
+    pub fn new(size: f32) -> Self {
+        Reserve { left: size, size }
+    }
+
+    // One miner's mine for the match, or the common deposit with room for every miner.
+    // Last standing mines last until sudden death, which settles the match from there on;
+    // sized for the hard round cap instead, they would never run low in a real match.
+    pub fn for_rules(rules: &MatchRules) -> Option<Reserve> {
+        let rounds = if rules.last_standing { SUDDEN_DEATH_ROUND } else { rules.max_rounds };
+        let mine = RESERVE_PER_SECOND * rules.round_seconds as f32 * rounds as f32;
+        match rules.reserves {
+            ReserveMode::Unlimited => None,
+            ReserveMode::Own => Some(Reserve::new(mine)),
+            ReserveMode::Shared => Some(Reserve::new(mine * (rules.human_count + rules.bot_count) as f32)),
+        }
+    }
+
+    // Share still in the ground, from 1 down to 0
+    pub fn fraction(&self) -> f32 {
+        if self.size <= 0.0 {
+            return 0.0;
+        }
+        (self.left / self.size).clamp(0.0, 1.0)
+    }
+
+    // How much of a full swing comes up: all of it while the reserve is rich, then
+    // less and less as it runs low, and nothing once it is empty
+    pub fn richness(&self) -> f32 {
+        if self.left <= 0.0 {
+            return 0.0;
+        }
+        (self.fraction() / RICH_SHARE).clamp(MIN_RICHNESS, 1.0)
+    }
+
+    // Takes a swing worth `value` out of the ground; returns what actually came up
+    pub fn extract(&mut self, value: f32) -> f32 {
+        let mined = (value * self.richness()).min(self.left);
+        self.left -= mined;
+        mined
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_rich_reserve_gives_the_whole_swing() {
+        let mut reserve = Reserve::new(100.0);
+        assert_eq!(reserve.extract(10.0), 10.0);
+        assert_eq!(reserve.left, 90.0);
+    }
+
+    #[test]
+    fn a_poor_reserve_gives_less() {
+        let mut reserve = Reserve { left: 25.0, size: 100.0 };
+        assert_eq!(reserve.extract(10.0), 5.0);
+        let reserve = Reserve { left: 1.0, size: 100.0 };
+        assert_eq!(reserve.richness(), MIN_RICHNESS);
+    }
+
+    #[test]
+    fn nothing_comes_out_past_the_bottom() {
+        let mut reserve = Reserve { left: 3.0, size: 10.0 };
+        assert_eq!(reserve.extract(100.0), 3.0);
+        assert_eq!(reserve.left, 0.0);
+        assert_eq!(reserve.extract(100.0), 0.0);
+        assert_eq!(reserve.fraction(), 0.0);
+    }
+
+    #[test]
+    fn unlimited_rules_have_no_reserve_and_shared_ones_have_room_for_everyone() {
+        let mut rules = MatchRules { reserves: ReserveMode::Unlimited, ..MatchRules::default() };
+        assert!(Reserve::for_rules(&rules).is_none());
+        rules.reserves = ReserveMode::Own;
+        let own = Reserve::for_rules(&rules).unwrap().size;
+        rules.reserves = ReserveMode::Shared;
+        let shared = Reserve::for_rules(&rules).unwrap().size;
+        assert_eq!(shared, own * (rules.human_count + rules.bot_count) as f32);
+    }
+
+    #[test]
+    fn last_standing_mines_last_until_sudden_death() {
+        let rules = MatchRules { last_standing: true, max_rounds: 5, ..MatchRules::default() };
+        let size = Reserve::for_rules(&rules).unwrap().size;
+        assert_eq!(size, RESERVE_PER_SECOND * rules.round_seconds as f32 * SUDDEN_DEATH_ROUND as f32);
+    }
+}
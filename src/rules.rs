@@ -0,0 +1,380 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// Match configuration chosen before a game starts
+pub const MAX_HUMANS: usize = 4;
+pub const MAX_BOTS: usize = 7;
+pub const MIN_ROUNDS: usize = 1;
+pub const MAX_ROUNDS: usize = 30;
+pub const MIN_ROUND_SECONDS: u64 = 15;
+pub const MAX_ROUND_SECONDS: u64 = 180;
+pub const SUDDEN_DEATH_ROUND: usize = 20; // From here on a last standing match hurts more every round...
+pub const LAST_STANDING_MAX_ROUNDS: usize = 50; // ...and it is called after this one whatever happens
+const ROUND_SECONDS_STEP: u64 = 15; // Lobby buttons change the round length in these steps
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BotDifficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl BotDifficulty {
+    pub const ALL: [BotDifficulty; 3] = [BotDifficulty::Easy, BotDifficulty::Normal, BotDifficulty::Hard];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BotDifficulty::Easy => "Easy",
+            BotDifficulty::Normal => "Normal",
+            BotDifficulty::Hard => "Hard",
+        }
+    }
+
+    fn parse(name: &str) -> Option<BotDifficulty> {
+        BotDifficulty::ALL.into_iter().find(|difficulty| difficulty.name().eq_ignore_ascii_case(name))
+    }
+}
+
+// How a round's donations are paid for and who gets to see them. Every format
+// ranks the miners by what they donated; only the bill and the visibility change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuctionFormat {
+    AllPay, // Every donation is spent, in the open
+    Sealed, // Every donation is spent, but rivals only see them when the round ends
+    SecondPrice, // The top donor pays the runner-up's amount; everyone else gets their gold back
+    RefundLosers, // The top donor pays in full; everyone else gets their gold back
+}
+
+impl AuctionFormat {
+    pub const ALL: [AuctionFormat; 4] = [
+        AuctionFormat::AllPay,
+        AuctionFormat::Sealed,
+        AuctionFormat::SecondPrice,
+        AuctionFormat::RefundLosers,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AuctionFormat::AllPay => "All-pay",
+            AuctionFormat::Sealed => "Sealed",
+            AuctionFormat::SecondPrice => "Second price",
+            AuctionFormat::RefundLosers => "Refund losers",
+        }
+    }
+
+    // Spelling on the command line
+    fn flag_value(self) -> &'static str {
+        match self {
+            AuctionFormat::AllPay => "all-pay",
+            AuctionFormat::Sealed => "sealed",
+            AuctionFormat::SecondPrice => "second-price",
+            AuctionFormat::RefundLosers => "refund-losers",
+        }
+    }
+
+    // Shown on the Donate panel
+    pub fn explanation(self) -> &'static str {
+        match self {
+            AuctionFormat::AllPay => "All-pay: every donation is spent, and everyone sees it straight away.",
+            AuctionFormat::Sealed => "Sealed bids: every donation is spent but stays hidden until the round ends.",
+            AuctionFormat::SecondPrice => "Second price: the top donor pays the runner-up's amount; all else is refunded.",
+            AuctionFormat::RefundLosers => "Refund losers: the top donor pays in full; everyone else is refunded.",
+        }
+    }
+
+    // Rivals can't see donations before the round ends
+    pub fn is_sealed(self) -> bool {
+        self == AuctionFormat::Sealed
+    }
+
+    fn parse(value: &str) -> Option<AuctionFormat> {
+        AuctionFormat::ALL.into_iter().find(|format| format.flag_value().eq_ignore_ascii_case(value))
+    }
+}
+
+// How much gold the ground holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReserveMode {
+    Unlimited, // Mines never run dry
+    Own, // Every mine has a reserve of its own that runs out as it is mined
+    Shared, // Everyone mines one common deposit, so whatever a rival takes is gone for all
+}
+
+impl ReserveMode {
+    pub const ALL: [ReserveMode; 3] = [ReserveMode::Unlimited, ReserveMode::Own, ReserveMode::Shared];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ReserveMode::Unlimited => "Unlimited",
+            ReserveMode::Own => "Own mine",
+            ReserveMode::Shared => "Shared deposit",
+        }
+    }
+
+    // Spelling on the command line
+    fn flag_value(self) -> &'static str {
+        match self {
+            ReserveMode::Unlimited => "unlimited",
+            ReserveMode::Own => "own",
+            ReserveMode::Shared => "shared",
+        }
+    }
+
+    fn parse(value: &str) -> Option<ReserveMode> {
+        ReserveMode::ALL.into_iter().find(|mode| mode.flag_value().eq_ignore_ascii_case(value))
+    }
+}
+
+// Settings the lobby host can change, in the order the lobby lists them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RuleField {
+    Rounds,
+    RoundLength,
+    Bots,
+    Difficulty,
+    Mode,
+    Pace,
+    Auction,
+    Fog,
+    Reserves,
+}
+
+impl RuleField {
+    pub const ALL: [RuleField; 9] = [
+        RuleField::Mode,
+        RuleField::Pace,
+        RuleField::Rounds,
+        RuleField::RoundLength,
+        RuleField::Bots,
+        RuleField::Difficulty,
+        RuleField::Auction,
+        RuleField::Fog,
+        RuleField::Reserves,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RuleField::Rounds => "Rounds",
+            RuleField::RoundLength => "Round length",
+            RuleField::Bots => "Bots",
+            RuleField::Difficulty => "Bot difficulty",
+            RuleField::Mode => "Mode",
+            RuleField::Pace => "Pace",
+            RuleField::Auction => "Auction",
+            RuleField::Fog => "Fog of war",
+            RuleField::Reserves => "Reserves",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchRules {
+    pub human_count: usize, // Hot-seat players sharing this machine, or players in a networked match
+    pub bot_count: usize,
+    pub max_rounds: usize,
+    pub round_seconds: u64,
+    pub bot_difficulty: BotDifficulty,
+    pub last_standing: bool, // Play on until one miner is left instead of stopping after `max_rounds`
+    pub seed: Option<u64>, // Fixes the round events; a random seed is picked per match otherwise
+    pub auction: AuctionFormat,
+    pub fog_of_war: bool, // Rivals' gold, donations and stats are hidden unless scouted
+    pub turn_based: bool, // Rounds are mined at once, then everyone commits their orders without a clock
+    pub reserves: ReserveMode,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        MatchRules {
+            human_count: 1,
+            bot_count: 3,
+            max_rounds: 15,
+            round_seconds: 60, // 1 minute
+            bot_difficulty: BotDifficulty::Normal,
+            last_standing: false,
+            seed: None,
+            auction: AuctionFormat::AllPay,
+            fog_of_war: false,
+            turn_based: false,
+            reserves: ReserveMode::Own,
+        }
+    }
+}
+
+impl MatchRules {
+    // Reads `--players N`, `--bots N`, `--rounds N`, `--round-length SECONDS`,
+    // `--difficulty easy|normal|hard`, `--last-standing`, `--seed N`,
+    // `--auction all-pay|sealed|second-price|refund-losers`, `--fog-of-war`, `--turn-based`
+    // and `--reserves unlimited|own|shared` from the command line
+    pub fn from_args<I>(args: I) -> Result<MatchRules, String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut rules = MatchRules::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--players" => rules.human_count = parse_count(&arg, args.next(), 1, MAX_HUMANS)?,
+                "--bots" => rules.bot_count = parse_count(&arg, args.next(), 0, MAX_BOTS)?,
+                "--rounds" => rules.max_rounds = parse_count(&arg, args.next(), MIN_ROUNDS, MAX_ROUNDS)?,
+                "--round-length" => {
+                    let seconds = parse_count(&arg, args.next(), MIN_ROUND_SECONDS as usize, MAX_ROUND_SECONDS as usize)?;
+                    rules.round_seconds = seconds as u64;
+                },
+                "--difficulty" => {
+                    let value = args.next().ok_or_else(|| format!("`{}` needs a value", arg))?;
+                    rules.bot_difficulty = BotDifficulty::parse(&value)
+                        .ok_or_else(|| format!("`{}` must be easy, normal or hard, got `{}`", arg, value))?;
+                },
+                "--last-standing" => rules.last_standing = true,
+                "--fog-of-war" => rules.fog_of_war = true,
+                "--turn-based" => rules.turn_based = true,
+                "--seed" => {
+                    let value = args.next().ok_or_else(|| format!("`{}` needs a value", arg))?;
+                    let seed = value.parse::<u64>().map_err(|_| format!("`{}` must be a whole number, got `{}`", arg, value))?;
+                    rules.seed = Some(seed);
+                },
+                "--auction" => {
+                    let value = args.next().ok_or_else(|| format!("`{}` needs a value", arg))?;
+                    rules.auction = AuctionFormat::parse(&value).ok_or_else(|| {
+                        format!("`{}` must be all-pay, sealed, second-price or refund-losers, got `{}`", arg, value)
+                    })?;
+                },
+                "--reserves" => {
+                    let value = args.next().ok_or_else(|| format!("`{}` needs a value", arg))?;
+                    rules.reserves = ReserveMode::parse(&value)
+                        .ok_or_else(|| format!("`{}` must be unlimited, own or shared, got `{}`", arg, value))?;
+                },
+                _ => return Err(format!("unknown argument `{}`", arg)),
+            }
+        }
+
+        Ok(rules)
+    }
+
+    pub fn is_hot_seat(&self) -> bool {
+        self.human_count > 1
+    }
+
+    pub fn round_duration(&self) -> Duration {
+        Duration::from_secs(self.round_seconds)
+    }
+
+    // Value shown next to a setting in the lobby
+    pub fn describe(&self, field: RuleField) -> String {
+        match field {
+            RuleField::Rounds if self.last_standing => "No limit".to_string(),
+            RuleField::Rounds => self.max_rounds.to_string(),
+            RuleField::RoundLength => format!("{}s", self.round_seconds),
+            RuleField::Bots => self.bot_count.to_string(),
+            RuleField::Difficulty => self.bot_difficulty.name().to_string(),
+            RuleField::Mode if self.last_standing => "Last standing".to_string(),
+            RuleField::Mode => "Fixed rounds".to_string(),
+            RuleField::Pace if self.turn_based => "Turn-based".to_string(),
+            RuleField::Pace => "Real time".to_string(),
+            RuleField::Auction => self.auction.name().to_string(),
+            RuleField::Fog if self.fog_of_war => "On".to_string(),
+            RuleField::Fog => "Off".to_string(),
+            RuleField::Reserves => self.reserves.name().to_string(),
+        }
+    }
+
+    // Steps a setting up (or down) by one notch, staying within its limits
+    pub fn adjust(&mut self, field: RuleField, up: bool) {
+        let step = |value: usize, min: usize, max: usize, by: usize| {
+            if up {
+                (value + by).min(max)
+            } else {
+                value.saturating_sub(by).max(min)
+            }
+        };
+
+        match field {
+            RuleField::Rounds => self.max_rounds = step(self.max_rounds, MIN_ROUNDS, MAX_ROUNDS, 1),
+            RuleField::RoundLength => {
+                let seconds = step(
+                    self.round_seconds as usize,
+                    MIN_ROUND_SECONDS as usize,
+                    MAX_ROUND_SECONDS as usize,
+                    ROUND_SECONDS_STEP as usize,
+                );
+                self.round_seconds = seconds as u64;
+            },
+            RuleField::Bots => self.bot_count = step(self.bot_count, 0, MAX_BOTS, 1),
+            RuleField::Difficulty => {
+                let index = BotDifficulty::ALL.iter().position(|d| *d == self.bot_difficulty).unwrap_or(1);
+                self.bot_difficulty = BotDifficulty::ALL[step(index, 0, BotDifficulty::ALL.len() - 1, 1)];
+            },
+            // Only two modes, so either button switches
+            RuleField::Mode => self.last_standing = !self.last_standing,
+            RuleField::Pace => self.turn_based = !self.turn_based,
+            RuleField::Fog => self.fog_of_war = !self.fog_of_war,
+            RuleField::Auction => {
+                let index = AuctionFormat::ALL.iter().position(|f| *f == self.auction).unwrap_or(0);
+                self.auction = AuctionFormat::ALL[step(index, 0, AuctionFormat::ALL.len() - 1, 1)];
+            },
+            RuleField::Reserves => {
+                let index = ReserveMode::ALL.iter().position(|m| *m == self.reserves).unwrap_or(1);
+                self.reserves = ReserveMode::ALL[step(index, 0, ReserveMode::ALL.len() - 1, 1)];
+            },
+        }
+    }
+
+    // Keeps rules received from another player within the limits the command line enforces
+    pub fn clamped(mut self) -> MatchRules {
+        self.human_count = self.human_count.clamp(1, MAX_HUMANS);
+        self.bot_count = self.bot_count.min(MAX_BOTS);
+        self.max_rounds = self.max_rounds.clamp(MIN_ROUNDS, MAX_ROUNDS);
+        self.round_seconds = self.round_seconds.clamp(MIN_ROUND_SECONDS, MAX_ROUND_SECONDS);
+        self
+    }
+}
+
+fn parse_count(flag: &str, value: Option<String>, min: usize, max: usize) -> Result<usize, String> {
+    let value = value.ok_or_else(|| format!("`{}` needs a value", flag))?;
+    match value.parse::<usize>() {
+        Ok(count) if (min..=max).contains(&count) => Ok(count),
+        _ => Err(format!("`{}` must be between {} and {}, got `{}`", flag, min, max, value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<MatchRules, String> {
+        MatchRules::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn reads_every_flag() {
+        let rules = parse(&[
+            "--players", "2", "--bots", "5", "--rounds", "8", "--round-length", "30", "--difficulty", "hard",
+            "--last-standing", "--fog-of-war", "--turn-based", "--seed", "42", "--auction", "second-price",
+            "--reserves", "shared",
+        ])
+        .unwrap();
+        assert_eq!((rules.human_count, rules.bot_count, rules.max_rounds, rules.round_seconds), (2, 5, 8, 30));
+        assert_eq!(rules.bot_difficulty, BotDifficulty::Hard);
+        assert!(rules.last_standing && rules.fog_of_war && rules.turn_based);
+        assert_eq!(rules.seed, Some(42));
+        assert_eq!(rules.auction, AuctionFormat::SecondPrice);
+        assert_eq!(rules.reserves, ReserveMode::Shared);
+    }
+
+    #[test]
+    fn no_flags_gives_the_defaults() {
+        assert_eq!(parse(&[]).unwrap().auction, AuctionFormat::AllPay);
+    }
+
+    #[test]
+    fn refuses_bad_values() {
+        assert!(parse(&["--auction", "dutch"]).is_err());
+        assert!(parse(&["--players", "0"]).is_err());
+        assert!(parse(&["--bots", "8"]).is_err());
+        assert!(parse(&["--round-length", "10"]).is_err());
+        assert!(parse(&["--seed", "-1"]).is_err());
+        assert!(parse(&["--rounds"]).is_err());
+        assert!(parse(&["--turbo"]).is_err());
+    }
+}
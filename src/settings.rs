@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+pub const SETTINGS_FILE: &str = "settings.toml";
+
+// Player preferences that survive restarts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub theme: String, // Id of a theme in BUILTIN_THEMES
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            theme: "light".to_string(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        match fs::read_to_string(SETTINGS_FILE) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|e| {
+                eprintln!("Ignoring {}: {}", SETTINGS_FILE, e);
+                Settings::default()
+            }),
+            Err(_) => Settings::default(),
+        }
+    }
+
+    pub fn save(&self) {
+        let result = toml::to_string(self)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| fs::write(SETTINGS_FILE, contents).map_err(|e| e.to_string()));
+
+        if let Err(message) = result {
+            eprintln!("Failed to save {}: {}", SETTINGS_FILE, message);
+        }
+    }
+}
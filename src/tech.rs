@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::OnceLock;
+
+pub const TECH_TREE_FILE: &str = "tech_tree.toml";
+
+// Shipped tree. A `tech_tree.toml` next to the executable takes precedence so the
+// tree can be rebalanced without a rebuild. Every player in a networked match
+// needs the same tree, since research is sent as node numbers.
+const BUILTIN_TECH_TREE: &str = include_str!("../tech_tree.toml");
+
+// What a miner starts with before any research
+pub const BASE_STATS: Stats = Stats {
+    mining_speed: 1.0,
+    gold_per_swing: 2.0,
+    crit_chance: 0.0,
+    interest: 0.0,
+    donation_bonus: 0.0,
+    damage_reduction: 0,
+};
+
+// Columns of the tech tree screen, left to right
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Branch {
+    Speed,
+    Yield,
+    Crit,
+    Interest,
+    Donation,
+    Defense,
+}
+
+impl Branch {
+    pub const ALL: [Branch; 6] = [
+        Branch::Speed,
+        Branch::Yield,
+        Branch::Crit,
+        Branch::Interest,
+        Branch::Donation,
+        Branch::Defense,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Branch::Speed => "Speed",
+            Branch::Yield => "Yield",
+            Branch::Crit => "Crits",
+            Branch::Interest => "Interest",
+            Branch::Donation => "Donations",
+            Branch::Defense => "Defense",
+        }
+    }
+}
+
+// A miner's effective numbers, and also what a single node adds to them
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Stats {
+    pub mining_speed: f32, // Swings per second
+    pub gold_per_swing: f32,
+    pub crit_chance: f32, // A critical swing mines several times over, see `yields.toml`
+    pub interest: f32, // Share of unspent gold paid at the end of a round
+    pub donation_bonus: f32, // Extra credit on every donation
+    pub damage_reduction: i32, // Taken off each round's damage
+}
+
+impl Stats {
+    pub fn add(&mut self, other: &Stats) {
+        self.mining_speed += other.mining_speed;
+        self.gold_per_swing += other.gold_per_swing;
+        self.crit_chance += other.crit_chance;
+        self.interest += other.interest;
+        self.donation_bonus += other.donation_bonus;
+        self.damage_reduction += other.damage_reduction;
+    }
+
+    // Short description of the non-zero stats, e.g. "+0.3 swings/s, +5% crits"
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.mining_speed != 0.0 {
+            parts.push(format!("+{:.2} swings/s", self.mining_speed));
+        }
+        if self.gold_per_swing != 0.0 {
+            parts.push(format!("+{:.0}g per swing", self.gold_per_swing));
+        }
+        if self.crit_chance != 0.0 {
+            parts.push(format!("+{:.0}% crits", self.crit_chance * 100.0));
+        }
+        if self.interest != 0.0 {
+            parts.push(format!("+{:.0}% interest", self.interest * 100.0));
+        }
+        if self.donation_bonus != 0.0 {
+            parts.push(format!("+{:.0}% donation credit", self.donation_bonus * 100.0));
+        }
+        if self.damage_reduction != 0 {
+            parts.push(format!("-{} damage per round", self.damage_reduction));
+        }
+        parts.join(", ")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TechNode {
+    pub id: String,
+    pub name: String,
+    pub branch: Branch,
+    pub cost: f32,
+    #[serde(default)]
+    requires: Vec<String>,
+    #[serde(default)]
+    pub effects: Stats,
+    #[serde(skip)]
+    pub prerequisites: Vec<usize>, // Indices of the `requires` nodes, filled in on load
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TechTree {
+    #[serde(rename = "node")]
+    pub nodes: Vec<TechNode>,
+}
+
+impl TechTree {
+    fn parse(contents: &str) -> Result<TechTree, String> {
+        let mut tree: TechTree = toml::from_str(contents).map_err(|e| e.to_string())?;
+
+        // Requirements must point back up the file, which also rules out cycles
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        for (index, node) in tree.nodes.iter_mut().enumerate() {
+            if node.cost < 0.0 {
+                return Err(format!("`{}` has a negative cost", node.id));
+            }
+            for required in &node.requires {
+                let prerequisite = seen
+                    .get(required)
+                    .ok_or_else(|| format!("`{}` requires `{}`, which isn't defined above it", node.id, required))?;
+                node.prerequisites.push(*prerequisite);
+            }
+            if seen.insert(node.id.clone(), index).is_some() {
+                return Err(format!("`{}` is defined twice", node.id));
+            }
+        }
+
+        Ok(tree)
+    }
+
+    // Loads the tree from disk, falling back to the shipped one if it is missing or broken
+    fn load() -> TechTree {
+        let contents = match fs::read_to_string(TECH_TREE_FILE) {
+            Ok(contents) => contents,
+            Err(_) => return TechTree::parse(BUILTIN_TECH_TREE).expect("embedded tech tree is valid"),
+        };
+
+        TechTree::parse(&contents).unwrap_or_else(|message| {
+            eprintln!("Ignoring {}: {}", TECH_TREE_FILE, message);
+            TechTree::parse(BUILTIN_TECH_TREE).expect("embedded tech tree is valid")
+        })
+    }
+
+    // Nodes of one branch in file order, as (index, node)
+    pub fn branch(&self, branch: Branch) -> impl Iterator<Item = (usize, &TechNode)> {
+        self.nodes.iter().enumerate().filter(move |(_, node)| node.branch == branch)
+    }
+}
+
+// The tree every miner researches from, loaded on first use
+pub fn tree() -> &'static TechTree {
+    static TREE: OnceLock<TechTree> = OnceLock::new();
+    TREE.get_or_init(TechTree::load)
+}
@@ -0,0 +1,150 @@
+use ggez::graphics::Color;
+use serde::{Deserialize, Deserializer};
+use std::fs;
+
+pub const THEMES_DIR: &str = "themes";
+
+// Shipped themes, in the order the settings button cycles through them. The
+// files in `themes/` take precedence so they can be tweaked without a rebuild;
+// the embedded copies keep the game working when the folder is missing.
+pub const BUILTIN_THEMES: [(&str, &str); 5] = [
+    ("light", include_str!("../themes/light.toml")),
+    ("dark", include_str!("../themes/dark.toml")),
+    ("high_contrast", include_str!("../themes/high_contrast.toml")),
+    ("deuteranopia", include_str!("../themes/deuteranopia.toml")),
+    ("protanopia", include_str!("../themes/protanopia.toml")),
+];
+
+// Every colour the UI draws with. Colours are written as "#RRGGBB" or "#RRGGBBAA".
+#[derive(Debug, Clone, Deserialize)]
+pub struct Theme {
+    pub name: String, // Display name shown in settings
+
+    #[serde(deserialize_with = "hex_color")]
+    pub background: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub panel: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub shadow: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub highlight: Color, // Sheen on panels and buttons
+    #[serde(deserialize_with = "hex_color")]
+    pub separator: Color,
+
+    #[serde(deserialize_with = "hex_color")]
+    pub primary: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub secondary: Color, // Losses, damage and low health
+    #[serde(deserialize_with = "hex_color")]
+    pub accent: Color, // Wins, affordable actions and high health
+    #[serde(deserialize_with = "hex_color")]
+    pub warning: Color, // Medium health
+    #[serde(deserialize_with = "hex_color")]
+    pub disabled: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub gold: Color,
+
+    #[serde(deserialize_with = "hex_color")]
+    pub text: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub text_on_light: Color, // Text on bright buttons
+    #[serde(deserialize_with = "hex_color")]
+    pub text_on_dark: Color, // Text on dark buttons and header bars
+    #[serde(deserialize_with = "hex_color")]
+    pub field: Color, // Text input background
+
+    #[serde(deserialize_with = "hex_color")]
+    pub row_even: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub row_odd: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub win_row: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub loss_row: Color,
+
+    #[serde(deserialize_with = "hex_color")]
+    pub medal_gold: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub medal_silver: Color,
+    #[serde(deserialize_with = "hex_color")]
+    pub medal_bronze: Color,
+
+    #[serde(deserialize_with = "hex_color")]
+    pub wood: Color, // Pickaxe icon
+    #[serde(deserialize_with = "hex_color")]
+    pub stone: Color, // Mine icon
+}
+
+impl Theme {
+    // Loads a shipped theme by id, preferring `themes/<id>.toml` on disk
+    pub fn load(id: &str) -> Result<Theme, String> {
+        let path = format!("{}/{}.toml", THEMES_DIR, id);
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(_) => BUILTIN_THEMES
+                .iter()
+                .find(|(builtin_id, _)| *builtin_id == id)
+                .map(|(_, contents)| contents.to_string())
+                .ok_or_else(|| format!("unknown theme `{}`", id))?,
+        };
+
+        toml::from_str(&contents).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    // Falls back to the embedded light theme if the requested one can't be used
+    pub fn load_or_default(id: &str) -> Theme {
+        Theme::load(id).unwrap_or_else(|message| {
+            eprintln!("Using the light theme: {}", message);
+            toml::from_str(BUILTIN_THEMES[0].1).expect("embedded light theme is valid")
+        })
+    }
+
+    // Dark text on bright colours, light text on dark ones (judged by perceived brightness)
+    pub fn text_on(&self, background: Color) -> Color {
+        let luminance = 0.299 * background.r + 0.587 * background.g + 0.114 * background.b;
+        if luminance > 0.6 {
+            self.text_on_light
+        } else {
+            self.text_on_dark
+        }
+    }
+
+    // Health colour shared by every health readout
+    pub fn health_color(&self, health: i32) -> Color {
+        if health <= 3 {
+            self.secondary
+        } else if health <= 6 {
+            self.warning
+        } else {
+            self.accent
+        }
+    }
+}
+
+// Id of the theme after `id` in BUILTIN_THEMES, wrapping around
+pub fn next_theme_id(id: &str) -> &'static str {
+    let index = BUILTIN_THEMES.iter().position(|(builtin_id, _)| *builtin_id == id);
+    let next = index.map_or(0, |i| (i + 1) % BUILTIN_THEMES.len());
+    BUILTIN_THEMES[next].0
+}
+
+fn hex_color<'de, D>(deserializer: D) -> Result<Color, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let hex = String::deserialize(deserializer)?;
+    parse_hex_color(&hex).ok_or_else(|| serde::de::Error::custom(format!("invalid colour `{}`", hex)))
+}
+
+fn parse_hex_color(hex: &str) -> Option<Color> {
+    let digits = hex.strip_prefix('#')?;
+    if !digits.is_ascii() || (digits.len() != 6 && digits.len() != 8) {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).ok();
+    let alpha = if digits.len() == 8 { channel(6)? } else { 255 };
+
+    Some(Color::from_rgba(channel(0)?, channel(2)?, channel(4)?, alpha))
+}
@@ -1,24 +1,427 @@
 use ggez::{Context, GameResult};
 use ggez::graphics::{self, Color, DrawParam, Text, DrawMode, Rect, MeshBuilder};
 use ggez::graphics::TextFragment;
-use std::time::Instant;
 
-use crate::game_state::{MainState, ROUND_DURATION, WINDOW_WIDTH, WINDOW_HEIGHT, MAX_ROUNDS};
+use crate::input::key_label;
+use crate::items::Item;
+use crate::market::Ore;
+use crate::miner::{MinerType, COMBO_WINDOW, CRIT_FLASH, DEPOSIT_INTEREST, HEAL_AMOUNT, LOAN_INTEREST, MAX_HEALTH_STEP, MAX_HEALTH_UPGRADES, PLAYER_COLOR_COUNT};
+use crate::perks::{self, DRAFT_HAND};
+use crate::rules::{RuleField, MAX_HUMANS};
+use crate::tech::{self, Branch};
+use crate::theme::Theme;
+use crate::yields;
+use crate::game::{GameState, DRAFT_DURATION, ELIMINATED_SPEEDUP};
+use crate::game_state::{MainState, BANK_STEP, CONTRIBUTION_AMOUNTS, DONATION_PERCENTAGES};
 
-// Modern color palette
-const COLOR_BACKGROUND: Color = Color::new(0.95, 0.97, 1.0, 1.0);  // Light blue-gray
-const COLOR_PRIMARY: Color = Color::new(0.2, 0.4, 0.8, 1.0);       // Royal blue
-const COLOR_SECONDARY: Color = Color::new(0.9, 0.4, 0.3, 1.0);     // Coral
-const COLOR_ACCENT: Color = Color::new(0.3, 0.7, 0.4, 1.0);        // Forest green
-const COLOR_DISABLED: Color = Color::new(0.7, 0.7, 0.75, 1.0);     // Slate gray
-const COLOR_TEXT: Color = Color::new(0.2, 0.2, 0.25, 1.0);         // Dark slate
-const COLOR_TEXT_LIGHT: Color = Color::new(1.0, 1.0, 1.0, 1.0);    // White
-const COLOR_PANEL: Color = Color::new(1.0, 1.0, 1.0, 0.9);         // Slightly transparent white
-const COLOR_GOLD: Color = Color::new(0.85, 0.65, 0.2, 1.0);        // Gold
+// Size the UI is designed for. Larger windows scale everything up uniformly and
+// give the spare room on the longer axis to the panels that stretch.
+pub const DESIGN_WIDTH: f32 = 800.0;
+pub const DESIGN_HEIGHT: f32 = 600.0;
+pub const MIN_WINDOW_WIDTH: f32 = 640.0;
+pub const MIN_WINDOW_HEIGHT: f32 = 480.0;
+const FINAL_STANDINGS_ROW_HEIGHT: f32 = 26.0;
+const ACTIVITY_LOG_ROWS: usize = 3;
+const EVENT_BANNER_SECONDS: f32 = 4.0; // How long a round event is announced across the screen
+const TECH_NODE_WIDTH: f32 = 116.0;
+const TECH_NODE_HEIGHT: f32 = 56.0;
+const PERK_CARD_WIDTH: f32 = 220.0;
+const PERK_CARD_HEIGHT: f32 = 200.0;
+
+// Branches with a one-click "research the next node" button in the Upgrades panel
+pub const QUICK_RESEARCH: [Branch; 2] = [Branch::Speed, Branch::Yield];
+
+// Colours players pick in the lobby. They identify people rather than decorate,
+// so they stay the same in every theme.
+pub const PLAYER_COLORS: [Color; PLAYER_COLOR_COUNT] = [
+    Color::new(0.20, 0.45, 0.85, 1.0), // Blue
+    Color::new(0.90, 0.55, 0.10, 1.0), // Orange
+    Color::new(0.20, 0.65, 0.35, 1.0), // Green
+    Color::new(0.80, 0.25, 0.60, 1.0), // Magenta
+    Color::new(0.10, 0.70, 0.75, 1.0), // Teal
+    Color::new(0.85, 0.75, 0.15, 1.0), // Yellow
+    Color::new(0.55, 0.35, 0.80, 1.0), // Purple
+    Color::new(0.60, 0.40, 0.25, 1.0), // Brown
+];
+
+// Logical drawing area for the current window. All layout code works in logical
+// units; `scale` converts to physical pixels, which also takes care of high-DPI.
+#[derive(Debug, Clone, Copy)]
+pub struct Layout {
+    pub width: f32,
+    pub height: f32,
+    pub scale: f32,
+}
+
+impl Layout {
+    pub fn for_window(pixel_width: f32, pixel_height: f32) -> Self {
+        let scale = (pixel_width / DESIGN_WIDTH).min(pixel_height / DESIGN_HEIGHT).max(0.1);
+
+        Layout {
+            width: pixel_width / scale,
+            height: pixel_height / scale,
+            scale,
+        }
+    }
+
+    pub fn screen_rect(&self) -> Rect {
+        Rect::new(0.0, 0.0, self.width, self.height)
+    }
+
+    // Window (physical pixel) position to layout position
+    pub fn logical_position(&self, x: f32, y: f32) -> (f32, f32) {
+        (x / self.scale, y / self.scale)
+    }
+}
+
+// Every clickable element. Drawing and hit-testing both go through `button_rect`
+// so the area that reacts to the mouse is always the area that is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ButtonId {
+    ResearchNext(Branch), // Next node down one of the QUICK_RESEARCH branches
+    TechTree, // Opens and closes the tech tree screen
+    TechNode(usize), // Index into the tech tree
+    BuyHeal,
+    BuyShield,
+    UpgradeMaxHealth,
+    CycleTarget, // Rival the items below act on
+    UseItem(Item),
+    SellOre(Ore),
+    SellAll,
+    PickPerk(usize), // Position in the active miner's perk offer
+    Donate(usize), // Index into CONTRIBUTION_AMOUNTS
+    DonatePercent(usize), // Index into DONATION_PERCENTAGES
+    DonateAll,
+    DonationField,
+    DonationSlider,
+    DonateCustom,
+    Strike, // The rock to strike by hand
+    Deposit, // Bank buttons move BANK_STEP at a time
+    Withdraw,
+    Borrow,
+    RepayLoan,
+    EndTurn, // Commits the orders of a turn-based turn
+    Continue,
+    SkipToResults, // Eliminated players can jump straight to the final standings
+    Restart,
+    CycleTheme,
+    SelectSeat(usize), // Hot-seat player whose panels to show
+    Lobby(LobbyButton),
+}
+
+// Controls on the networked lobby screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LobbyButton {
+    Name, // Text field for the player's name
+    Color,
+    Ready,
+    Start, // Host only
+    RuleDown(RuleField), // Host only
+    RuleUp(RuleField), // Host only
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ButtonState {
+    Normal,
+    Hovered,
+    Pressed,
+    Disabled,
+}
+
+// Buttons that can be interacted with in the current game state
+pub fn visible_buttons(state: &MainState) -> Vec<ButtonId> {
+    if state.lobby.is_some() {
+        let mut buttons = Vec::new();
+        if state.lobby_player().is_some() {
+            buttons.extend([LobbyButton::Name, LobbyButton::Color, LobbyButton::Ready].map(ButtonId::Lobby));
+        }
+        if state.is_lobby_host() {
+            for field in RuleField::ALL {
+                buttons.push(ButtonId::Lobby(LobbyButton::RuleDown(field)));
+                buttons.push(ButtonId::Lobby(LobbyButton::RuleUp(field)));
+            }
+            buttons.push(ButtonId::Lobby(LobbyButton::Start));
+        }
+        buttons.push(ButtonId::CycleTheme);
+        return buttons;
+    }
+
+    let mut buttons = match state.game.game_state {
+        GameState::Draft | GameState::Playing if state.is_observer() || state.game.all_humans_out() => Vec::new(),
+        GameState::Draft => (0..state.active_miner().perk_offer.len()).map(ButtonId::PickPerk).collect(),
+        GameState::Playing if state.show_tech_tree => {
+            let mut buttons: Vec<ButtonId> = (0..tech::tree().nodes.len()).map(ButtonId::TechNode).collect();
+            buttons.push(ButtonId::TechTree);
+            buttons
+        },
+        GameState::Playing => {
+            let mut buttons: Vec<ButtonId> = QUICK_RESEARCH.map(ButtonId::ResearchNext).to_vec();
+            buttons.extend([
+                ButtonId::TechTree,
+                ButtonId::BuyHeal,
+                ButtonId::BuyShield,
+                ButtonId::UpgradeMaxHealth,
+                ButtonId::CycleTarget,
+            ]);
+            buttons.extend(state.game.available_items().into_iter().map(ButtonId::UseItem));
+            buttons.extend(Ore::ALL.map(ButtonId::SellOre));
+            buttons.push(ButtonId::SellAll);
+            buttons.extend((0..CONTRIBUTION_AMOUNTS.len()).map(ButtonId::Donate));
+            buttons.extend((0..DONATION_PERCENTAGES.len()).map(ButtonId::DonatePercent));
+            buttons.extend([
+                ButtonId::DonationSlider,
+                ButtonId::DonationField,
+                ButtonId::DonateCustom,
+                ButtonId::DonateAll,
+                ButtonId::Deposit,
+                ButtonId::Withdraw,
+                ButtonId::Borrow,
+                ButtonId::RepayLoan,
+            ]);
+            // A turn-based round is mined before anyone can strike, and ends on a button instead
+            if state.game.rules.turn_based {
+                buttons.push(ButtonId::EndTurn);
+            } else {
+                buttons.push(ButtonId::Strike);
+            }
+            
+            // Other hot-seat players' rows in the opponents panel
+            for miner_index in opponent_rows(state) {
+                match state.game.miners[miner_index].miner_type {
+                    MinerType::Human(seat) if state.local_seats.contains(&seat) => {
+                        buttons.push(ButtonId::SelectSeat(seat));
+                    },
+                    _ => {},
+                }
+            }
+            buttons
+        },
+        GameState::RoundEnd => {
+            if state.game.round_results.is_some() && can_skip_to_results(state) {
+                vec![ButtonId::Continue, ButtonId::SkipToResults]
+            } else if state.game.round_results.is_some() {
+                vec![ButtonId::Continue]
+            } else {
+                Vec::new()
+            }
+        },
+        GameState::GameOver => vec![ButtonId::Restart],
+    };
+    
+    // Settings are reachable from every screen
+    buttons.push(ButtonId::CycleTheme);
+    buttons
+}
+
+pub fn button_rect(state: &MainState, id: ButtonId) -> Rect {
+    match id {
+        ButtonId::ResearchNext(branch) => {
+            let row = QUICK_RESEARCH.iter().position(|b| *b == branch).unwrap_or(0);
+            Rect::new(30.0, 230.0 + row as f32 * 44.0, 200.0, 38.0)
+        },
+        // Next to the theme button on the tech tree screen, in the Upgrades header otherwise
+        ButtonId::TechTree if state.show_tech_tree => Rect::new(state.layout.width - 320.0, 22.0, 140.0, 36.0),
+        ButtonId::TechTree => Rect::new(150.0, 200.0, 90.0, 28.0),
+        ButtonId::TechNode(node) => tech_node_rect(state, node),
+        // The shop sits above the opponents: health on the first row, sabotage on the second
+        ButtonId::BuyHeal => Rect::new(95.0, 336.0, 135.0, 34.0),
+        ButtonId::BuyShield => Rect::new(240.0, 336.0, 135.0, 34.0),
+        ButtonId::UpgradeMaxHealth => Rect::new(385.0, 336.0, 135.0, 34.0),
+        ButtonId::CycleTarget => Rect::new(95.0, 378.0, 135.0, 34.0),
+        // Scouting sits in the Opponents header, next to what it reveals
+        ButtonId::UseItem(item) if item.is_scouting() => {
+            let column = Item::SCOUTING.iter().position(|i| *i == item).unwrap_or(0);
+            Rect::new(150.0 + column as f32 * 115.0, 436.0, 110.0, 28.0)
+        },
+        ButtonId::UseItem(item) => Rect::new(240.0 + item.index() as f32 * 95.0, 378.0, 90.0, 34.0),
+        // Fixed amounts and percentages share the panel width in two rows
+        ButtonId::Donate(i) => Rect::new(state.layout.width - 240.0 + i as f32 * 45.0, 185.0, 40.0, 30.0),
+        ButtonId::DonatePercent(i) => Rect::new(state.layout.width - 240.0 + i as f32 * 75.0, 225.0, 70.0, 30.0),
+        ButtonId::DonationSlider => Rect::new(state.layout.width - 240.0, 265.0, 220.0, 24.0),
+        ButtonId::DonationField => Rect::new(state.layout.width - 240.0, 300.0, 140.0, 30.0),
+        ButtonId::DonateCustom => Rect::new(state.layout.width - 95.0, 300.0, 75.0, 30.0),
+        ButtonId::DonateAll => Rect::new(state.layout.width - 240.0, 360.0, 220.0, 30.0),
+        // The market fills the rest of the donation column
+        ButtonId::SellAll => Rect::new(state.layout.width - 100.0, 406.0, 80.0, 26.0),
+        ButtonId::SellOre(ore) => Rect::new(state.layout.width - 95.0, 441.0 + ore.index() as f32 * 32.0, 75.0, 26.0),
+        // The rock and then the bank sit under the activity log
+        ButtonId::Strike => Rect::new(260.0, 218.0, state.layout.width - 530.0, 44.0),
+        ButtonId::Deposit => Rect::new(270.0, 292.0, 60.0, 24.0),
+        ButtonId::Withdraw => Rect::new(333.0, 292.0, 60.0, 24.0),
+        ButtonId::Borrow => Rect::new(396.0, 292.0, 60.0, 24.0),
+        ButtonId::RepayLoan => Rect::new(459.0, 292.0, 60.0, 24.0),
+        // Takes the place of the round timer, which a turn-based round doesn't have
+        ButtonId::EndTurn => Rect::new(190.0, 22.0, 150.0, 36.0),
+        // The hand is laid out side by side in the middle of the draft screen
+        ButtonId::PickPerk(i) => {
+            let hand_width = DRAFT_HAND as f32 * (PERK_CARD_WIDTH + 20.0) - 20.0;
+            Rect::new(
+                (state.layout.width - hand_width) / 2.0 + i as f32 * (PERK_CARD_WIDTH + 20.0),
+                170.0,
+                PERK_CARD_WIDTH,
+                PERK_CARD_HEIGHT,
+            )
+        },
+        // Continue moves left to make room when the skip button is shown beside it
+        ButtonId::Continue if can_skip_to_results(state) => {
+            let panel_rect = round_end_panel_rect(state);
+            Rect::new(state.layout.width / 2.0 - 210.0, panel_rect.bottom() - 50.0, 200.0, 40.0)
+        },
+        ButtonId::Continue => {
+            let panel_rect = round_end_panel_rect(state);
+            Rect::new(state.layout.width / 2.0 - 100.0, panel_rect.bottom() - 50.0, 200.0, 40.0)
+        },
+        ButtonId::SkipToResults => {
+            let panel_rect = round_end_panel_rect(state);
+            Rect::new(state.layout.width / 2.0 + 10.0, panel_rect.bottom() - 50.0, 200.0, 40.0)
+        },
+        ButtonId::SelectSeat(seat) => {
+            let row = opponent_rows(state).iter().position(|i| *i == seat).unwrap_or(0);
+            opponent_row_rect(state, row)
+        },
+        ButtonId::CycleTheme => Rect::new(state.layout.width - 160.0, 22.0, 140.0, 36.0),
+        // Observers see the final standings table instead of the game over panel
+        ButtonId::Restart if state.is_observer() => {
+            Rect::new(state.layout.width / 2.0 - 75.0, state.layout.height - 60.0, 150.0, 40.0)
+        },
+        ButtonId::Restart => Rect::new(state.layout.width / 2.0 - 75.0, game_over_panel_rect(state).bottom() - 60.0, 150.0, 40.0),
+        ButtonId::Lobby(button) => lobby_button_rect(state, button),
+    }
+}
+
+// Only a local match can be played out ahead of time; a server keeps its own clock
+fn can_skip_to_results(state: &MainState) -> bool {
+    state.net.is_none() && state.game.all_humans_out()
+}
+
+fn lobby_button_rect(state: &MainState, button: LobbyButton) -> Rect {
+    let rule_row_y = |field: RuleField| {
+        let row = RuleField::ALL.iter().position(|f| *f == field).unwrap_or(0);
+        120.0 + row as f32 * 28.0
+    };
+
+    match button {
+        LobbyButton::Name => Rect::new(30.0, 440.0, 220.0, 34.0),
+        LobbyButton::Color => Rect::new(270.0, 440.0, 130.0, 34.0),
+        LobbyButton::Ready => Rect::new(420.0, 440.0, 150.0, 34.0),
+        LobbyButton::Start => Rect::new(state.layout.width - 190.0, 440.0, 160.0, 34.0),
+        LobbyButton::RuleDown(field) => Rect::new(state.layout.width - 120.0, rule_row_y(field), 40.0, 26.0),
+        LobbyButton::RuleUp(field) => Rect::new(state.layout.width - 70.0, rule_row_y(field), 40.0, 26.0),
+    }
+}
+
+fn button_state(state: &MainState, id: ButtonId) -> ButtonState {
+    if !state.is_button_enabled(id) {
+        return ButtonState::Disabled;
+    }
+
+    let (x, y) = state.mouse_position;
+    let hovered = button_rect(state, id).contains([x, y]);
+
+    match (hovered, state.pressed_button == Some(id)) {
+        (true, true) => ButtonState::Pressed,
+        (true, false) => ButtonState::Hovered,
+        _ => ButtonState::Normal,
+    }
+}
+
+// Pressed buttons sink into their shadow
+fn button_face(rect: Rect, button_state: ButtonState) -> Rect {
+    if button_state == ButtonState::Pressed {
+        Rect::new(rect.x + 2.0, rect.y + 2.0, rect.w, rect.h)
+    } else {
+        rect
+    }
+}
+
+// Focus ring for keyboard navigation and a keycap badge with the button's shortcut
+fn draw_button_extras(state: &MainState, ctx: &mut Context, id: ButtonId) -> GameResult {
+    let theme = &state.theme;
+    let rect = button_rect(state, id);
+
+    if state.focused_button == Some(id) {
+        let focus_rect = Rect::new(rect.x - 3.0, rect.y - 3.0, rect.w + 6.0, rect.h + 6.0);
+        let focus_ring = MeshBuilder::new()
+            .rounded_rectangle(
+                DrawMode::stroke(2.0),
+                focus_rect,
+                10.0,
+                theme.primary,
+            )?
+            .build(ctx)?;
+
+        graphics::draw(ctx, &focus_ring, DrawParam::default())?;
+    }
+
+    // Shared actions are bound by the first player
+    let key = state
+        .bindings_for(state.active_seat)
+        .and_then(|bindings| bindings.key_for(id))
+        .or_else(|| state.key_bindings[0].key_for(id));
+    
+    if let Some(key) = key {
+        let label = key_label(key);
+        let badge_w = 8.0 + label.len() as f32 * 7.0;
+        let badge_rect = Rect::new(rect.right() - badge_w - 4.0, rect.y - 7.0, badge_w, 15.0);
+
+        let badge = MeshBuilder::new()
+            .rounded_rectangle(
+                DrawMode::fill(),
+                badge_rect,
+                4.0,
+                theme.text,
+            )?
+            .build(ctx)?;
+
+        graphics::draw(ctx, &badge, DrawParam::default())?;
+
+        let badge_text = Text::new(
+            TextFragment::new(label)
+                .scale(13.0)
+                .color(theme.text_on_dark)
+        );
+
+        graphics::draw(
+            ctx,
+            &badge_text,
+            DrawParam::default().dest([badge_rect.x + 4.0, badge_rect.y + 1.0]),
+        )?;
+    }
+
+    Ok(())
+}
+
+// Settings button that cycles through the shipped themes
+fn draw_theme_button(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
+    
+    draw_button_with_text(
+        ctx,
+        theme,
+        button_rect(state, ButtonId::CycleTheme),
+        theme.primary,
+        &format!("Theme: {}", theme.name),
+        14.0,
+        button_state(state, ButtonId::CycleTheme)
+    )?;
+    draw_button_extras(state, ctx, ButtonId::CycleTheme)?;
+    
+    Ok(())
+}
+
+fn mix_colors(a: Color, b: Color, t: f32) -> Color {
+    Color::new(
+        a.r + (b.r - a.r) * t,
+        a.g + (b.g - a.g) * t,
+        a.b + (b.b - a.b) * t,
+        a.a + (b.a - a.a) * t,
+    )
+}
 
 // Helper function to create modern looking panels
 fn draw_panel(
     ctx: &mut Context,
+    theme: &Theme,
     rect: Rect,
     color: Color,
     shadow_size: f32,
@@ -37,7 +440,7 @@ fn draw_panel(
                 DrawMode::fill(),
                 shadow_rect,
                 8.0, // Corner radius
-                Color::new(0.0, 0.0, 0.0, 0.2), // Semi-transparent black shadow
+                theme.shadow,
             )?
             .build(ctx)?;
         
@@ -63,7 +466,7 @@ fn draw_panel(
             DrawMode::fill(),
             highlight_rect,
             1.0,
-            Color::new(1.0, 1.0, 1.0, 0.4), // Semi-transparent white
+            theme.highlight,
         )?
         .build(ctx)?;
     
@@ -75,10 +478,19 @@ fn draw_panel(
 // Helper function to create a beautiful gradient button
 fn draw_button(
     ctx: &mut Context,
+    theme: &Theme,
     rect: Rect,
     color: Color,
-    hover: bool,
+    button_state: ButtonState,
 ) -> GameResult {
+    // Disabled buttons keep a hint of their colour but are washed out towards gray
+    let color = match button_state {
+        ButtonState::Disabled => mix_colors(color, theme.disabled, 0.6),
+        ButtonState::Pressed => mix_colors(color, Color::BLACK, 0.15),
+        _ => color,
+    };
+    let face = button_face(rect, button_state);
+
     // Create a shadow for the button
     let shadow_rect = Rect::new(
         rect.x + 2.0,
@@ -92,7 +504,7 @@ fn draw_button(
             DrawMode::fill(),
             shadow_rect,
             8.0, // Corner radius
-            Color::new(0.0, 0.0, 0.0, 0.2), // Semi-transparent black shadow
+            theme.shadow,
         )?
         .build(ctx)?;
     
@@ -102,7 +514,7 @@ fn draw_button(
     let button_base = MeshBuilder::new()
         .rounded_rectangle(
             DrawMode::fill(),
-            rect,
+            face,
             8.0, // Corner radius
             color,
         )?
@@ -111,13 +523,19 @@ fn draw_button(
     graphics::draw(ctx, &button_base, DrawParam::default())?;
     
     // Add highlight to make it look 3D
-    let highlight_rect = Rect::new(rect.x, rect.y, rect.w, rect.h / 2.0);
+    // Strength relative to the theme's highlight colour
+    let highlight_strength = match button_state {
+        ButtonState::Hovered => 0.9, // Brighter highlight when hovered
+        ButtonState::Pressed | ButtonState::Disabled => 0.25, // Flat when pressed or unavailable
+        ButtonState::Normal => 0.5,
+    };
+    let highlight_rect = Rect::new(face.x, face.y, face.w, face.h / 2.0);
     let highlight = MeshBuilder::new()
         .rounded_rectangle(
             DrawMode::fill(),
             highlight_rect,
             8.0,
-            Color::new(1.0, 1.0, 1.0, if hover { 0.3 } else { 0.2 }), // Brighter highlight when "hovered"
+            Color { a: theme.highlight.a * highlight_strength, ..theme.highlight },
         )?
         .build(ctx)?;
     
@@ -129,21 +547,19 @@ fn draw_button(
 // Helper function to create buttons with text
 fn draw_button_with_text(
     ctx: &mut Context,
+    theme: &Theme,
     rect: Rect,
     color: Color,
     text: &str,
     text_size: f32,
-    hover: bool,
+    button_state: ButtonState,
 ) -> GameResult {
     // Draw the button
-    draw_button(ctx, rect, color, hover)?;
+    draw_button(ctx, theme, rect, color, button_state)?;
+    let rect = button_face(rect, button_state);
     
     // Draw text
-    let text_color = if color.r + color.g + color.b > 1.8 {
-        COLOR_TEXT // Dark text for light buttons
-    } else {
-        COLOR_TEXT_LIGHT // Light text for dark buttons
-    };
+    let text_color = theme.text_on(color);
     
     // Create text with proper scaling
     let button_text = Text::new(
@@ -169,6 +585,7 @@ fn draw_button_with_text(
 // Function to create a better looking header text
 fn draw_header_text(
     ctx: &mut Context,
+    theme: &Theme,
     text: &str,
     x: f32,
     y: f32,
@@ -179,7 +596,7 @@ fn draw_header_text(
     let shadow_text = Text::new(
         TextFragment::new(text)
             .scale(size)
-            .color(Color::new(0.0, 0.0, 0.0, 0.3))
+            .color(theme.shadow)
     );
     
     graphics::draw(
@@ -206,6 +623,7 @@ fn draw_header_text(
 // Function to draw a game stat with label and value
 fn draw_stat(
     ctx: &mut Context,
+    theme: &Theme,
     label: &str,
     value: &str,
     x: f32,
@@ -216,7 +634,7 @@ fn draw_stat(
     let label_text = Text::new(
         TextFragment::new(label)
             .scale(18.0)
-            .color(COLOR_TEXT)
+            .color(theme.text)
     );
     
     graphics::draw(
@@ -247,6 +665,7 @@ fn draw_stat(
 // Draws a progress bar
 fn draw_progress_bar(
     ctx: &mut Context,
+    theme: &Theme,
     rect: Rect,
     progress: f32, // 0.0 to 1.0
     color: Color,
@@ -257,14 +676,14 @@ fn draw_progress_bar(
             DrawMode::fill(),
             rect,
             4.0,
-            COLOR_DISABLED,
+            theme.disabled,
         )?
         .build(ctx)?;
     
     graphics::draw(ctx, &background, DrawParam::default())?;
     
     // Progress
-    let progress_width = rect.w * progress.min(1.0).max(0.0);
+    let progress_width = rect.w * progress.clamp(0.0, 1.0);
     if progress_width > 0.0 {
         let progress_rect = Rect::new(rect.x, rect.y, progress_width, rect.h);
         let progress_mesh = MeshBuilder::new()
@@ -282,85 +701,155 @@ fn draw_progress_bar(
     Ok(())
 }
 pub fn draw_game_ui(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
     // Clear with the background color
-    graphics::clear(ctx, COLOR_BACKGROUND);
+    graphics::clear(ctx, theme.background);
     
     // Calculate round timer progress
-    let round_elapsed = Instant::now().duration_since(state.round_start_time);
-    let time_left = if round_elapsed < ROUND_DURATION {
-        ROUND_DURATION - round_elapsed
-    } else {
-        std::time::Duration::from_secs(0)
-    };
-    let timer_progress = 1.0 - (time_left.as_secs_f32() / ROUND_DURATION.as_secs_f32());
+    let time_left = state.game.time_left();
+    let timer_progress = 1.0 - (time_left.as_secs_f32() / state.game.rules.round_duration().as_secs_f32());
 
     // Top header panel
-    let header_rect = Rect::new(10.0, 10.0, WINDOW_WIDTH - 20.0, 60.0);
-    draw_panel(ctx, header_rect, COLOR_PANEL, 3.0)?;
+    let header_rect = Rect::new(10.0, 10.0, state.layout.width - 20.0, 60.0);
+    draw_panel(ctx, theme, header_rect, theme.panel, 3.0)?;
     
     // Draw round info
     draw_header_text(
         ctx,
-        &format!("Round {}/{}", state.current_round, MAX_ROUNDS),
+        theme,
+        &state.game.round_label(),
         30.0,
         25.0,
         24.0,
-        COLOR_PRIMARY
+        theme.primary
     )?;
     
-    // Draw timer
-    let timer_rect = Rect::new(200.0, 30.0, 300.0, 20.0);
-    draw_progress_bar(ctx, timer_rect, timer_progress, COLOR_SECONDARY)?;
-    
-    // Draw time text
-    let time_text = Text::new(
-        TextFragment::new(format!("{}s", time_left.as_secs()))
-            .scale(18.0)
-            .color(COLOR_TEXT)
-    );
+    if state.game.rules.turn_based {
+        draw_turn_status(state, ctx)?;
+    } else {
+        // Draw timer
+        let timer_rect = Rect::new(190.0, 30.0, 260.0, 20.0);
+        draw_progress_bar(ctx, theme, timer_rect, timer_progress, theme.secondary)?;
+        
+        // Draw time text
+        let time_text = Text::new(
+            TextFragment::new(format!("{}s", time_left.as_secs()))
+                .scale(18.0)
+                .color(theme.text)
+        );
+        
+        graphics::draw(
+            ctx,
+            &time_text,
+            DrawParam::default().dest([460.0, 28.0]),
+        )?;
+    }
     
-    graphics::draw(
-        ctx,
-        &time_text,
-        DrawParam::default().dest([510.0, 28.0]),
-    )?;
+    draw_round_event(state, ctx, 505.0, 29.0)?;
     
     // Player stats panel
-    let stats_rect = Rect::new(10.0, 80.0, 240.0, 90.0);
-    draw_panel(ctx, stats_rect, COLOR_PANEL, 3.0)?;
+    let stats_rect = Rect::new(10.0, 80.0, 240.0, 106.0);
+    draw_panel(ctx, theme, stats_rect, theme.panel, 3.0)?;
+    
+    // Whose panels these are when several people share the machine
+    if state.game.rules.is_hot_seat() {
+        let name_text = Text::new(
+            TextFragment::new(state.active_miner().name.as_str())
+                .scale(18.0)
+                .color(theme.primary)
+        );
+        
+        graphics::draw(
+            ctx,
+            &name_text,
+            DrawParam::default().dest([150.0, 95.0]),
+        )?;
+    }
     
     // Draw gold
     draw_stat(
         ctx,
+        theme,
         "Gold: ",
-        &format!("{:.0}", state.player.gold),
+        &format!("{:.0}", state.active_miner().gold),
         30.0,
         95.0,
-        COLOR_GOLD
+        theme.gold
     )?;
     
-    // Draw health
-    let health_color = if state.player.health <= 3 {
-        COLOR_SECONDARY
-    } else if state.player.health <= 6 {
-        Color::new(0.9, 0.6, 0.1, 1.0) // Orange
-    } else {
-        COLOR_ACCENT
+    // Bank balance and any loan, under the gold they come from
+    let miner = state.active_miner();
+    let bank_line = match miner.loan_due {
+        Some(due) => format!("Bank: {:.0}g   Loan: {:.0}g due R{}", miner.deposit, miner.loan, due),
+        None => format!("Bank: {:.0}g", miner.deposit),
     };
+    let bank_text = Text::new(
+        TextFragment::new(bank_line)
+            .scale(12.0)
+            .color(theme.text)
+    );
+    
+    graphics::draw(
+        ctx,
+        &bank_text,
+        DrawParam::default().dest([30.0, 114.0]),
+    )?;
+    
+    // Draw health
+    let health_color = theme.health_color(state.active_miner().health);
     
     draw_stat(
         ctx,
+        theme,
         "Health: ",
-        &state.player.health.to_string(),
+        &format!("{}/{}", state.active_miner().health, state.active_miner().max_health),
         30.0,
         130.0,
         health_color
     )?;
+    
+    if state.active_miner().shielded {
+        let shield_text = Text::new(
+            TextFragment::new("Shielded")
+                .scale(18.0)
+                .color(theme.primary)
+        );
+        
+        graphics::draw(
+            ctx,
+            &shield_text,
+            DrawParam::default().dest([150.0, 130.0]),
+        )?;
+    }
+    
+    if let Some(card) = state.active_miner().perk {
+        let perk_text = Text::new(
+            TextFragment::new(format!("Perk: {}", perks::deck().cards[card].name))
+                .scale(13.0)
+                .color(theme.accent)
+        );
+        
+        graphics::draw(
+            ctx,
+            &perk_text,
+            DrawParam::default().dest([30.0, 150.0]),
+        )?;
+    }
+
+    draw_mine_reserve(state, ctx)?;
 
     // Draw upgrade options
     draw_upgrade_options(state, ctx)?;
     
+    draw_shop(state, ctx)?;
+    
     draw_game_activity_log(state, ctx)?;
+    
+    if !state.game.rules.turn_based {
+        draw_rock(state, ctx)?;
+    }
+    
+    draw_bank(state, ctx)?;
 
     // Draw bot info
     draw_bot_info(state, ctx)?;
@@ -368,22 +857,124 @@ pub fn draw_game_ui(state: &MainState, ctx: &mut Context) -> GameResult {
     // Draw contribute gold option
     draw_contribute_option(state, ctx)?;
 
+    draw_theme_button(state, ctx)?;
+    
+    draw_event_banner(state, ctx)?;
+
+    Ok(())
+}
+
+// The End Turn button and how many miners are still deciding, in place of the round timer
+fn draw_turn_status(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
+    let label = if state.active_miner().turn_done { "Turn Ended" } else { "End Turn" };
+    
+    draw_button_with_text(
+        ctx,
+        theme,
+        button_rect(state, ButtonId::EndTurn),
+        theme.secondary,
+        label,
+        18.0,
+        button_state(state, ButtonId::EndTurn)
+    )?;
+    draw_button_extras(state, ctx, ButtonId::EndTurn)?;
+    
+    // In a match played by file the others decide on their own machines, once the file reaches them
+    let status = if state.match_file.is_some() && state.active_miner().turn_done {
+        "Turn saved".to_string()
+    } else {
+        let deciding = state.game.miners.iter().filter(|miner| miner.alive && !miner.turn_done).count();
+        format!("{} to go", deciding)
+    };
+    let status_text = Text::new(
+        TextFragment::new(status)
+            .scale(16.0)
+            .color(theme.text)
+    );
+    
+    graphics::draw(
+        ctx,
+        &status_text,
+        DrawParam::default().dest([350.0, 31.0]),
+    )?;
+    
+    Ok(())
+}
+
+// Name of the current round's event, shown beside the timer
+fn draw_round_event(state: &MainState, ctx: &mut Context, x: f32, y: f32) -> GameResult {
+    if let Some(event) = state.game.event {
+        let event_text = Text::new(
+            TextFragment::new(event.name())
+                .scale(16.0)
+                .color(state.theme.accent)
+        );
+        
+        graphics::draw(
+            ctx,
+            &event_text,
+            DrawParam::default().dest([x, y]),
+        )?;
+    }
+    
+    Ok(())
+}
+
+// Announces the round's event across the middle of the screen for its first few seconds
+fn draw_event_banner(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
+    let event = match state.game.event {
+        // A turn-based round has no clock to take the banner down, so the header has to do
+        Some(event) if !state.game.rules.turn_based && state.game.round_elapsed.as_secs_f32() < EVENT_BANNER_SECONDS => event,
+        _ => return Ok(()),
+    };
+    
+    let banner_rect = Rect::new(state.layout.width / 2.0 - 200.0, 230.0, 400.0, 80.0);
+    draw_panel(ctx, theme, banner_rect, theme.panel, 3.0)?;
+    
+    let name = event.name();
+    draw_header_text(
+        ctx,
+        theme,
+        name,
+        banner_rect.x + (banner_rect.w - name.len() as f32 * 13.0) / 2.0,
+        banner_rect.y + 12.0,
+        26.0,
+        theme.accent
+    )?;
+    
+    let description = event.description();
+    let description_text = Text::new(
+        TextFragment::new(description)
+            .scale(16.0)
+            .color(theme.text)
+    );
+    
+    graphics::draw(
+        ctx,
+        &description_text,
+        DrawParam::default().dest([banner_rect.x + (banner_rect.w - description.len() as f32 * 8.0) / 2.0, banner_rect.y + 50.0]),
+    )?;
+    
     Ok(())
 }
 
 fn draw_game_activity_log(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
     // Center panel for game activity
-    let log_rect = Rect::new(260.0, 80.0, WINDOW_WIDTH - 530.0, 240.0);
-    draw_panel(ctx, log_rect, COLOR_PANEL, 3.0)?;
+    let log_rect = Rect::new(260.0, 80.0, state.layout.width - 530.0, 130.0);
+    draw_panel(ctx, theme, log_rect, theme.panel, 3.0)?;
     
     // Panel header
     draw_header_text(
         ctx,
+        theme,
         "Game Activity",
         280.0,
         90.0,
         22.0,
-        COLOR_PRIMARY
+        theme.primary
     )?;
     
     // Draw a separator line
@@ -398,37 +989,43 @@ fn draw_game_activity_log(state: &MainState, ctx: &mut Context) -> GameResult {
         .rectangle(
             DrawMode::fill(),
             line_rect,
-            Color::new(0.8, 0.8, 0.8, 0.8)
+            theme.separator
         )?
         .build(ctx)?;
     
     graphics::draw(ctx, &line, DrawParam::default())?;
     
-    // Generate some sample activity entries
-    // In a real implementation, you would track this in the game state
-    let activities = [
-        ("You upgraded your Pickaxe to Lv1.", COLOR_TEXT),
-        ("Bot #3 contributed 58g of gold.", COLOR_TEXT),
-        ("Bot #1 upgraded their Mine to Lv1.", COLOR_TEXT),
-        ("You contributed 10g of gold.", COLOR_ACCENT),
-        ("Round 3 ended - you ranked #2!", COLOR_PRIMARY),
-    ];
+    // The latest entries, oldest at the top. Your own actions stand out, and so
+    // does anything done to you.
+    let log = &state.game.activity;
+    let activities = log[log.len().saturating_sub(ACTIVITY_LOG_ROWS)..].iter().map(|entry| {
+        let color = if entry.miner.is_none() {
+            theme.primary
+        } else if entry.target == Some(state.active_seat) {
+            theme.secondary
+        } else if entry.miner == Some(state.active_seat) {
+            theme.accent
+        } else {
+            theme.text
+        };
+        (entry.text_for(&state.local_seats), color)
+    });
     
-    let mut y_offset = log_rect.y + 60.0;
+    let mut y_offset = log_rect.y + 50.0;
     
-    for (i, (message, color)) in activities.iter().enumerate() {
+    for (i, (message, color)) in activities.enumerate() {
         // Row background - alternating colors
         let row_rect = Rect::new(
             log_rect.x + 10.0,
             y_offset - 5.0,
             log_rect.w - 20.0,
-            30.0
+            24.0
         );
         
         let row_color = if i % 2 == 0 {
-            Color::new(0.95, 0.95, 0.95, 0.7) // Slightly darker for even rows
+            theme.row_even // Slightly darker for even rows
         } else {
-            Color::new(1.0, 1.0, 1.0, 0.5) // Slightly lighter for odd rows
+            theme.row_odd // Slightly lighter for odd rows
         };
         
         let row = MeshBuilder::new()
@@ -444,9 +1041,9 @@ fn draw_game_activity_log(state: &MainState, ctx: &mut Context) -> GameResult {
         
         // Activity text
         let activity_text = Text::new(
-            TextFragment::new(*message)
-                .scale(16.0)
-                .color(*color)
+            TextFragment::new(message)
+                .scale(14.0)
+                .color(color)
         );
         
         graphics::draw(
@@ -455,154 +1052,424 @@ fn draw_game_activity_log(state: &MainState, ctx: &mut Context) -> GameResult {
             DrawParam::default().dest([log_rect.x + 20.0, y_offset]),
         )?;
         
-        y_offset += 35.0;
+        y_offset += 27.0;
     }
     
     Ok(())
 }
 
-fn draw_upgrade_options(state: &MainState, ctx: &mut Context) -> GameResult {
-    // Upgrades panel
-    let upgrades_rect = Rect::new(10.0, 180.0, 240.0, 140.0);
-    draw_panel(ctx, upgrades_rect, COLOR_PANEL, 3.0)?;
+// Strikes flash the rock for as long as the combo stays open, and the combo and the
+// latest strike show on its face. A critical swing or strike lights it up in full.
+fn draw_rock(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
+    let miner = state.active_miner();
+    let rect = button_rect(state, ButtonId::Strike);
+    let button_state = button_state(state, ButtonId::Strike);
+    
+    let flash = miner.combo_timer.as_secs_f32() / COMBO_WINDOW.as_secs_f32();
+    let color = if miner.crit_showing() {
+        mix_colors(theme.gold, theme.warning, miner.crit_flash.as_secs_f32() / CRIT_FLASH.as_secs_f32())
+    } else {
+        mix_colors(theme.stone, theme.gold, flash * 0.5)
+    };
+    draw_button(ctx, theme, rect, color, button_state)?;
+    draw_button_extras(state, ctx, ButtonId::Strike)?;
+    let face = button_face(rect, button_state);
+    let text_color = theme.text_on(color);
     
-    // Panel header
-    draw_header_text(
+    let label_text = Text::new(
+        TextFragment::new("Strike!")
+            .scale(20.0)
+            .color(text_color)
+    );
+    
+    graphics::draw(
         ctx,
-        "Upgrades",
-        30.0,
-        190.0,
-        22.0,
-        COLOR_PRIMARY
+        &label_text,
+        DrawParam::default().dest([face.x + 14.0, face.y + 12.0]),
     )?;
     
-    // Pickaxe upgrade button
-    let mut pickaxe_color = COLOR_SECONDARY;
-    let pickaxe_hover = false; // In a real game, check if mouse is over button
-    
-    if state.player.pickaxe_level < 4 && state.player.gold >= state.player.pickaxe_upgrade_cost() {
-        pickaxe_color = COLOR_ACCENT;
-    } else if state.player.pickaxe_level >= 4 {
-        pickaxe_color = COLOR_DISABLED;
+    if miner.crit_showing() {
+        let crit_text = Text::new(
+            TextFragment::new(format!("Critical! +{:.1}", miner.last_crit))
+                .scale(17.0)
+                .color(text_color)
+        );
+        
+        graphics::draw(
+            ctx,
+            &crit_text,
+            DrawParam::default().dest([face.x + 100.0, face.y + 14.0]),
+        )?;
+    } else if miner.combo_running() {
+        let combo = if miner.combo > 0 {
+            format!("+{:.1}  Combo x{:.1}", miner.last_strike, miner.combo_multiplier())
+        } else {
+            format!("+{:.1}", miner.last_strike)
+        };
+        let combo_text = Text::new(
+            TextFragment::new(combo)
+                .scale(15.0)
+                .color(text_color)
+        );
+        
+        graphics::draw(
+            ctx,
+            &combo_text,
+            DrawParam::default().dest([face.x + 100.0, face.y + 15.0]),
+        )?;
     }
     
-    let pickaxe_rect = Rect::new(30.0, 220.0, 200.0, 40.0);
-    draw_button(ctx, pickaxe_rect, pickaxe_color, pickaxe_hover)?;
-    
-    // Pickaxe icon (simplified)
-    let pick_handle = Rect::new(45.0, 230.0, 15.0, 20.0);
-    let pick_handle_mesh = MeshBuilder::new()
-        .rectangle(
-            DrawMode::fill(),
-            pick_handle,
-            Color::new(0.6, 0.4, 0.2, 1.0) // Brown
-        )?
-        .build(ctx)?;
-    
-    graphics::draw(ctx, &pick_handle_mesh, DrawParam::default())?;
-    
-    // Text
-    let text_color = if pickaxe_color.r + pickaxe_color.g + pickaxe_color.b > 1.8 {
-        COLOR_TEXT // Dark text for light buttons
-    } else {
-        COLOR_TEXT_LIGHT // Light text for dark buttons
+    Ok(())
+}
+
+// What is left in the ground: the active miner's own mine, or the deposit everyone shares.
+// The bar turns to the warning colour once swings start coming up poorer.
+fn draw_mine_reserve(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
+    let (label, reserve) = match (state.game.common_deposit, state.active_miner().reserve) {
+        (Some(deposit), _) => ("Shared mine", deposit),
+        (None, Some(reserve)) => ("Your mine", reserve),
+        (None, None) => return Ok(()),
     };
     
-    let pickaxe_text = Text::new(
-        TextFragment::new(format!(
-            "Pickaxe Lv{}/4: {:.0}g",
-            state.player.pickaxe_level,
-            state.player.pickaxe_upgrade_cost()
-        ))
-        .scale(18.0)
-        .color(text_color)
+    let reserve_text = Text::new(
+        TextFragment::new(format!("{} {:.0}%", label, reserve.fraction() * 100.0))
+            .scale(12.0)
+            .color(theme.text)
     );
     
     graphics::draw(
         ctx,
-        &pickaxe_text,
-        DrawParam::default().dest([70.0, 230.0]),
+        &reserve_text,
+        DrawParam::default().dest([30.0, 167.0]),
     )?;
     
-    // Mine upgrade button
-    let mut mine_color = COLOR_PRIMARY;
-    let mine_hover = false; // In a real game, check if mouse is over button
-    
-    if state.player.mine_level < 4 && state.player.gold >= state.player.mine_upgrade_cost() {
-        mine_color = COLOR_ACCENT;
-    } else if state.player.mine_level >= 4 {
-        mine_color = COLOR_DISABLED;
-    }
-    
-    let mine_rect = Rect::new(30.0, 270.0, 200.0, 40.0);
-    draw_button(ctx, mine_rect, mine_color, mine_hover)?;
+    let color = if reserve.richness() < 1.0 { theme.secondary } else { theme.gold };
+    draw_progress_bar(ctx, theme, Rect::new(145.0, 168.0, 85.0, 10.0), reserve.fraction(), color)?;
     
-    // Mine icon (simplified)
-    let mine_icon = MeshBuilder::new()
-        .circle(
-            DrawMode::fill(),
-            [45.0 + 7.5, 280.0 + 7.5],
-            7.5,
-            0.1,
-            Color::new(0.5, 0.5, 0.5, 1.0) // Gray
-        )?
-        .build(ctx)?;
+    Ok(())
+}
+
+// Deposits and loans, under the activity log
+fn draw_bank(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
     
-    graphics::draw(ctx, &mine_icon, DrawParam::default())?;
+    let bank_rect = Rect::new(260.0, 270.0, state.layout.width - 530.0, 50.0);
+    draw_panel(ctx, theme, bank_rect, theme.panel, 3.0)?;
     
-    // Text
-    let text_color = if mine_color.r + mine_color.g + mine_color.b > 1.8 {
-        COLOR_TEXT // Dark text for light buttons
-    } else {
-        COLOR_TEXT_LIGHT // Light text for dark buttons
-    };
+    draw_header_text(ctx, theme, "Bank", 270.0, 274.0, 16.0, theme.primary)?;
     
-    let mine_text = Text::new(
+    let rates_text = Text::new(
         TextFragment::new(format!(
-            "Mine Lv{}/4: {:.0}g",
-            state.player.mine_level,
-            state.player.mine_upgrade_cost()
+            "Deposits +{:.0}%, loans +{:.0}% a round",
+            DEPOSIT_INTEREST * 100.0,
+            LOAN_INTEREST * 100.0
         ))
-        .scale(18.0)
-        .color(text_color)
+            .scale(12.0)
+            .color(theme.text)
     );
     
     graphics::draw(
         ctx,
-        &mine_text,
-        DrawParam::default().dest([70.0, 280.0]),
+        &rates_text,
+        DrawParam::default().dest([320.0, 276.0]),
     )?;
-
-    Ok(())
-}
-
-fn draw_bot_info(state: &MainState, ctx: &mut Context) -> GameResult {
-    // Opponents panel
-    let opponents_rect = Rect::new(10.0, 330.0, WINDOW_WIDTH - 280.0, 260.0);
-    draw_panel(ctx, opponents_rect, COLOR_PANEL, 3.0)?;
     
-    // Panel header
-    draw_header_text(
-        ctx,
+    let step = format!("{:.0}", BANK_STEP);
+    let buttons = [
+        (ButtonId::Deposit, format!("+{}", step), theme.gold),
+        (ButtonId::Withdraw, format!("-{}", step), theme.gold),
+        (ButtonId::Borrow, "Borrow".to_string(), theme.secondary),
+        (ButtonId::RepayLoan, "Repay".to_string(), theme.accent),
+    ];
+    
+    for (id, label, color) in buttons {
+        draw_button_with_text(
+            ctx,
+            theme,
+            button_rect(state, id),
+            color,
+            &label,
+            13.0,
+            button_state(state, id)
+        )?;
+        draw_button_extras(state, ctx, id)?;
+    }
+    
+    Ok(())
+}
+
+fn draw_upgrade_options(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
+    let miner = state.active_miner();
+    // Upgrades panel
+    let upgrades_rect = Rect::new(10.0, 196.0, 240.0, 124.0);
+    draw_panel(ctx, theme, upgrades_rect, theme.panel, 3.0)?;
+    
+    // Panel header
+    draw_header_text(
+        ctx,
+        theme,
+        "Upgrades",
+        30.0,
+        204.0,
+        22.0,
+        theme.primary
+    )?;
+    
+    draw_button_with_text(
+        ctx,
+        theme,
+        button_rect(state, ButtonId::TechTree),
+        theme.primary,
+        "Tech Tree",
+        14.0,
+        button_state(state, ButtonId::TechTree)
+    )?;
+    draw_button_extras(state, ctx, ButtonId::TechTree)?;
+    
+    // One button per quick branch, buying whatever comes next in it
+    for branch in QUICK_RESEARCH {
+        let id = ButtonId::ResearchNext(branch);
+        let next = miner.next_in_branch(branch).map(|node| &tech::tree().nodes[node]);
+        
+        let mut color = if branch == Branch::Speed { theme.secondary } else { theme.primary };
+        match next {
+            Some(node) if miner.gold >= node.cost => color = theme.accent,
+            None => color = theme.disabled,
+            _ => {},
+        }
+        
+        let rect = button_rect(state, id);
+        let button_state = button_state(state, id);
+        draw_button(ctx, theme, rect, color, button_state)?;
+        draw_button_extras(state, ctx, id)?;
+        let face = button_face(rect, button_state);
+        
+        // Pickaxe handle for speed, a lump of stone for yield (simplified)
+        let icon = if branch == Branch::Speed {
+            MeshBuilder::new()
+                .rectangle(
+                    DrawMode::fill(),
+                    Rect::new(face.x + 15.0, face.y + 10.0, 15.0, 20.0),
+                    theme.wood
+                )?
+                .build(ctx)?
+        } else {
+            MeshBuilder::new()
+                .circle(
+                    DrawMode::fill(),
+                    [face.x + 22.5, face.y + 17.5],
+                    7.5,
+                    0.1,
+                    theme.stone
+                )?
+                .build(ctx)?
+        };
+        
+        graphics::draw(ctx, &icon, DrawParam::default())?;
+        
+        let text_color = theme.text_on(color);
+        
+        let (title, detail) = match next {
+            Some(node) => (node.name.clone(), format!("{:.0}g  {}", node.cost, node.effects.describe())),
+            None => (format!("{} maxed", branch.name()), "Nothing left to research".to_string()),
+        };
+        
+        let title_text = Text::new(
+            TextFragment::new(title)
+                .scale(15.0)
+                .color(text_color)
+        );
+        
+        graphics::draw(
+            ctx,
+            &title_text,
+            DrawParam::default().dest([face.x + 40.0, face.y + 4.0]),
+        )?;
+        
+        let detail_text = Text::new(
+            TextFragment::new(detail)
+                .scale(12.0)
+                .color(text_color)
+        );
+        
+        graphics::draw(
+            ctx,
+            &detail_text,
+            DrawParam::default().dest([face.x + 40.0, face.y + 22.0]),
+        )?;
+    }
+
+    Ok(())
+}
+
+// Health for gold: heals cost more each time, a shield only lasts the current round
+fn draw_shop(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
+    let miner = state.active_miner();
+    
+    let shop_rect = Rect::new(10.0, 330.0, state.layout.width - 280.0, 90.0);
+    draw_panel(ctx, theme, shop_rect, theme.panel, 3.0)?;
+    
+    draw_header_text(ctx, theme, "Shop", 30.0, 341.0, 22.0, theme.primary)?;
+    
+    let heal_label = format!("Heal +{}: {:.0}g", HEAL_AMOUNT, miner.heal_cost());
+    let shield_label = if miner.shielded {
+        "Shield active".to_string()
+    } else {
+        format!("Shield: {:.0}g", miner.shield_cost())
+    };
+    let max_health_label = if miner.max_health_level >= MAX_HEALTH_UPGRADES {
+        "Max HP maxed".to_string()
+    } else {
+        format!("Max HP +{}: {:.0}g", MAX_HEALTH_STEP, miner.max_health_upgrade_cost())
+    };
+    
+    let items = [
+        (ButtonId::BuyHeal, heal_label, theme.accent),
+        (ButtonId::BuyShield, shield_label, theme.primary),
+        (ButtonId::UpgradeMaxHealth, max_health_label, theme.secondary),
+    ];
+    
+    for (id, label, color) in items {
+        draw_button_with_text(
+            ctx,
+            theme,
+            button_rect(state, id),
+            color,
+            &label,
+            15.0,
+            button_state(state, id)
+        )?;
+        draw_button_extras(state, ctx, id)?;
+    }
+    
+    // Sabotage items, each with its own cooldown
+    let target_label = match state.item_target(state.active_seat) {
+        Some(target) => format!("Target: {}", state.game.miners[target].name),
+        None => "No target".to_string(),
+    };
+    draw_button_with_text(
+        ctx,
+        theme,
+        button_rect(state, ButtonId::CycleTarget),
+        theme.primary,
+        &target_label,
+        15.0,
+        button_state(state, ButtonId::CycleTarget)
+    )?;
+    draw_button_extras(state, ctx, ButtonId::CycleTarget)?;
+    
+    for item in Item::SABOTAGE {
+        draw_item_button(state, ctx, item)?;
+    }
+    
+    Ok(())
+}
+
+// Price, or the seconds left on the cooldown
+fn draw_item_button(state: &MainState, ctx: &mut Context, item: Item) -> GameResult {
+    let id = ButtonId::UseItem(item);
+    let cooldown = state.active_miner().cooldown_left(item);
+    let label = if cooldown.is_zero() {
+        format!("{} {:.0}g", item.name(), item.cost())
+    } else {
+        format!("{} {}s", item.name(), cooldown.as_secs() + 1)
+    };
+    draw_button_with_text(
+        ctx,
+        &state.theme,
+        button_rect(state, id),
+        state.theme.secondary,
+        &label,
+        13.0,
+        button_state(state, id)
+    )?;
+    draw_button_extras(state, ctx, id)?;
+    
+    Ok(())
+}
+
+// Living miners other than the one whose panels are shown
+fn opponent_rows(state: &MainState) -> Vec<usize> {
+    state.game.miners
+        .iter()
+        .enumerate()
+        .filter(|(i, miner)| *i != state.active_seat && miner.alive)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+// Rows are 50px apart and squeeze together when a full hot-seat table doesn't fit
+fn opponent_row_rect(state: &MainState, row: usize) -> Rect {
+    let panel_height = state.layout.height - 440.0;
+    let row_count = opponent_rows(state).len().max(1) as f32;
+    let spacing = ((panel_height - 50.0) / row_count).min(50.0);
+    
+    Rect::new(20.0, 470.0 + row as f32 * spacing, state.layout.width - 300.0, (spacing - 6.0).min(40.0))
+}
+
+fn draw_bot_info(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
+    // Opponents panel
+    let opponents_rect = Rect::new(10.0, 430.0, state.layout.width - 280.0, state.layout.height - 440.0);
+    draw_panel(ctx, theme, opponents_rect, theme.panel, 3.0)?;
+    
+    // Panel header
+    draw_header_text(
+        ctx,
+        theme,
         "Opponents",
         30.0,
-        340.0,
+        440.0,
         22.0,
-        COLOR_PRIMARY
+        theme.primary
     )?;
     
-    let mut y_offset = 380.0;
+    for (label, x) in [("Donated", 420.0), ("Gold", 480.0)] {
+        let column_text = Text::new(
+            TextFragment::new(label)
+                .scale(14.0)
+                .color(theme.gold)
+        );
+        
+        graphics::draw(
+            ctx,
+            &column_text,
+            DrawParam::default().dest([x, 448.0]),
+        )?;
+    }
+    
+    if state.game.rules.fog_of_war {
+        for item in Item::SCOUTING {
+            draw_item_button(state, ctx, item)?;
+        }
+    }
+    
+    let target = state.item_target(state.active_seat);
     
-    for (i, bot) in state.bots.iter().enumerate() {
-        if bot.alive {
+    for (i, miner_index) in opponent_rows(state).into_iter().enumerate() {
+        let bot = &state.game.miners[miner_index];
+        let view = state.game.observe(Some(state.active_seat), miner_index);
+        let row_rect = opponent_row_rect(state, i);
+        let y_offset = row_rect.y + 5.0;
+        {
             // Background for bot row
-            let row_rect = Rect::new(20.0, y_offset - 5.0, opponents_rect.w - 20.0, 40.0);
-            let row_color = if i % 2 == 0 {
-                Color::new(0.95, 0.95, 0.95, 0.7) // Slightly darker for even rows
+            let mut row_color = if i % 2 == 0 {
+                theme.row_even // Slightly darker for even rows
             } else {
-                Color::new(1.0, 1.0, 1.0, 0.5) // Slightly lighter for odd rows
+                theme.row_odd // Slightly lighter for odd rows
             };
             
+            // Other hot-seat players' rows switch the panels over to them
+            if let MinerType::Human(seat) = bot.miner_type {
+                if button_state(state, ButtonId::SelectSeat(seat)) == ButtonState::Hovered {
+                    row_color = mix_colors(row_color, theme.primary, 0.2);
+                }
+            }
+            
             let row = MeshBuilder::new()
                 .rounded_rectangle(
                     DrawMode::fill(),
@@ -614,11 +1481,19 @@ fn draw_bot_info(state: &MainState, ctx: &mut Context) -> GameResult {
             
             graphics::draw(ctx, &row, DrawParam::default())?;
             
-            // Bot name with icon
+            // Outline the rival that dynamite and claim jumps will hit
+            if target == Some(miner_index) {
+                let outline = MeshBuilder::new()
+                    .rounded_rectangle(DrawMode::stroke(2.0), row_rect, 4.0, theme.secondary)?
+                    .build(ctx)?;
+                graphics::draw(ctx, &outline, DrawParam::default())?;
+            }
+            
+            // Bot name with icon, in the player's own colour if they picked one
             let bot_name = Text::new(
-                TextFragment::new(format!("Bot #{}", i + 1))
+                TextFragment::new(bot.name.as_str())
                     .scale(18.0)
-                    .color(COLOR_PRIMARY)
+                    .color(bot.color.map_or(theme.primary, |color| PLAYER_COLORS[color]))
             );
             
             graphics::draw(
@@ -629,24 +1504,18 @@ fn draw_bot_info(state: &MainState, ctx: &mut Context) -> GameResult {
             
             // Health bar
             let health_rect = Rect::new(120.0, y_offset + 5.0, 100.0, 15.0);
-            let health_progress = bot.health as f32 / 10.0; // Assuming max health is 10
+            let health_progress = view.health as f32 / view.max_health as f32;
             
-            // Health color based on remaining health
-            let health_color = if bot.health <= 3 {
-                COLOR_SECONDARY // Red for low health
-            } else if bot.health <= 6 {
-                Color::new(0.9, 0.6, 0.1, 1.0) // Orange for medium health
-            } else {
-                COLOR_ACCENT // Green for high health
-            };
+            // Health color based on remaining health; a shield turns the bar blue
+            let health_color = if view.shielded { theme.primary } else { theme.health_color(view.health) };
             
-            draw_progress_bar(ctx, health_rect, health_progress, health_color)?;
+            draw_progress_bar(ctx, theme, health_rect, health_progress, health_color)?;
             
             // Health text
             let health_text = Text::new(
-                TextFragment::new(format!("{}", bot.health))
+                TextFragment::new(format!("{}", view.health))
                     .scale(16.0)
-                    .color(COLOR_TEXT)
+                    .color(theme.text)
             );
             
             graphics::draw(
@@ -655,286 +1524,423 @@ fn draw_bot_info(state: &MainState, ctx: &mut Context) -> GameResult {
                 DrawParam::default().dest([230.0, y_offset]),
             )?;
             
-            // Pickaxe level icon and text
+            // Mining speed and yield from their research
             let pickaxe_icon_rect = Rect::new(280.0, y_offset + 2.0, 10.0, 15.0);
             let pickaxe_icon_mesh = MeshBuilder::new()
                 .rectangle(
                     DrawMode::fill(),
                     pickaxe_icon_rect,
-                    Color::new(0.6, 0.4, 0.2, 1.0) // Brown
+                    theme.wood
                 )?
                 .build(ctx)?;
             
             graphics::draw(ctx, &pickaxe_icon_mesh, DrawParam::default())?;
             
-            let pickaxe_text = Text::new(
-                TextFragment::new(format!("Lv{}", bot.pickaxe_level))
+            // The fog only gives away a rough tier
+            let speed = match view.stats {
+                Some(stats) => format!("{:.1}/s", stats.mining_speed),
+                None => format!("Tier {}", view.tier),
+            };
+            let speed_text = Text::new(
+                TextFragment::new(speed)
                     .scale(16.0)
-                    .color(COLOR_SECONDARY)
+                    .color(theme.secondary)
             );
             
             graphics::draw(
                 ctx,
-                &pickaxe_text,
-                DrawParam::default().dest([300.0, y_offset]),
+                &speed_text,
+                DrawParam::default().dest([295.0, y_offset]),
             )?;
             
-            // Mine level icon and text
             let mine_icon = MeshBuilder::new()
                 .circle(
                     DrawMode::fill(),
                     [370.0, y_offset + 10.0],
                     5.0,
                     0.1,
-                    Color::new(0.5, 0.5, 0.5, 1.0) // Gray
+                    theme.stone
                 )?
                 .build(ctx)?;
             
             graphics::draw(ctx, &mine_icon, DrawParam::default())?;
             
-            let mine_text = Text::new(
-                TextFragment::new(format!("Lv{}", bot.mine_level))
+            let yield_text = Text::new(
+                TextFragment::new(amount_label(view.stats.map(|stats| stats.gold_per_swing)))
+                    .scale(16.0)
+                    .color(theme.primary)
+            );
+            
+            graphics::draw(
+                ctx,
+                &yield_text,
+                DrawParam::default().dest([380.0, y_offset]),
+            )?;
+            
+            // Donation so far this round, as far as anyone else can tell
+            let donated_text = Text::new(
+                TextFragment::new(amount_label(view.donation))
                     .scale(16.0)
-                    .color(COLOR_PRIMARY)
+                    .color(theme.gold)
             );
             
             graphics::draw(
                 ctx,
-                &mine_text,
-                DrawParam::default().dest([385.0, y_offset]),
+                &donated_text,
+                DrawParam::default().dest([420.0, y_offset]),
             )?;
             
-            y_offset += 50.0; // Increased spacing between bot rows
+            // Hot-seat players can see each other's gold; bots keep theirs hidden unless scouted
+            if !bot.is_bot() || view.scouted {
+                let gold_text = Text::new(
+                    TextFragment::new(amount_label(view.gold))
+                        .scale(16.0)
+                        .color(theme.gold)
+                );
+                
+                graphics::draw(
+                    ctx,
+                    &gold_text,
+                    DrawParam::default().dest([480.0, y_offset]),
+                )?;
+            }
         }
     }
     
     Ok(())
 }
-fn draw_win_loss_tracker(state: &MainState, ctx: &mut Context, x: f32, y: f32) -> GameResult {
-    // Section header
+// Gold amount, or "?" while the fog, a sealed auction or a perk hides it
+fn amount_label(amount: Option<f32>) -> String {
+    match amount {
+        Some(amount) => format!("{:.0}g", amount),
+        None => "?".to_string(),
+    }
+}
+
+// Ore held, today's prices and which way they are heading, with a sell button per ore
+fn draw_market(state: &MainState, ctx: &mut Context, x: f32, y: f32) -> GameResult {
+    let theme = &state.theme;
+    let miner = state.active_miner();
+    let market = &state.game.market;
+    
     draw_header_text(
         ctx,
-        "Round Results",
+        theme,
+        "Market",
         x,
         y,
         22.0,
-        COLOR_PRIMARY
+        theme.primary
     )?;
     
-    // Draw a separator line
-    let line_rect = Rect::new(
-        x,
-        y + 30.0,
-        220.0,
-        2.0
-    );
-    
-    let line = MeshBuilder::new()
-        .rectangle(
-            DrawMode::fill(),
-            line_rect,
-            Color::new(0.8, 0.8, 0.8, 0.8)
-        )?
-        .build(ctx)?;
-    
-    graphics::draw(ctx, &line, DrawParam::default())?;
-    
-    // Draw past round results (win/loss streak)
-    let mut y_offset = y + 50.0;
+    draw_button_with_text(
+        ctx,
+        theme,
+        button_rect(state, ButtonId::SellAll),
+        theme.gold,
+        "Sell All",
+        14.0,
+        button_state(state, ButtonId::SellAll)
+    )?;
+    draw_button_extras(state, ctx, ButtonId::SellAll)?;
     
-    // We'll use the current_round to simulate some past results
-    // In the real implementation, you would track this in the game state
-    for round in 1..state.current_round {
-        // For demo purposes, alternate wins and losses
-        let win = round % 2 == 0;
-        
-        let result_rect = Rect::new(
-            x,
-            y_offset - 5.0,
-            220.0,
-            30.0
-        );
+    for ore in Ore::ALL {
+        let id = ButtonId::SellOre(ore);
+        let rect = button_rect(state, id);
+        let text_y = rect.y + 4.0;
         
-        let result_color = if win {
-            Color::new(0.8, 1.0, 0.8, 0.6) // Light green for win
+        // Rising prices in the accent colour, falling ones in the warning colour
+        let trend = market.trend[ore.index()];
+        let (arrow, price_color) = if trend > ore.base_price() * 0.01 {
+            ("+", theme.accent)
+        } else if trend < -ore.base_price() * 0.01 {
+            ("-", theme.secondary)
         } else {
-            Color::new(1.0, 0.8, 0.8, 0.6) // Light red for loss
+            ("", theme.text)
         };
         
-        let result_bg = MeshBuilder::new()
-            .rounded_rectangle(
-                DrawMode::fill(),
-                result_rect,
-                4.0,
-                result_color
-            )?
-            .build(ctx)?;
-        
-        graphics::draw(ctx, &result_bg, DrawParam::default())?;
-        
-        // Round number
-        let round_text = Text::new(
-            TextFragment::new(format!("Round {}", round))
-                .scale(16.0)
-                .color(COLOR_TEXT)
-        );
-        
-        graphics::draw(
-            ctx,
-            &round_text,
-            DrawParam::default().dest([x + 10.0, y_offset]),
-        )?;
+        let columns = [
+            (ore.name().to_string(), x, theme.text),
+            (format!("{:.0}", miner.sellable(ore)), x + 56.0, theme.text),
+            (format!("{:.1}g{}", market.price(ore), arrow), x + 88.0, price_color),
+        ];
         
-        // Result text
-        let result_text = Text::new(
-            TextFragment::new(if win { "WIN" } else { "LOSS" })
-                .scale(16.0)
-                .color(if win { COLOR_ACCENT } else { COLOR_SECONDARY })
-        );
+        for (value, column_x, color) in columns {
+            let value_text = Text::new(
+                TextFragment::new(value)
+                    .scale(15.0)
+                    .color(color)
+            );
+            
+            graphics::draw(
+                ctx,
+                &value_text,
+                DrawParam::default().dest([column_x, text_y]),
+            )?;
+        }
         
-        graphics::draw(
+        draw_button_with_text(
             ctx,
-            &result_text,
-            DrawParam::default().dest([x + 150.0, y_offset]),
+            theme,
+            rect,
+            theme.accent,
+            "Sell",
+            14.0,
+            button_state(state, id)
         )?;
-        
-        y_offset += 35.0;
+        draw_button_extras(state, ctx, id)?;
     }
     
     Ok(())
 }
 
 fn draw_contribute_option(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
     // Contribution panel - extend height to match the opponents panel
-    let contribute_rect = Rect::new(WINDOW_WIDTH - 260.0, 80.0, 250.0, 510.0);
-    draw_panel(ctx, contribute_rect, COLOR_PANEL, 3.0)?;
+    let contribute_rect = Rect::new(state.layout.width - 260.0, 80.0, 250.0, state.layout.height - 90.0);
+    draw_panel(ctx, theme, contribute_rect, theme.panel, 3.0)?;
     
     // Panel header
     draw_header_text(
         ctx,
+        theme,
         "Donate Gold",
-        WINDOW_WIDTH - 240.0,
+        state.layout.width - 240.0,
         90.0,
         22.0,
-        COLOR_PRIMARY
+        theme.primary
     )?;
     
-    // Donation explanation
+    // How this match's auction format treats donations; the top donors take the least damage in all of them
     let explanation_text = Text::new(
-        TextFragment::new("Donate gold to avoid taking damage at the end of each round.")
-            .scale(16.0)
-            .color(COLOR_TEXT)
+        TextFragment::new(wrap_words(state.game.rules.auction.explanation(), 36))
+            .scale(12.0)
+            .color(theme.text)
     );
     
     graphics::draw(
         ctx,
         &explanation_text,
-        DrawParam::default().dest([WINDOW_WIDTH - 240.0, 120.0]),
+        DrawParam::default().dest([state.layout.width - 240.0, 116.0]),
     )?;
     
     // Draw current donation
     let donated_text = Text::new(
-        TextFragment::new(format!("Current donation: {:.0}g", state.player.donated_gold))
-            .scale(18.0)
-            .color(COLOR_GOLD)
+        TextFragment::new(format!("Current donation: {:.0}g", state.active_miner().donated_gold))
+            .scale(16.0)
+            .color(theme.gold)
     );
     
     graphics::draw(
         ctx,
         &donated_text,
-        DrawParam::default().dest([WINDOW_WIDTH - 240.0, 150.0]),
+        DrawParam::default().dest([state.layout.width - 240.0, 160.0]),
     )?;
 
-    // Draw contribution amount buttons
-    let contribution_amounts = [10.0, 50.0, 100.0, 500.0, 1000.0];
-    let mut y_offset = 190.0;
-    
     // Draw numeric contribution options
-    for amount in &contribution_amounts {
-        let button_rect = Rect::new(WINDOW_WIDTH - 240.0, y_offset, 220.0, 30.0);
-        
-        let button_color = if state.player.gold >= *amount {
-            COLOR_ACCENT
-        } else {
-            COLOR_DISABLED
-        };
-        
-        let button_hover = false; // In a real game, check if mouse is over button
+    for (i, amount) in CONTRIBUTION_AMOUNTS.iter().enumerate() {
+        let id = ButtonId::Donate(i);
         
         // Use helper function for button with text
         draw_button_with_text(
             ctx,
-            button_rect,
-            button_color,
-            &format!("Donate {:.0}g", amount),
-            16.0,
-            button_hover
+            theme,
+            button_rect(state, id),
+            theme.accent,
+            &format!("{:.0}", amount),
+            14.0,
+            button_state(state, id)
         )?;
+        draw_button_extras(state, ctx, id)?;
+    }
+    
+    // Draw percentage options
+    for (i, percentage) in DONATION_PERCENTAGES.iter().enumerate() {
+        let id = ButtonId::DonatePercent(i);
         
-        y_offset += 40.0;
+        draw_button_with_text(
+            ctx,
+            theme,
+            button_rect(state, id),
+            theme.accent,
+            &format!("{:.0}%", percentage * 100.0),
+            16.0,
+            button_state(state, id)
+        )?;
+        draw_button_extras(state, ctx, id)?;
     }
     
-    // Draw "All" option
-    let all_button_rect = Rect::new(WINDOW_WIDTH - 240.0, y_offset, 220.0, 30.0);
-    let all_button_color = if state.player.gold > 0.0 { 
-        COLOR_GOLD
-    } else { 
-        COLOR_DISABLED
-    };
+    // Draw custom amount slider and field
+    draw_donation_slider(state, ctx)?;
+    draw_donation_field(state, ctx)?;
+    
+    draw_button_with_text(
+        ctx,
+        theme,
+        button_rect(state, ButtonId::DonateCustom),
+        theme.primary,
+        "Donate",
+        16.0,
+        button_state(state, ButtonId::DonateCustom)
+    )?;
+    draw_button_extras(state, ctx, ButtonId::DonateCustom)?;
     
-    let all_button_hover = false; // In a real game, check if mouse is over button
+    // Draw "All" option
+    let all_button_rect = button_rect(state, ButtonId::DonateAll);
     
     // Use helper function for button with text
     draw_button_with_text(
         ctx,
+        theme,
         all_button_rect,
-        all_button_color,
-        &format!("Donate All ({:.0}g)", state.player.gold),
+        theme.gold,
+        &format!("Donate All ({:.0}g)", state.active_miner().gold),
         16.0,
-        all_button_hover
+        button_state(state, ButtonId::DonateAll)
     )?;
+    draw_button_extras(state, ctx, ButtonId::DonateAll)?;
     
-    // Add win/loss tracker section
-    draw_win_loss_tracker(state, ctx, WINDOW_WIDTH - 240.0, y_offset + 80.0)?;
+    draw_market(state, ctx, state.layout.width - 240.0, all_button_rect.bottom() + 18.0)?;
+
+    Ok(())
+}
 
+
+fn round_end_panel_rect(state: &MainState) -> Rect {
+    let result_count = state.game.round_results.as_ref().map_or(0, |results| results.len());
+    let panel_height = (result_count as f32 * 40.0) + 120.0;
+    
+    Rect::new(
+        state.layout.width / 2.0 - 250.0,
+        state.layout.height / 2.0 - panel_height / 2.0,
+        500.0,
+        panel_height
+    )
+}
+fn draw_donation_slider(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
+    let slider_rect = button_rect(state, ButtonId::DonationSlider);
+    let track_rect = Rect::new(slider_rect.x, slider_rect.y + 8.0, slider_rect.w, 8.0);
+    let fraction = state.custom_donation_fraction();
+    
+    draw_progress_bar(ctx, theme, track_rect, fraction, theme.gold)?;
+    
+    // Handle
+    let handle_color = match button_state(state, ButtonId::DonationSlider) {
+        ButtonState::Pressed => mix_colors(theme.primary, Color::BLACK, 0.15),
+        ButtonState::Hovered => mix_colors(theme.primary, Color::WHITE, 0.2),
+        _ => theme.primary,
+    };
+    
+    let handle = MeshBuilder::new()
+        .circle(
+            DrawMode::fill(),
+            [track_rect.x + track_rect.w * fraction, track_rect.y + track_rect.h / 2.0],
+            9.0,
+            0.1,
+            handle_color
+        )?
+        .build(ctx)?;
+    
+    graphics::draw(ctx, &handle, DrawParam::default())?;
+    draw_button_extras(state, ctx, ButtonId::DonationSlider)?;
+    
     Ok(())
 }
 
+fn draw_donation_field(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
+    let field_rect = button_rect(state, ButtonId::DonationField);
+    let focused = state.focused_button == Some(ButtonId::DonationField);
+    let too_much = state.custom_donation().is_some_and(|amount| amount > state.active_miner().gold);
+    
+    let field = MeshBuilder::new()
+        .rounded_rectangle(
+            DrawMode::fill(),
+            field_rect,
+            4.0,
+            theme.field
+        )?
+        .rounded_rectangle(
+            DrawMode::stroke(if focused { 2.0 } else { 1.0 }),
+            field_rect,
+            4.0,
+            if too_much { theme.secondary } else if focused { theme.primary } else { theme.disabled }
+        )?
+        .build(ctx)?;
+    
+    graphics::draw(ctx, &field, DrawParam::default())?;
+    
+    // Typed amount with a caret while focused, or a placeholder
+    let (field_text, text_color) = if state.donation_input.is_empty() && !focused {
+        ("Amount...".to_string(), theme.disabled)
+    } else if focused {
+        (format!("{}g|", state.donation_input), theme.text)
+    } else {
+        (format!("{}g", state.donation_input), theme.text)
+    };
+    
+    let amount_text = Text::new(
+        TextFragment::new(field_text)
+            .scale(16.0)
+            .color(if too_much { theme.secondary } else { text_color })
+    );
+    
+    graphics::draw(
+        ctx,
+        &amount_text,
+        DrawParam::default().dest([field_rect.x + 8.0, field_rect.y + 7.0]),
+    )?;
+    
+    if too_much {
+        let warning_text = Text::new(
+            TextFragment::new("Not enough gold")
+                .scale(14.0)
+                .color(theme.secondary)
+        );
+        
+        graphics::draw(
+            ctx,
+            &warning_text,
+            DrawParam::default().dest([field_rect.x, field_rect.bottom() + 6.0]),
+        )?;
+    }
+    
+    draw_button_extras(state, ctx, ButtonId::DonationField)?;
+    
+    Ok(())
+}
 
 pub fn draw_round_end_ui(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
     // Clear with the background color
-    graphics::clear(ctx, COLOR_BACKGROUND);
+    graphics::clear(ctx, theme.background);
     
-    if let Some(results) = &state.round_results {
+    if let Some(results) = &state.game.round_results {
         // Main panel
-        let panel_height = (results.len() as f32 * 40.0) + 120.0;
-        let panel_rect = Rect::new(
-            WINDOW_WIDTH / 2.0 - 250.0,
-            WINDOW_HEIGHT / 2.0 - panel_height / 2.0,
-            500.0,
-            panel_height
-        );
+        let panel_rect = round_end_panel_rect(state);
         
-        draw_panel(ctx, panel_rect, COLOR_PANEL, 5.0)?;
+        draw_panel(ctx, theme, panel_rect, theme.panel, 5.0)?;
         
         // Draw round results header
         draw_header_text(
             ctx,
-            &format!("Round {} Results", state.current_round),
-            WINDOW_WIDTH / 2.0 - 120.0,
+            theme,
+            &format!("Round {} Results", state.game.current_round),
+            state.layout.width / 2.0 - 120.0,
             panel_rect.y + 20.0,
             28.0,
-            COLOR_PRIMARY
+            theme.primary
         )?;
         
         let mut y_offset = panel_rect.y + 70.0;
         
         // Table headers
         let headers = [
-            ("Rank", 50.0, COLOR_TEXT),
-            ("Player", 150.0, COLOR_TEXT),
-            ("Donated", 150.0, COLOR_GOLD),
-            ("Damage", 120.0, COLOR_SECONDARY)
+            ("Rank", 50.0, theme.text),
+            ("Player", 150.0, theme.text),
+            ("Donated", 150.0, theme.gold),
+            ("Damage", 120.0, theme.secondary)
         ];
         
         let mut x_offset = panel_rect.x + 20.0;
@@ -959,6 +1965,10 @@ pub fn draw_round_end_ui(state: &MainState, ctx: &mut Context) -> GameResult {
         
         // Draw results rows
         for (position, (miner_index, donated_gold)) in results.iter().enumerate() {
+            let miner = &state.game.miners[*miner_index];
+            // Knocked out in an earlier round, so not ranked in this one
+            let out_before = miner.eliminated_in.filter(|round| *round < state.game.current_round);
+            
             // Row background - alternating colors
             let row_rect = Rect::new(
                 panel_rect.x + 10.0,
@@ -967,10 +1977,12 @@ pub fn draw_round_end_ui(state: &MainState, ctx: &mut Context) -> GameResult {
                 30.0
             );
             
-            let row_color = if position % 2 == 0 {
-                Color::new(0.95, 0.95, 0.95, 0.7) // Slightly darker for even rows
+            let row_color = if out_before.is_some() {
+                theme.loss_row
+            } else if position % 2 == 0 {
+                theme.row_even // Slightly darker for even rows
             } else {
-                Color::new(1.0, 1.0, 1.0, 0.5) // Slightly lighter for odd rows
+                theme.row_odd // Slightly lighter for odd rows
             };
             
             let row = MeshBuilder::new()
@@ -986,14 +1998,15 @@ pub fn draw_round_end_ui(state: &MainState, ctx: &mut Context) -> GameResult {
             
             // Position/rank
             let position_color = match position {
-                0 => Color::new(0.9, 0.8, 0.0, 1.0), // Gold
-                1 => Color::new(0.8, 0.8, 0.8, 1.0), // Silver
-                2 => Color::new(0.8, 0.5, 0.2, 1.0), // Bronze
-                _ => COLOR_TEXT,                      // Default
+                0 => theme.medal_gold,
+                1 => theme.medal_silver,
+                2 => theme.medal_bronze,
+                _ => theme.text,
             };
             
+            let rank = if out_before.is_some() { "-".to_string() } else { format!("#{}", position + 1) };
             let position_text = Text::new(
-                TextFragment::new(format!("#{}", position + 1))
+                TextFragment::new(rank)
                     .scale(18.0)
                     .color(position_color)
             );
@@ -1005,16 +2018,10 @@ pub fn draw_round_end_ui(state: &MainState, ctx: &mut Context) -> GameResult {
             )?;
             
             // Player name
-            let miner_name = if *miner_index == 0 {
-                "You (Player)".to_string()
-            } else {
-                format!("Bot #{}", miner_index)
-            };
-            
             let name_text = Text::new(
-                TextFragment::new(miner_name)
+                TextFragment::new(miner.name.as_str())
                     .scale(18.0)
-                    .color(COLOR_TEXT)
+                    .color(if miner.alive { theme.text } else { theme.disabled })
             );
             
             graphics::draw(
@@ -1025,9 +2032,9 @@ pub fn draw_round_end_ui(state: &MainState, ctx: &mut Context) -> GameResult {
             
             // Donated gold
             let gold_text = Text::new(
-                TextFragment::new(format!("{:.0}g", donated_gold))
+                TextFragment::new(if out_before.is_some() { "-".to_string() } else { format!("{:.0}g", donated_gold) })
                     .scale(18.0)
-                    .color(COLOR_GOLD)
+                    .color(theme.gold)
             );
             
             graphics::draw(
@@ -1036,13 +2043,18 @@ pub fn draw_round_end_ui(state: &MainState, ctx: &mut Context) -> GameResult {
                 DrawParam::default().dest([panel_rect.x + 220.0, y_offset]),
             )?;
             
-            // Damage taken
+            // Damage taken, or when the miner dropped out
             let damage = position as i32;
+            let damage_label = match out_before {
+                Some(round) => format!("Out in round {}", round),
+                None if !miner.alive => format!("-{} (out)", damage),
+                None => format!("-{}", damage),
+            };
             
             let damage_text = Text::new(
-                TextFragment::new(format!("-{}", damage))
+                TextFragment::new(damage_label)
                     .scale(18.0)
-                    .color(COLOR_SECONDARY)
+                    .color(theme.secondary)
             );
             
             graphics::draw(
@@ -1055,39 +2067,63 @@ pub fn draw_round_end_ui(state: &MainState, ctx: &mut Context) -> GameResult {
         }
         
         // Draw continue button
-        let button_rect = Rect::new(
-            WINDOW_WIDTH / 2.0 - 100.0,
-            panel_rect.y + panel_height - 50.0,
-            200.0,
-            40.0
-        );
-        
+        let continue_label = if state.game.all_humans_out() {
+            format!("Keep Watching ({}x)", ELIMINATED_SPEEDUP)
+        } else {
+            "Continue to Next Round".to_string()
+        };
         draw_button_with_text(
             ctx,
-            button_rect,
-            COLOR_ACCENT,
-            "Continue to Next Round",
+            theme,
+            button_rect(state, ButtonId::Continue),
+            theme.accent,
+            &continue_label,
             18.0,
-            false // Not hovered
+            button_state(state, ButtonId::Continue)
         )?;
+        draw_button_extras(state, ctx, ButtonId::Continue)?;
+        
+        if can_skip_to_results(state) {
+            draw_button_with_text(
+                ctx,
+                theme,
+                button_rect(state, ButtonId::SkipToResults),
+                theme.primary,
+                "Skip to Results",
+                18.0,
+                button_state(state, ButtonId::SkipToResults)
+            )?;
+            draw_button_extras(state, ctx, ButtonId::SkipToResults)?;
+        }
     }
     
+    draw_theme_button(state, ctx)?;
+    
     Ok(())
 }
 
+// Player stats on the left, everyone's standings on the right
+fn game_over_panel_rect(state: &MainState) -> Rect {
+    let standings_height = 215.0 + state.game.miners.len() as f32 * FINAL_STANDINGS_ROW_HEIGHT;
+    let panel_height = standings_height.max(400.0);
+    
+    Rect::new(
+        state.layout.width / 2.0 - 380.0,
+        state.layout.height / 2.0 - panel_height / 2.0,
+        760.0,
+        panel_height
+    )
+}
+
 pub fn draw_game_over_ui(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
     // Clear with the background color
-    graphics::clear(ctx, COLOR_BACKGROUND);
+    graphics::clear(ctx, theme.background);
     
     // Create a fancy game over panel
-    let panel_rect = Rect::new(
-        WINDOW_WIDTH / 2.0 - 250.0,
-        WINDOW_HEIGHT / 2.0 - 200.0, // Make panel taller
-        500.0,
-        400.0 // Increased height
-    );
+    let panel_rect = game_over_panel_rect(state);
     
-    draw_panel(ctx, panel_rect, COLOR_PANEL, 8.0)?; // Larger shadow for emphasis
+    draw_panel(ctx, theme, panel_rect, theme.panel, 8.0)?; // Larger shadow for emphasis
     
     // Add a decorative header bar
     let header_bar_rect = Rect::new(
@@ -1097,10 +2133,10 @@ pub fn draw_game_over_ui(state: &MainState, ctx: &mut Context) -> GameResult {
         50.0
     );
     
-    let header_bar_color = if state.player.alive {
-        COLOR_ACCENT // Green for victory
+    let header_bar_color = if state.active_miner().alive {
+        theme.accent // Green for victory
     } else {
-        COLOR_SECONDARY // Red for defeat
+        theme.secondary // Red for defeat
     };
     
     let header_bar = MeshBuilder::new()
@@ -1115,19 +2151,21 @@ pub fn draw_game_over_ui(state: &MainState, ctx: &mut Context) -> GameResult {
     graphics::draw(ctx, &header_bar, DrawParam::default())?;
     
     // Draw game over text
-    let game_over_message = if state.player.alive {
-        "Game Complete - You Survived!"
-    } else {
-        "Game Over - You Died!"
+    let game_over_message = match (state.game.rules.is_hot_seat(), state.active_miner().alive) {
+        (false, true) => "Game Complete - You Survived!".to_string(),
+        (false, false) => "Game Over - You Died!".to_string(),
+        (true, true) => format!("Game Complete - {} Survived!", state.active_miner().name),
+        (true, false) => format!("Game Over - {} Died!", state.active_miner().name),
     };
     
     draw_header_text(
         ctx,
-        game_over_message,
-        WINDOW_WIDTH / 2.0 - 180.0,
+        theme,
+        &game_over_message,
+        state.layout.width / 2.0 - 180.0,
         panel_rect.y + 10.0,
         28.0,
-        COLOR_TEXT_LIGHT
+        theme.text_on_dark
     )?;
     
     // Draw a separator line
@@ -1142,7 +2180,7 @@ pub fn draw_game_over_ui(state: &MainState, ctx: &mut Context) -> GameResult {
         .rectangle(
             DrawMode::fill(),
             line_rect,
-            Color::new(0.8, 0.8, 0.8, 0.8)
+            theme.separator
         )?
         .build(ctx)?;
     
@@ -1150,102 +2188,104 @@ pub fn draw_game_over_ui(state: &MainState, ctx: &mut Context) -> GameResult {
     
     // Game stats
     let stats_text = Text::new(
-        TextFragment::new(format!("Rounds Completed: {}/{}", 
-            if state.player.alive { state.current_round } else { state.current_round - 1 }, 
-            MAX_ROUNDS
-        ))
+        TextFragment::new(match state.active_miner().eliminated_in.map_or(state.game.current_round, |round| round - 1) {
+            // A last-miner-standing match has no round limit to count towards
+            rounds if state.game.rules.last_standing => format!("Rounds Completed: {}", rounds),
+            rounds => format!("Rounds Completed: {}/{}", rounds, state.game.rules.max_rounds),
+        })
         .scale(20.0)
-        .color(COLOR_PRIMARY)
+        .color(theme.primary)
     );
     
     graphics::draw(
         ctx,
         &stats_text,
-        DrawParam::default().dest([panel_rect.x + 100.0, panel_rect.y + 90.0]),
+        DrawParam::default().dest([panel_rect.x + 40.0, panel_rect.y + 90.0]),
     )?;
     
     // Add player health
     let health_label = Text::new(
         TextFragment::new("Final Health: ")
             .scale(20.0)
-            .color(COLOR_TEXT)
+            .color(theme.text)
     );
     
     graphics::draw(
         ctx,
         &health_label,
-        DrawParam::default().dest([panel_rect.x + 100.0, panel_rect.y + 130.0]),
+        DrawParam::default().dest([panel_rect.x + 40.0, panel_rect.y + 130.0]),
     )?;
     
     let health_value = Text::new(
-        TextFragment::new(format!("{}", state.player.health))
+        TextFragment::new(format!("{}", state.active_miner().health))
             .scale(20.0)
-            .color(if state.player.health > 5 { COLOR_ACCENT } else { COLOR_SECONDARY })
+            .color(if state.active_miner().health > 5 { theme.accent } else { theme.secondary })
     );
     
     graphics::draw(
         ctx,
         &health_value,
-        DrawParam::default().dest([panel_rect.x + 260.0, panel_rect.y + 130.0]),
+        DrawParam::default().dest([panel_rect.x + 200.0, panel_rect.y + 130.0]),
     )?;
     
     // Gold collected stat
     let gold_label = Text::new(
         TextFragment::new("Gold Collected: ")
             .scale(20.0)
-            .color(COLOR_TEXT)
+            .color(theme.text)
     );
     
     graphics::draw(
         ctx,
         &gold_label,
-        DrawParam::default().dest([panel_rect.x + 100.0, panel_rect.y + 170.0]),
+        DrawParam::default().dest([panel_rect.x + 40.0, panel_rect.y + 170.0]),
     )?;
     
     let gold_value = Text::new(
-        TextFragment::new(format!("{:.0}g", state.player.gold + state.player.donated_gold))
+        TextFragment::new(format!("{:.0}g", state.active_miner().gold + state.active_miner().donated_gold))
             .scale(20.0)
-            .color(COLOR_GOLD)
+            .color(theme.gold)
     );
     
     graphics::draw(
         ctx,
         &gold_value,
-        DrawParam::default().dest([panel_rect.x + 260.0, panel_rect.y + 170.0]),
+        DrawParam::default().dest([panel_rect.x + 200.0, panel_rect.y + 170.0]),
     )?;
     
     // Add round wins count
-    let wins_count = state.past_results.iter().filter(|&&win| win).count();
+    let past_results = state.game.past_results(state.active_seat);
+    let wins_count = past_results.iter().filter(|&&win| win).count();
     
     let wins_label = Text::new(
         TextFragment::new("Rounds Won: ")
             .scale(20.0)
-            .color(COLOR_TEXT)
+            .color(theme.text)
     );
     
     graphics::draw(
         ctx,
         &wins_label,
-        DrawParam::default().dest([panel_rect.x + 100.0, panel_rect.y + 210.0]),
+        DrawParam::default().dest([panel_rect.x + 40.0, panel_rect.y + 210.0]),
     )?;
     
     let wins_value = Text::new(
-        TextFragment::new(format!("{}/{}", wins_count, state.past_results.len()))
+        TextFragment::new(format!("{}/{}", wins_count, past_results.len()))
             .scale(20.0)
-            .color(COLOR_ACCENT)
+            .color(theme.accent)
     );
     
     graphics::draw(
         ctx,
         &wins_value,
-        DrawParam::default().dest([panel_rect.x + 260.0, panel_rect.y + 210.0]),
+        DrawParam::default().dest([panel_rect.x + 200.0, panel_rect.y + 210.0]),
     )?;
     
     // Add win streak info
     let mut current_streak = 0;
     let mut best_streak = 0;
     
-    for &win in state.past_results.iter().rev() {
+    for &win in past_results.iter().rev() {
         if win {
             current_streak += 1;
             best_streak = best_streak.max(current_streak);
@@ -1257,43 +2297,1073 @@ pub fn draw_game_over_ui(state: &MainState, ctx: &mut Context) -> GameResult {
     let streak_label = Text::new(
         TextFragment::new("Best Win Streak: ")
             .scale(20.0)
-            .color(COLOR_TEXT)
+            .color(theme.text)
     );
     
     graphics::draw(
         ctx,
         &streak_label,
-        DrawParam::default().dest([panel_rect.x + 100.0, panel_rect.y + 250.0]),
+        DrawParam::default().dest([panel_rect.x + 40.0, panel_rect.y + 250.0]),
     )?;
     
     let streak_value = Text::new(
         TextFragment::new(format!("{}", best_streak))
             .scale(20.0)
-            .color(COLOR_ACCENT)
+            .color(theme.accent)
     );
     
     graphics::draw(
         ctx,
         &streak_value,
-        DrawParam::default().dest([panel_rect.x + 260.0, panel_rect.y + 250.0]),
+        DrawParam::default().dest([panel_rect.x + 200.0, panel_rect.y + 250.0]),
     )?;
     
-    // Draw restart button
-    let restart_rect = Rect::new(
-        WINDOW_WIDTH / 2.0 - 75.0,
-        panel_rect.y + 330.0, // Adjusted y position
-        150.0,
-        40.0
+    // Where the player finished, once the rest of the match has been played out
+    let placement_label = Text::new(
+        TextFragment::new("Final Placement: ")
+            .scale(20.0)
+            .color(theme.text)
     );
     
+    graphics::draw(
+        ctx,
+        &placement_label,
+        DrawParam::default().dest([panel_rect.x + 40.0, panel_rect.y + 290.0]),
+    )?;
+    
+    let placement_value = Text::new(
+        TextFragment::new(format!("#{} of {}", state.game.placement(state.active_seat), state.game.miners.len()))
+            .scale(20.0)
+            .color(theme.primary)
+    );
+    
+    graphics::draw(
+        ctx,
+        &placement_value,
+        DrawParam::default().dest([panel_rect.x + 200.0, panel_rect.y + 290.0]),
+    )?;
+    
+    draw_final_standings(state, ctx, panel_rect)?;
+    
+    // Draw restart button
     draw_button_with_text(
         ctx,
-        restart_rect,
-        COLOR_PRIMARY,
+        theme,
+        button_rect(state, ButtonId::Restart),
+        theme.primary,
         "Restart Game",
         20.0,
-        false // Not hovered
+        button_state(state, ButtonId::Restart)
+    )?;
+    draw_button_extras(state, ctx, ButtonId::Restart)?;
+    
+    draw_theme_button(state, ctx)?;
+
+    Ok(())
+}
+
+// Every miner from winner to first out, on the right half of the game over panel
+fn draw_final_standings(state: &MainState, ctx: &mut Context, panel_rect: Rect) -> GameResult {
+    let theme = &state.theme;
+    let left = panel_rect.x + 390.0;
+    
+    draw_header_text(ctx, theme, "Final Standings", left, panel_rect.y + 85.0, 20.0, theme.primary)?;
+    
+    let columns = [
+        ("#", 0.0, theme.text),
+        ("Miner", 35.0, theme.text),
+        ("Out", 175.0, theme.secondary),
+        ("HP", 235.0, theme.text),
+        ("Donated", 280.0, theme.gold),
+    ];
+    
+    for (label, x, color) in columns {
+        let header_text = Text::new(
+            TextFragment::new(label)
+                .scale(16.0)
+                .color(color)
+        );
+        
+        graphics::draw(
+            ctx,
+            &header_text,
+            DrawParam::default().dest([left + x, panel_rect.y + 120.0]),
+        )?;
+    }
+    
+    for (place, miner_index) in state.game.standings().into_iter().enumerate() {
+        let miner = &state.game.miners[miner_index];
+        let y = panel_rect.y + 145.0 + place as f32 * FINAL_STANDINGS_ROW_HEIGHT;
+        
+        let row_color = if miner_index == state.active_seat && !miner.is_bot() {
+            theme.win_row
+        } else if place % 2 == 0 {
+            theme.row_even
+        } else {
+            theme.row_odd
+        };
+        
+        let row = MeshBuilder::new()
+            .rounded_rectangle(
+                DrawMode::fill(),
+                Rect::new(left - 10.0, y - 3.0, panel_rect.right() - left - 10.0, FINAL_STANDINGS_ROW_HEIGHT - 4.0),
+                4.0,
+                row_color
+            )?
+            .build(ctx)?;
+        
+        graphics::draw(ctx, &row, DrawParam::default())?;
+        
+        let place_color = match place {
+            0 => theme.medal_gold,
+            1 => theme.medal_silver,
+            2 => theme.medal_bronze,
+            _ => theme.text,
+        };
+        let name_color = if miner.alive {
+            miner.color.map_or(theme.text, |color| PLAYER_COLORS[color])
+        } else {
+            theme.disabled
+        };
+        
+        let values = [
+            (format!("{}", place + 1), 0.0, place_color),
+            (miner.name.clone(), 35.0, name_color),
+            (miner.eliminated_in.map_or("-".to_string(), |round| format!("R{}", round)), 175.0, theme.secondary),
+            (miner.health.to_string(), 235.0, theme.text),
+            (format!("{:.0}g", miner.total_donated), 280.0, theme.gold),
+        ];
+        
+        for (value, x, color) in values {
+            let value_text = Text::new(
+                TextFragment::new(value)
+                    .scale(16.0)
+                    .color(color)
+            );
+            
+            graphics::draw(
+                ctx,
+                &value_text,
+                DrawParam::default().dest([left + x, y]),
+            )?;
+        }
+    }
+    
+    Ok(())
+}
+
+pub fn draw_lobby_ui(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
+    let lobby = match &state.lobby {
+        Some(lobby) => lobby,
+        None => return Ok(()),
+    };
+    let half_width = state.layout.width / 2.0;
+    
+    // Header
+    let header_rect = Rect::new(10.0, 10.0, state.layout.width - 20.0, 60.0);
+    draw_panel(ctx, theme, header_rect, theme.panel, 3.0)?;
+    
+    draw_header_text(ctx, theme, "Lobby", 30.0, 25.0, 24.0, theme.primary)?;
+    
+    let status = if state.is_lobby_host() {
+        "You are the host - start when everyone is ready"
+    } else if state.lobby_player().is_some() {
+        "Waiting for the host to start the match"
+    } else {
+        "The lobby is full - you are watching"
+    };
+    
+    let status_text = Text::new(
+        TextFragment::new(status)
+            .scale(16.0)
+            .color(theme.text)
+    );
+    
+    graphics::draw(
+        ctx,
+        &status_text,
+        DrawParam::default().dest([130.0, 32.0]),
+    )?;
+    
+    // Players panel
+    let players_rect = Rect::new(10.0, 80.0, half_width - 15.0, 300.0);
+    draw_panel(ctx, theme, players_rect, theme.panel, 3.0)?;
+    
+    draw_header_text(
+        ctx,
+        theme,
+        &format!("Players ({}/{})", lobby.players.len(), MAX_HUMANS),
+        30.0,
+        90.0,
+        22.0,
+        theme.primary
+    )?;
+    
+    for (i, player) in lobby.players.iter().enumerate() {
+        let row_rect = Rect::new(20.0, 130.0 + i as f32 * 55.0, players_rect.w - 20.0, 45.0);
+        let row_color = if i % 2 == 0 { theme.row_even } else { theme.row_odd };
+        let player_color = PLAYER_COLORS[player.color % PLAYER_COLOR_COUNT];
+        
+        let row = MeshBuilder::new()
+            .rounded_rectangle(
+                DrawMode::fill(),
+                row_rect,
+                4.0,
+                row_color
+            )?
+            .circle(
+                DrawMode::fill(),
+                [row_rect.x + 20.0, row_rect.y + row_rect.h / 2.0],
+                10.0,
+                0.1,
+                player_color
+            )?
+            .build(ctx)?;
+        
+        graphics::draw(ctx, &row, DrawParam::default())?;
+        
+        // The host is always the first player
+        let label = if i == 0 {
+            format!("{} (host)", player.name)
+        } else {
+            player.name.clone()
+        };
+        
+        let name_text = Text::new(
+            TextFragment::new(label)
+                .scale(18.0)
+                .color(player_color)
+        );
+        
+        graphics::draw(
+            ctx,
+            &name_text,
+            DrawParam::default().dest([row_rect.x + 40.0, row_rect.y + 13.0]),
+        )?;
+        
+        let (ready_label, ready_color) = if player.ready {
+            ("Ready", theme.accent)
+        } else {
+            ("Not ready", theme.secondary)
+        };
+        
+        let ready_text = Text::new(
+            TextFragment::new(ready_label)
+                .scale(16.0)
+                .color(ready_color)
+        );
+        
+        graphics::draw(
+            ctx,
+            &ready_text,
+            DrawParam::default().dest([row_rect.right() - 90.0, row_rect.y + 14.0]),
+        )?;
+    }
+    
+    // Rules panel; only the host gets the buttons to change them
+    let rules_rect = Rect::new(half_width + 5.0, 80.0, half_width - 15.0, 300.0);
+    draw_panel(ctx, theme, rules_rect, theme.panel, 3.0)?;
+    
+    draw_header_text(ctx, theme, "Match Rules", rules_rect.x + 20.0, 90.0, 22.0, theme.primary)?;
+    
+    for field in RuleField::ALL {
+        let down_rect = lobby_button_rect(state, LobbyButton::RuleDown(field));
+        
+        draw_stat(
+            ctx,
+            theme,
+            &format!("{}: ", field.label()),
+            &lobby.rules.describe(field),
+            rules_rect.x + 20.0,
+            down_rect.y + 4.0,
+            theme.gold
+        )?;
+        
+        if state.is_lobby_host() {
+            for (id, label) in [(LobbyButton::RuleDown(field), "-"), (LobbyButton::RuleUp(field), "+")] {
+                let id = ButtonId::Lobby(id);
+                draw_button_with_text(
+                    ctx,
+                    theme,
+                    button_rect(state, id),
+                    theme.primary,
+                    label,
+                    20.0,
+                    button_state(state, id)
+                )?;
+            }
+        }
+    }
+    
+    // Own settings
+    let own_rect = Rect::new(10.0, 390.0, state.layout.width - 20.0, state.layout.height - 400.0);
+    draw_panel(ctx, theme, own_rect, theme.panel, 3.0)?;
+    
+    if let Some(player) = state.lobby_player() {
+        draw_header_text(ctx, theme, "You", 30.0, 400.0, 22.0, theme.primary)?;
+        
+        draw_name_field(state, ctx, &player.name)?;
+        
+        let color_id = ButtonId::Lobby(LobbyButton::Color);
+        draw_button_with_text(
+            ctx,
+            theme,
+            button_rect(state, color_id),
+            PLAYER_COLORS[player.color % PLAYER_COLOR_COUNT],
+            "Colour",
+            18.0,
+            button_state(state, color_id)
+        )?;
+        draw_button_extras(state, ctx, color_id)?;
+        
+        let ready_id = ButtonId::Lobby(LobbyButton::Ready);
+        draw_button_with_text(
+            ctx,
+            theme,
+            button_rect(state, ready_id),
+            if player.ready { theme.secondary } else { theme.accent },
+            if player.ready { "Not Ready" } else { "Ready" },
+            18.0,
+            button_state(state, ready_id)
+        )?;
+        draw_button_extras(state, ctx, ready_id)?;
+    }
+    
+    if state.is_lobby_host() {
+        let start_id = ButtonId::Lobby(LobbyButton::Start);
+        draw_button_with_text(
+            ctx,
+            theme,
+            button_rect(state, start_id),
+            theme.accent,
+            "Start Match",
+            18.0,
+            button_state(state, start_id)
+        )?;
+        draw_button_extras(state, ctx, start_id)?;
+    }
+    
+    if lobby.spectators > 0 {
+        let spectators_text = Text::new(
+            TextFragment::new(format!("{} watching", lobby.spectators))
+                .scale(16.0)
+                .color(theme.text)
+        );
+        
+        graphics::draw(
+            ctx,
+            &spectators_text,
+            DrawParam::default().dest([30.0, 500.0]),
+        )?;
+    }
+    
+    draw_theme_button(state, ctx)?;
+    
+    Ok(())
+}
+
+fn draw_name_field(state: &MainState, ctx: &mut Context, name: &str) -> GameResult {
+    let theme = &state.theme;
+    let id = ButtonId::Lobby(LobbyButton::Name);
+    let field_rect = button_rect(state, id);
+    let focused = state.focused_button == Some(id);
+    
+    let field = MeshBuilder::new()
+        .rounded_rectangle(
+            DrawMode::fill(),
+            field_rect,
+            4.0,
+            theme.field
+        )?
+        .rounded_rectangle(
+            DrawMode::stroke(if focused { 2.0 } else { 1.0 }),
+            field_rect,
+            4.0,
+            if focused { theme.primary } else { theme.disabled }
+        )?
+        .build(ctx)?;
+    
+    graphics::draw(ctx, &field, DrawParam::default())?;
+    
+    // The name being typed, with a caret, replaces the current one while editing
+    let field_text = if focused {
+        format!("{}|", state.name_input)
+    } else {
+        name.to_string()
+    };
+    
+    let name_text = Text::new(
+        TextFragment::new(field_text)
+            .scale(16.0)
+            .color(theme.text)
+    );
+    
+    graphics::draw(
+        ctx,
+        &name_text,
+        DrawParam::default().dest([field_rect.x + 8.0, field_rect.y + 8.0]),
     )?;
+    
+    draw_button_extras(state, ctx, id)?;
+    
+    Ok(())
+}
 
+// Every miner's row on the observer screen, squeezed together for large matches
+fn observer_row_rect(state: &MainState, row: usize) -> Rect {
+    let bottom = if state.game.game_state == GameState::GameOver {
+        state.layout.height - 80.0 // Room for the restart button
+    } else {
+        state.layout.height - 20.0
+    };
+    let spacing = ((bottom - 160.0) / state.game.miners.len().max(1) as f32).min(40.0);
+    
+    Rect::new(20.0, 160.0 + row as f32 * spacing, state.layout.width - 40.0, (spacing - 6.0).min(34.0))
+}
+
+// Read-only view of a whole match: every miner's gold, mining, health and live donations
+pub fn draw_observer_ui(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
+    let game = &state.game;
+    let game_over = game.game_state == GameState::GameOver;
+    
+    // Header with round and timer, as on the player screen
+    let header_rect = Rect::new(10.0, 10.0, state.layout.width - 20.0, 60.0);
+    draw_panel(ctx, theme, header_rect, theme.panel, 3.0)?;
+    
+    let title = if game_over {
+        "Match Over".to_string()
+    } else if game.time_scale() > 1 {
+        format!("{} - watching at {}x", game.round_label(), game.time_scale())
+    } else {
+        game.round_label()
+    };
+    draw_header_text(ctx, theme, &title, 30.0, 25.0, 24.0, theme.primary)?;
+    
+    // Turn-based rounds have no clock to show
+    if !game_over && !game.rules.turn_based {
+        let time_left = game.time_left();
+        let timer_progress = 1.0 - (time_left.as_secs_f32() / game.rules.round_duration().as_secs_f32());
+        draw_progress_bar(ctx, theme, Rect::new(190.0, 30.0, 260.0, 20.0), timer_progress, theme.secondary)?;
+        
+        let time_text = Text::new(
+            TextFragment::new(format!("{}s", time_left.as_secs()))
+                .scale(18.0)
+                .color(theme.text)
+        );
+        
+        graphics::draw(
+            ctx,
+            &time_text,
+            DrawParam::default().dest([460.0, 28.0]),
+        )?;
+    }
+    if !game_over {
+        draw_round_event(state, ctx, 505.0, 29.0)?;
+    }
+    
+    // Miner table
+    let table_rect = Rect::new(10.0, 80.0, state.layout.width - 20.0, state.layout.height - 90.0);
+    draw_panel(ctx, theme, table_rect, theme.panel, 3.0)?;
+    
+    let heading = if game_over { "Final Standings" } else { "Watching" };
+    draw_header_text(ctx, theme, heading, 30.0, 90.0, 22.0, theme.primary)?;
+    
+    let columns = [
+        ("Miner", 30.0, theme.text),
+        ("Health", 200.0, theme.text),
+        ("Gold", 350.0, theme.gold),
+        ("Speed", 440.0, theme.text),
+        ("Yield", 530.0, theme.text),
+        ("Donated", 610.0, theme.gold),
+    ];
+    
+    for (label, x, color) in columns {
+        let header_text = Text::new(
+            TextFragment::new(label)
+                .scale(16.0)
+                .color(color)
+        );
+        
+        graphics::draw(
+            ctx,
+            &header_text,
+            DrawParam::default().dest([x, 130.0]),
+        )?;
+    }
+    
+    // The biggest donor this round is the one to beat
+    let leader = game
+        .miners
+        .iter()
+        .enumerate()
+        .filter(|(_, miner)| miner.alive)
+        .filter_map(|(i, _)| game.observe(None, i).donation.filter(|donated| *donated > 0.0).map(|donated| (i, donated)))
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        .map(|(i, _)| i);
+    
+    // Once the match is over the rows are the final standings
+    let order: Vec<usize> = if game_over { game.standings() } else { (0..game.miners.len()).collect() };
+    
+    for (position, i) in order.into_iter().enumerate() {
+        let miner = &game.miners[i];
+        let row_rect = observer_row_rect(state, position);
+        let text_y = row_rect.y + (row_rect.h - 16.0) / 2.0;
+        
+        let row_color = if leader == Some(i) {
+            theme.win_row
+        } else if !miner.alive {
+            theme.loss_row
+        } else if position % 2 == 0 {
+            theme.row_even
+        } else {
+            theme.row_odd
+        };
+        
+        let row = MeshBuilder::new()
+            .rounded_rectangle(
+                DrawMode::fill(),
+                row_rect,
+                4.0,
+                row_color
+            )?
+            .build(ctx)?;
+        
+        graphics::draw(ctx, &row, DrawParam::default())?;
+        
+        let name_color = if miner.alive {
+            miner.color.map_or(theme.primary, |color| PLAYER_COLORS[color])
+        } else {
+            theme.disabled
+        };
+        
+        let name_text = Text::new(
+            TextFragment::new(miner.name.as_str())
+                .scale(16.0)
+                .color(name_color)
+        );
+        
+        graphics::draw(
+            ctx,
+            &name_text,
+            DrawParam::default().dest([30.0, text_y]),
+        )?;
+        
+        if !miner.alive {
+            let out_text = Text::new(
+                TextFragment::new("Eliminated")
+                    .scale(16.0)
+                    .color(theme.secondary)
+            );
+            
+            graphics::draw(
+                ctx,
+                &out_text,
+                DrawParam::default().dest([200.0, text_y]),
+            )?;
+            continue;
+        }
+        
+        let health_rect = Rect::new(200.0, text_y + 2.0, 100.0, 12.0);
+        let health_progress = miner.health as f32 / miner.max_health as f32;
+        draw_progress_bar(ctx, theme, health_rect, health_progress, theme.health_color(miner.health))?;
+        
+        // Spectators see what the players would, so the fog covers them too
+        let view = game.observe(None, i);
+        let speed = match view.stats {
+            Some(stats) => format!("{:.1}/s", stats.mining_speed),
+            None => format!("Tier {}", view.tier),
+        };
+        let values = [
+            (miner.health.to_string(), 305.0, theme.text),
+            (amount_label(view.gold), 350.0, theme.gold),
+            (speed, 440.0, theme.text),
+            (amount_label(view.stats.map(|stats| stats.gold_per_swing)), 530.0, theme.text),
+            (amount_label(view.donation), 610.0, theme.gold),
+        ];
+        
+        for (value, x, color) in values {
+            let value_text = Text::new(
+                TextFragment::new(value)
+                    .scale(16.0)
+                    .color(color)
+            );
+            
+            graphics::draw(
+                ctx,
+                &value_text,
+                DrawParam::default().dest([x, text_y]),
+            )?;
+        }
+    }
+    
+    if game_over && state.is_button_enabled(ButtonId::Restart) {
+        draw_button_with_text(
+            ctx,
+            theme,
+            button_rect(state, ButtonId::Restart),
+            theme.primary,
+            "Watch Again",
+            18.0,
+            button_state(state, ButtonId::Restart)
+        )?;
+        draw_button_extras(state, ctx, ButtonId::Restart)?;
+    }
+    
+    draw_theme_button(state, ctx)?;
+    
+    Ok(())
+}
+
+// Rows of the tech tree close up when a branch is longer than the shipped ones
+fn tech_tree_row_spacing(state: &MainState) -> f32 {
+    let tree = tech::tree();
+    let deepest = Branch::ALL.iter().map(|branch| tree.branch(*branch).count()).max().unwrap_or(1).max(1);
+    ((state.layout.height - 280.0) / deepest as f32).min(80.0)
+}
+
+fn tech_tree_column_width(state: &MainState) -> f32 {
+    (state.layout.width - 40.0) / Branch::ALL.len() as f32
+}
+
+// Branches are columns, nodes go down them in file order
+fn tech_node_rect(state: &MainState, node: usize) -> Rect {
+    let tree = tech::tree();
+    let branch = tree.nodes[node].branch;
+    let column = Branch::ALL.iter().position(|b| *b == branch).unwrap_or(0);
+    let row = tree.branch(branch).position(|(index, _)| index == node).unwrap_or(0);
+    let column_width = tech_tree_column_width(state);
+    let spacing = tech_tree_row_spacing(state);
+    
+    Rect::new(
+        20.0 + column as f32 * column_width + (column_width - TECH_NODE_WIDTH) / 2.0,
+        130.0 + row as f32 * spacing,
+        TECH_NODE_WIDTH,
+        TECH_NODE_HEIGHT.min(spacing - 16.0),
+    )
+}
+
+// Full-screen view of the research on offer; the round keeps running behind it
+pub fn draw_tech_tree_ui(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
+    let tree = tech::tree();
+    let miner = state.active_miner();
+    
+    // Header with the gold there is to spend and the round timer
+    let header_rect = Rect::new(10.0, 10.0, state.layout.width - 20.0, 60.0);
+    draw_panel(ctx, theme, header_rect, theme.panel, 3.0)?;
+    
+    draw_header_text(ctx, theme, "Tech Tree", 30.0, 25.0, 24.0, theme.primary)?;
+    
+    let status = if state.game.rules.turn_based {
+        format!("Gold: {:.0}", miner.gold)
+    } else {
+        format!("Gold: {:.0}   {}s left", miner.gold, state.game.time_left().as_secs())
+    };
+    let status_text = Text::new(
+        TextFragment::new(status)
+            .scale(18.0)
+            .color(theme.gold)
+    );
+    
+    graphics::draw(
+        ctx,
+        &status_text,
+        DrawParam::default().dest([180.0, 30.0]),
+    )?;
+    
+    let tree_rect = Rect::new(10.0, 80.0, state.layout.width - 20.0, state.layout.height - 90.0);
+    draw_panel(ctx, theme, tree_rect, theme.panel, 3.0)?;
+    
+    let column_width = tech_tree_column_width(state);
+    for (column, branch) in Branch::ALL.iter().enumerate() {
+        let name = branch.name();
+        let branch_text = Text::new(
+            TextFragment::new(name)
+                .scale(16.0)
+                .color(theme.primary)
+        );
+        
+        let x = 20.0 + column as f32 * column_width + (column_width - name.len() as f32 * 8.0) / 2.0;
+        graphics::draw(
+            ctx,
+            &branch_text,
+            DrawParam::default().dest([x, 95.0]),
+        )?;
+    }
+    
+    // Prerequisite lines first so the nodes sit on top of them
+    for (index, node) in tree.nodes.iter().enumerate() {
+        let rect = tech_node_rect(state, index);
+        for &required in &node.prerequisites {
+            let from = tech_node_rect(state, required);
+            let color = if miner.has_researched(required) { theme.primary } else { theme.separator };
+            let line = MeshBuilder::new()
+                .line(
+                    &[[from.x + from.w / 2.0, from.bottom()], [rect.x + rect.w / 2.0, rect.y]],
+                    2.0,
+                    color
+                )?
+                .build(ctx)?;
+            
+            graphics::draw(ctx, &line, DrawParam::default())?;
+        }
+    }
+    
+    for (index, node) in tree.nodes.iter().enumerate() {
+        let id = ButtonId::TechNode(index);
+        let researched = miner.has_researched(index);
+        let color = if researched {
+            theme.primary
+        } else if !miner.can_research(index) {
+            theme.disabled
+        } else if miner.gold >= node.cost {
+            theme.accent
+        } else {
+            theme.secondary
+        };
+        
+        // Owned nodes can't be bought again but shouldn't look washed out
+        let rect = button_rect(state, id);
+        let node_state = if researched { ButtonState::Normal } else { button_state(state, id) };
+        draw_button(ctx, theme, rect, color, node_state)?;
+        draw_button_extras(state, ctx, id)?;
+        let face = button_face(rect, node_state);
+        let text_color = theme.text_on(color);
+        
+        // Long names break onto a second line
+        let name = if node.name.len() > 14 { node.name.replacen(' ', "\n", 1) } else { node.name.clone() };
+        let name_text = Text::new(
+            TextFragment::new(name)
+                .scale(13.0)
+                .color(text_color)
+        );
+        
+        graphics::draw(
+            ctx,
+            &name_text,
+            DrawParam::default().dest([face.x + 8.0, face.y + 5.0]),
+        )?;
+        
+        let price = if researched { "Owned".to_string() } else { format!("{:.0}g", node.cost) };
+        let price_text = Text::new(
+            TextFragment::new(price)
+                .scale(13.0)
+                .color(text_color)
+        );
+        
+        graphics::draw(
+            ctx,
+            &price_text,
+            DrawParam::default().dest([face.x + 8.0, face.bottom() - 18.0]),
+        )?;
+    }
+    
+    draw_tech_node_details(state, ctx)?;
+    
+    draw_button_with_text(
+        ctx,
+        theme,
+        button_rect(state, ButtonId::TechTree),
+        theme.primary,
+        "Close",
+        18.0,
+        button_state(state, ButtonId::TechTree)
+    )?;
+    draw_button_extras(state, ctx, ButtonId::TechTree)?;
+    
+    draw_theme_button(state, ctx)?;
+    
+    Ok(())
+}
+
+// What the node under the mouse (or keyboard focus) does, or the miner's current stats
+fn draw_tech_node_details(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
+    let tree = tech::tree();
+    let miner = state.active_miner();
+    
+    let details_rect = Rect::new(20.0, state.layout.height - 140.0, state.layout.width - 40.0, 110.0);
+    let details = MeshBuilder::new()
+        .rounded_rectangle(
+            DrawMode::fill(),
+            details_rect,
+            4.0,
+            theme.row_even
+        )?
+        .build(ctx)?;
+    
+    graphics::draw(ctx, &details, DrawParam::default())?;
+    
+    let (x, y) = state.mouse_position;
+    let selected = match state.button_at(x, y).or(state.focused_button) {
+        Some(ButtonId::TechNode(node)) => Some(node),
+        _ => None,
+    };
+    
+    let lines = match selected {
+        Some(index) => {
+            let node = &tree.nodes[index];
+            let requires: Vec<&str> = node.prerequisites.iter().map(|&i| tree.nodes[i].name.as_str()).collect();
+            let status = if miner.has_researched(index) {
+                "Researched"
+            } else if !miner.can_research(index) {
+                "Locked - research the requirements first"
+            } else if miner.gold >= node.cost {
+                "Available - click to research"
+            } else {
+                "Available - not enough gold yet"
+            };
+            
+            // A yield node digs the mine a level deeper, which changes how far swings stray
+            let effects = if node.branch == Branch::Yield && !miner.has_researched(index) {
+                let range = yields::table().range(miner.mine_level() + 1);
+                format!("{}, swings x{:.2}-{:.2}", node.effects.describe(), range.min, range.max)
+            } else {
+                node.effects.describe()
+            };
+            
+            vec![
+                (format!("{} ({}, {:.0}g)", node.name, node.branch.name(), node.cost), theme.primary),
+                (effects, theme.text),
+                (
+                    if requires.is_empty() { "No requirements".to_string() } else { format!("Requires {}", requires.join(", ")) },
+                    theme.text,
+                ),
+                (status.to_string(), theme.accent),
+            ]
+        },
+        None => {
+            let stats = miner.stats();
+            let range = yields::table().range(miner.mine_level());
+            vec![
+                ("Your miner".to_string(), theme.primary),
+                (
+                    format!(
+                        "{:.2} swings/s, {:.0}g per swing (x{:.2}-{:.2}), {:.0}% crits for x{:.1}",
+                        stats.mining_speed,
+                        stats.gold_per_swing,
+                        range.min,
+                        range.max,
+                        stats.crit_chance * 100.0,
+                        yields::table().crit_multiplier
+                    ),
+                    theme.text,
+                ),
+                (
+                    format!(
+                        "{:.0}% interest, +{:.0}% donation credit, -{} damage per round",
+                        stats.interest * 100.0,
+                        stats.donation_bonus * 100.0,
+                        stats.damage_reduction
+                    ),
+                    theme.text,
+                ),
+                ("Hover over a node to see what it does".to_string(), theme.secondary),
+            ]
+        },
+    };
+    
+    for (row, (line, color)) in lines.into_iter().enumerate() {
+        let line_text = Text::new(
+            TextFragment::new(line)
+                .scale(if row == 0 { 18.0 } else { 15.0 })
+                .color(color)
+        );
+        
+        graphics::draw(
+            ctx,
+            &line_text,
+            DrawParam::default().dest([details_rect.x + 15.0, details_rect.y + 10.0 + row as f32 * 24.0]),
+        )?;
+    }
+    
+    Ok(())
+}
+
+// Breaks text onto new lines between words so no line runs past `max_chars`
+fn wrap_words(text: &str, max_chars: usize) -> String {
+    let mut wrapped = String::new();
+    let mut line_len = 0;
+    for word in text.split_whitespace() {
+        if line_len > 0 && line_len + 1 + word.len() > max_chars {
+            wrapped.push('\n');
+            line_len = 0;
+        } else if line_len > 0 {
+            wrapped.push(' ');
+            line_len += 1;
+        }
+        wrapped.push_str(word);
+        line_len += word.len();
+    }
+    wrapped
+}
+
+// Perk cards are coloured by how rare their pool is
+fn perk_pool_color(theme: &Theme, pool: &str) -> Color {
+    match pool {
+        "epic" => theme.accent,
+        "rare" => theme.primary,
+        _ => theme.secondary,
+    }
+}
+
+// Every survivor picks one of their dealt perk cards before the round starts
+pub fn draw_draft_ui(state: &MainState, ctx: &mut Context) -> GameResult {
+    let theme = &state.theme;
+    let deck = perks::deck();
+    let game = &state.game;
+    let miner = state.active_miner();
+    
+    // Header with the time left to pick
+    let header_rect = Rect::new(10.0, 10.0, state.layout.width - 20.0, 60.0);
+    draw_panel(ctx, theme, header_rect, theme.panel, 3.0)?;
+    
+    draw_header_text(ctx, theme, "Perk Draft", 30.0, 25.0, 24.0, theme.primary)?;
+    
+    // Turn-based drafts wait as long as it takes
+    if !game.rules.turn_based {
+        let draft_progress = game.round_elapsed.as_secs_f32() / DRAFT_DURATION.as_secs_f32();
+        draw_progress_bar(ctx, theme, Rect::new(190.0, 30.0, 260.0, 20.0), draft_progress, theme.secondary)?;
+        
+        let time_text = Text::new(
+            TextFragment::new(format!("{}s", game.draft_time_left().as_secs()))
+                .scale(18.0)
+                .color(theme.text)
+        );
+        
+        graphics::draw(
+            ctx,
+            &time_text,
+            DrawParam::default().dest([460.0, 28.0]),
+        )?;
+    }
+    
+    draw_round_event(state, ctx, 505.0, 29.0)?;
+    
+    // What the pick is for, and what the round will bring
+    let intro_rect = Rect::new(10.0, 80.0, state.layout.width - 20.0, 70.0);
+    draw_panel(ctx, theme, intro_rect, theme.panel, 3.0)?;
+    
+    let prompt = if game.rules.is_hot_seat() {
+        format!("{}: {}, pick a card to keep for this round", game.round_label(), miner.name)
+    } else {
+        format!("{}: pick a card to keep for this round", game.round_label())
+    };
+    let event = match game.event {
+        Some(event) => format!("Round event: {} - {}", event.name(), event.description()),
+        None => "No round event this time".to_string(),
+    };
+    
+    for (row, (line, color)) in [(prompt, theme.text), (event, theme.accent)].into_iter().enumerate() {
+        let line_text = Text::new(
+            TextFragment::new(line)
+                .scale(16.0)
+                .color(color)
+        );
+        
+        graphics::draw(
+            ctx,
+            &line_text,
+            DrawParam::default().dest([30.0, 92.0 + row as f32 * 24.0]),
+        )?;
+    }
+    
+    let hand_rect = Rect::new(10.0, 160.0, state.layout.width - 20.0, 220.0);
+    draw_panel(ctx, theme, hand_rect, theme.panel, 3.0)?;
+    
+    if miner.is_drafting() {
+        for (i, &card) in miner.perk_offer.iter().enumerate() {
+            let id = ButtonId::PickPerk(i);
+            let perk = &deck.cards[card];
+            let color = perk_pool_color(theme, &perk.pool);
+            let card_state = button_state(state, id);
+            draw_button(ctx, theme, button_rect(state, id), color, card_state)?;
+            draw_button_extras(state, ctx, id)?;
+            let face = button_face(button_rect(state, id), card_state);
+            let text_color = theme.text_on(color);
+            
+            let lines = [
+                (perk.pool.to_uppercase(), 12.0, 12.0),
+                (wrap_words(&perk.name, 18), 20.0, 32.0),
+                (wrap_words(&perk.description, 24), 15.0, 90.0),
+            ];
+            for (line, size, y) in lines {
+                let line_text = Text::new(
+                    TextFragment::new(line)
+                        .scale(size)
+                        .color(text_color)
+                );
+                
+                graphics::draw(
+                    ctx,
+                    &line_text,
+                    DrawParam::default().dest([face.x + 14.0, face.y + y]),
+                )?;
+            }
+        }
+    } else {
+        let message = match miner.perk {
+            Some(card) => format!("You picked {}. Waiting for the others...", deck.cards[card].name),
+            None => "Waiting for the others to pick...".to_string(),
+        };
+        let message_text = Text::new(
+            TextFragment::new(message.as_str())
+                .scale(18.0)
+                .color(theme.text)
+        );
+        
+        graphics::draw(
+            ctx,
+            &message_text,
+            DrawParam::default().dest([(state.layout.width - message.len() as f32 * 9.0) / 2.0, 260.0]),
+        )?;
+    }
+    
+    // Everyone still in the match and how far along they are, two to a row
+    let picks_rect = Rect::new(10.0, 390.0, state.layout.width - 20.0, state.layout.height - 400.0);
+    draw_panel(ctx, theme, picks_rect, theme.panel, 3.0)?;
+    
+    draw_header_text(ctx, theme, "Picks", 30.0, 400.0, 22.0, theme.primary)?;
+    
+    let column_width = (state.layout.width - 40.0) / 2.0;
+    let survivors = game.miners.iter().filter(|miner| miner.alive);
+    for (i, other) in survivors.enumerate() {
+        let x = 30.0 + (i % 2) as f32 * column_width;
+        let y = 435.0 + (i / 2) as f32 * 24.0;
+        
+        let name_text = Text::new(
+            TextFragment::new(other.name.as_str())
+                .scale(16.0)
+                .color(other.color.map_or(theme.primary, |color| PLAYER_COLORS[color]))
+        );
+        
+        graphics::draw(
+            ctx,
+            &name_text,
+            DrawParam::default().dest([x, y]),
+        )?;
+        
+        let (pick, color) = match other.perk {
+            Some(card) => (deck.cards[card].name.clone(), theme.text),
+            None if other.is_drafting() => ("choosing...".to_string(), theme.secondary),
+            None => ("-".to_string(), theme.text),
+        };
+        let pick_text = Text::new(
+            TextFragment::new(pick)
+                .scale(16.0)
+                .color(color)
+        );
+        
+        graphics::draw(
+            ctx,
+            &pick_text,
+            DrawParam::default().dest([x + 130.0, y]),
+        )?;
+    }
+    
+    draw_theme_button(state, ctx)?;
+    
     Ok(())
 }
@@ -0,0 +1,178 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::OnceLock;
+
+pub const YIELDS_FILE: &str = "yields.toml";
+
+// Shipped yield table, used when there is no `yields.toml` next to the executable or it
+// is broken. Everyone in a network match needs the same table.
+const BUILTIN_YIELDS: &str = include_str!("../yields.toml");
+
+// Multipliers a swing's roll is drawn between
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct YieldRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl YieldRange {
+    pub fn mean(&self) -> f32 {
+        (self.min + self.max) / 2.0
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct YieldTable {
+    pub base_crit_chance: f32, // Every miner's crit chance before research and perks
+    pub crit_multiplier: f32,
+    #[serde(rename = "level")]
+    levels: Vec<YieldRange>, // Indexed by mine level
+}
+
+impl YieldTable {
+    fn parse(contents: &str) -> Result<YieldTable, String> {
+        let table: YieldTable = toml::from_str(contents).map_err(|e| e.to_string())?;
+
+        if table.levels.is_empty() {
+            return Err("there has to be at least one [[level]]".to_string());
+        }
+        for (level, range) in table.levels.iter().enumerate() {
+            if range.min < 0.0 || range.max < range.min {
+                return Err(format!("level {} needs 0 <= min <= max", level));
+            }
+        }
+        if table.crit_multiplier < 1.0 {
+            return Err("`crit_multiplier` can't be below 1".to_string());
+        }
+        if !(0.0..=1.0).contains(&table.base_crit_chance) {
+            return Err("`base_crit_chance` has to be between 0 and 1".to_string());
+        }
+
+        Ok(table)
+    }
+
+    // Loads the table from disk, falling back to the shipped one if it is missing or broken
+    fn load() -> YieldTable {
+        let contents = match fs::read_to_string(YIELDS_FILE) {
+            Ok(contents) => contents,
+            Err(_) => return YieldTable::parse(BUILTIN_YIELDS).expect("embedded yield table is valid"),
+        };
+
+        YieldTable::parse(&contents).unwrap_or_else(|message| {
+            eprintln!("Ignoring {}: {}", YIELDS_FILE, message);
+            YieldTable::parse(BUILTIN_YIELDS).expect("embedded yield table is valid")
+        })
+    }
+
+    // Deeper mines than the table lists use its last level
+    pub fn range(&self, mine_level: usize) -> YieldRange {
+        self.levels[mine_level.min(self.levels.len() - 1)]
+    }
+
+    // What crits add to swings on average, as a multiplier
+    pub fn crit_factor(&self, crit_chance: f32) -> f32 {
+        1.0 + crit_chance.clamp(0.0, 1.0) * (self.crit_multiplier - 1.0)
+    }
+
+    // What a swing brings up on average, crits included, as a share of the miner's gold per swing
+    pub fn expected_factor(&self, mine_level: usize, crit_chance: f32) -> f32 {
+        self.range(mine_level).mean() * self.crit_factor(crit_chance)
+    }
+
+    // Draws a roll from the mine level's range
+    pub fn roll<R: Rng>(&self, mine_level: usize, rng: &mut R) -> f32 {
+        let range = self.range(mine_level);
+        if range.max <= range.min {
+            return range.min;
+        }
+        rng.gen_range(range.min..range.max)
+    }
+
+    // Rolls one swing or strike: its multiplier and whether it was a crit
+    pub fn roll_swing<R: Rng>(&self, mine_level: usize, crit_chance: f32, rng: &mut R) -> (f32, bool) {
+        let roll = self.roll(mine_level, rng);
+        if rng.gen::<f32>() < crit_chance {
+            (roll * self.crit_multiplier, true)
+        } else {
+            (roll, false)
+        }
+    }
+}
+
+// The yield table every swing rolls from, loaded on first use
+pub fn table() -> &'static YieldTable {
+    static TABLE: OnceLock<YieldTable> = OnceLock::new();
+    TABLE.get_or_init(YieldTable::load)
+}
+
+// Seed of one miner's rolls in a match
+pub fn miner_seed(game_seed: u64, miner_index: usize) -> u64 {
+    game_seed ^ (miner_index as u64 + 1).wrapping_mul(0xA24B_AED4_963E_E407)
+}
+
+// Generator for a miner's nth roll. Every roll comes from the match seed, so a match with
+// a fixed seed mines the same whoever runs it, and a client's copy can't drift from the server's.
+pub fn roll_rng(miner_seed: u64, roll: u64) -> StdRng {
+    StdRng::seed_from_u64(miner_seed ^ roll.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builtin() -> YieldTable {
+        YieldTable::parse(BUILTIN_YIELDS).unwrap()
+    }
+
+    // The first `count` rolls of one miner in a match
+    fn rolls(table: &YieldTable, game_seed: u64, miner_index: usize, count: u64) -> Vec<(f32, bool)> {
+        let seed = miner_seed(game_seed, miner_index);
+        (0..count).map(|roll| table.roll_swing(2, 0.3, &mut roll_rng(seed, roll))).collect()
+    }
+
+    #[test]
+    fn rolls_stay_in_their_level_range() {
+        let table = builtin();
+        for level in 0..table.levels.len() + 2 {
+            let range = table.range(level);
+            let mut rng = roll_rng(9, level as u64);
+            for _ in 0..200 {
+                let roll = table.roll(level, &mut rng);
+                assert!(roll >= range.min && roll <= range.max, "level {} rolled {}", level, roll);
+            }
+        }
+        // Past the last level the last range holds
+        assert_eq!(table.range(table.levels.len() + 5).max, table.levels.last().unwrap().max);
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_rolls_and_crits() {
+        let table = builtin();
+        assert_eq!(rolls(&table, 42, 0, 50), rolls(&table, 42, 0, 50));
+        assert_ne!(rolls(&table, 42, 0, 50), rolls(&table, 42, 1, 50));
+        assert_ne!(rolls(&table, 42, 0, 50), rolls(&table, 43, 0, 50));
+    }
+
+    #[test]
+    fn a_crit_multiplies_the_roll() {
+        let table = builtin();
+        let roll = table.roll(1, &mut roll_rng(3, 0));
+        assert_eq!(table.roll_swing(1, 1.0, &mut roll_rng(3, 0)), (roll * table.crit_multiplier, true));
+        assert_eq!(table.roll_swing(1, 0.0, &mut roll_rng(3, 0)), (roll, false));
+        assert_eq!(table.crit_factor(0.5), 1.0 + 0.5 * (table.crit_multiplier - 1.0));
+    }
+
+    #[test]
+    fn broken_tables_are_refused() {
+        let level = "[[level]]\nmin = 0.9\nmax = 1.1\n";
+        assert!(YieldTable::parse(&format!("base_crit_chance = 0.02\ncrit_multiplier = 2.0\n{}", level)).is_ok());
+        assert!(YieldTable::parse("base_crit_chance = 0.02\ncrit_multiplier = 2.0\n").is_err());
+        assert!(YieldTable::parse(&format!("base_crit_chance = 0.02\ncrit_multiplier = 0.5\n{}", level)).is_err());
+        assert!(YieldTable::parse(&format!("base_crit_chance = 2.0\ncrit_multiplier = 2.0\n{}", level)).is_err());
+        assert!(YieldTable::parse("base_crit_chance = 0.02\ncrit_multiplier = 2.0\n[[level]]\nmin = 1.2\nmax = 1.1\n").is_err());
+    }
+}